@@ -1,4 +1,4 @@
-pub static ADJECTIVES: [&'static str; 200] = [
+pub static ADJECTIVES: [&str; 200] = [
     "attractive",
     "bald",
     "beautiful",
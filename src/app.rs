@@ -1,379 +1,3005 @@
 //! Application code.
 
-use crate::cli::{Cli, InitArgs, WindowAddArgs, WindowRemoveArgs};
-use crate::conf::{Config, WindowConf};
-use crate::tmux;
+use crate::cli::{
+    AttachArgs, Cli, ConfigDumpArgs, ConfigWriteArgs, DownArgs, InitArgs, InitField, LogsArgs,
+    NukeArgs, PopupArgs, RestartArgs, RestoreArgs, SaveArgs, SendArgs, StatusArgs, UpArgs,
+    WaitSpec, WindowAddArgs, WindowListArgs, WindowMoveArgs, WindowRemoveArgs, WindowShowArgs,
+    WindowSort, WindowToggleArgs,
+};
+use crate::conf::{self, Config, ConfigDocument, WindowConf};
+use crate::error::{Result, SeshError};
+use crate::format::Format;
+use crate::snapshot::{self, PaneSnapshot, Snapshot, WindowSnapshot};
+use crate::templates;
+use crate::tmux::{self, AttachOptions, RealTmuxBackend, TmuxBackend, WindowTarget};
 use crate::words::rand_phrase;
-use anyhow::{Result, anyhow};
+use serde::Serialize;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Machine-readable result of `up`, `down` and `status`, printed with `--json`.
+#[derive(Debug, Default, Serialize)]
+struct SessionReport {
+    session: String,
+    /// The config's `description`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    /// Whether the session is running once the command finishes
+    running: bool,
+    /// Set by `up`: whether the session had to be created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    created: Option<bool>,
+    /// Set by `down`: whether a running session was killed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    killed: Option<bool>,
+    windows: Vec<WindowReport>,
+}
 
-pub fn run_init(cli: &Cli, args: &InitArgs) -> Result<()> {
-    if cli.config_file_exists() && !args.overwrite {
-        return Err(anyhow!(
-            "Config file {:?} already exists. To overwrite, pass --overwrite.",
-            cli.config
-        ));
-    }
+/// Result of `init`, printed with `--json`.
+#[derive(Debug, Serialize)]
+struct InitReport<'a> {
+    /// As from [`conf::display_config_path`]
+    config: String,
+    name: &'a str,
+}
 
-    let name = match args.name.as_ref() {
-        Some(n) => n.clone(),
-        None => rand_phrase(None, None)?,
-    };
-    let conf = Config {
-        name,
-        ..Default::default()
-    };
+#[derive(Debug, Serialize)]
+struct WindowReport {
+    name: Option<String>,
+    state: WindowState,
+}
 
-    conf.write(&cli.config)?;
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum WindowState {
+    /// `up` created the window
+    Created,
+    /// `up` found the window already running
+    Existing,
+    /// `status` found the window running
+    Running,
+    /// `status` didn't find the window
+    Missing,
+    /// `status` didn't find the window, which is disabled in the config
+    Disabled,
+    /// `up --rebuild-dead` re-sent the command to a window left at a shell
+    Rebuilt,
+    /// `up` failed to create or set up the window
+    Failed,
+}
 
-    if !cli.quiet {
-        println!("Wrote config file to {:?}", &cli.config);
+impl std::fmt::Display for WindowState {
+    /// Same as the JSON name, e.g. `running`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Created => "created",
+            Self::Existing => "existing",
+            Self::Running => "running",
+            Self::Missing => "missing",
+            Self::Disabled => "disabled",
+            Self::Rebuilt => "rebuilt",
+            Self::Failed => "failed",
+        };
+        f.write_str(name)
     }
-    Ok(())
 }
 
-/// Check to see if the session is running and if
-/// each of the session's windows are running.
-pub fn run_status(cli: &Cli) -> Result<()> {
-    tmux::check_tmux_available()?;
-
-    let config = Config::load(&cli.config)?;
-
-    let session_exists = tmux::has_session(&config.name)?;
+/// Build the real tmux backend from the global CLI options.
+fn real_backend(cli: &Cli) -> RealTmuxBackend {
+    RealTmuxBackend::with_retries(cli.retries).with_bin(&cli.tmux_bin)
+}
 
-    if !session_exists {
-        if !cli.quiet {
-            println!("Session '{}' is NOT running", config.name);
-        }
-        return Ok(());
+/// Make sure tmux is installed, warning if it's older than
+/// the minimum supported version.
+fn check_tmux<T: TmuxBackend>(cli: &Cli, backend: &T) -> Result<()> {
+    backend.check_available()?;
+
+    // A version we can't parse shouldn't stop sesh from working
+    if let Ok(version) = backend.version()
+        && version < tmux::MIN_VERSION
+        && !cli.quiet
+    {
+        eprintln!(
+            "Warning: tmux {} is older than the minimum supported version ({})",
+            tmux::format_version(&version),
+            tmux::format_version(&tmux::MIN_VERSION)
+        );
     }
 
-    if !cli.quiet {
-        println!("Session '{}' is running", config.name);
-
-        let running_windows = tmux::list_windows(&config.name)?;
+    Ok(())
+}
 
-        if config.window.is_empty() {
-            println!("  No windows configured");
-        } else {
-            println!("  Windows:");
-            for (idx, window_conf) in config.window.iter().enumerate() {
-                let window_name = window_conf
-                    .name
-                    .as_ref()
-                    .map(|s| s.as_str())
-                    .unwrap_or("unnamed");
-
-                // Check if this window is running (by name or index)
-                let is_running = running_windows.iter().any(|w| w == window_name)
-                    || (idx < running_windows.len() && window_name == "unnamed");
-
-                let status = if is_running { "✓" } else { "✗" };
-                println!("    {} {}", status, window_name);
-            }
-        }
+/// Wrap text in an ANSI color code if color is enabled.
+fn paint(text: &str, code: &str, enabled: bool) -> String {
+    if enabled {
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    } else {
+        text.to_string()
     }
+}
 
-    Ok(())
+fn green(text: &str, enabled: bool) -> String {
+    paint(text, "32", enabled)
 }
 
-/// Ensure the session + windows are running.
-pub fn run_up(cli: &Cli) -> Result<()> {
-    tmux::check_tmux_available()?;
+fn red(text: &str, enabled: bool) -> String {
+    paint(text, "31", enabled)
+}
 
-    let config = Config::load(&cli.config)?;
+/// Print a yes/no prompt and read the answer from `input`.
+///
+/// Anything other than `y`/`yes` counts as "no".
+fn confirm<R: BufRead>(prompt: &str, input: &mut R) -> Result<bool> {
+    print!("{}", prompt);
+    std::io::stdout().flush()?;
 
-    // Check if session already exists
-    let session_exists = tmux::has_session(&config.name)?;
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
 
-    if !session_exists {
-        // Create new session (detached)
-        tmux::new_session(&config.name, true)?;
+    let answer = answer.trim().to_lowercase();
+    Ok(answer == "y" || answer == "yes")
+}
 
-        if !cli.quiet {
-            println!("Created session '{}'", config.name);
-        }
+/// Load the config file for a `window` command to edit, refusing the
+/// global `default.toml` fallback.
+fn editable_config(cli: &Cli) -> Result<ConfigDocument> {
+    if cli.global_config {
+        return Err(SeshError::ConfigWrite(format!(
+            "{} is the global config, used because there's no project config. \
+             Run `sesh init` to create one, or pass --config to edit it anyway.",
+            conf::display_config_path(&cli.config)
+        )));
     }
+    ConfigDocument::load(&cli.config, cli.config_format)
+}
 
-    // Get list of existing windows
-    let existing_windows = if session_exists {
-        tmux::list_windows(&config.name)?
-    } else {
-        // A new session always has one default window (index 0)
-        vec![]
-    };
+/// Load the config file, with the `--overlay` file merged on top.
+fn load_config(cli: &Cli) -> Result<Config> {
+    Config::load_with_overlay(&cli.config, cli.overlay.as_ref(), cli.config_format)
+}
 
-    // Create windows from config
-    for (idx, window_conf) in config.window.iter().enumerate() {
-        let window_name = window_conf.name.as_deref();
+/// Session environment variable recording the config file a session
+/// was started from, to catch other configs using the same `name`.
+const CONFIG_ENV: &str = "SESH_CONFIG";
+
+/// If `session` was started from a different config file than the
+/// current one, e.g. another project's config with the same `name`,
+/// return that file's path.
+fn other_config<T: TmuxBackend>(cli: &Cli, backend: &T, session: &str) -> Result<Option<String>> {
+    let current = conf::display_config_path(&cli.config);
+    Ok(backend
+        .show_environment(session, CONFIG_ENV)?
+        .filter(|recorded| *recorded != current))
+}
 
-        // Check if window already exists
-        let window_exists = if let Some(name) = window_name {
-            existing_windows.iter().any(|w| w == name)
-        } else {
-            false
-        };
+/// Warn if `session` was started from a different config file.
+fn warn_other_config<T: TmuxBackend>(cli: &Cli, backend: &T, session: &str) -> Result<()> {
+    if let Some(other) = other_config(cli, backend, session)?
+        && !cli.quiet
+    {
+        eprintln!(
+            "Warning: session '{}' was started from another config ({}); \
+             is another project using the same session name?",
+            session, other
+        );
+    }
+    Ok(())
+}
 
-        if window_exists {
-            if !cli.quiet {
-                println!("  Window '{}' already exists", window_name.unwrap());
-            }
-            continue;
-        }
+/// Get the tmux session name to use, preferring the
+/// `--session-name` override over the config's `name`,
+/// with any `--prefix` prepended.
+fn session_name(cli: &Cli, config: &Config) -> String {
+    let name = cli.session_name.as_deref().unwrap_or(&config.name);
+    format!("{}{}", cli.prefix.as_deref().unwrap_or_default(), name)
+}
 
-        // For the first window, we need to handle it differently
-        if idx == 0 && !session_exists {
-            // The session was just created with a default window at index 0
-            // Rename it to match our config
-            if let Some(name) = window_name {
-                tmux::rename_window(&config.name, idx, name)?;
-                if !cli.quiet {
-                    println!("  Renamed window 0 to '{}'", name);
-                }
-            }
+/// Check a command's `--tag` filter against the config, saying so if
+/// it skips the session. With only one config there's nothing else to
+/// pick from, so a mismatch just makes the command a no-op.
+fn tag_matches(cli: &Cli, config: &Config, tag: Option<&str>) -> bool {
+    let Some(tag) = tag else {
+        return true;
+    };
+    if config.has_tag(tag) {
+        return true;
+    }
 
-            // Execute command if specified
-            if let Some(command) = &window_conf.command {
-                tmux::send_keys(&config.name, idx, command)?;
+    // On stderr, so it can't end up in `--json` output
+    if !cli.quiet {
+        eprintln!(
+            "Skipping session '{}' (not tagged '{}')",
+            session_name(cli, config),
+            tag
+        );
+    }
+    false
+}
 
-                if !cli.quiet {
-                    let name = window_name.unwrap_or("window 0");
-                    println!("  Executed command in {}", name);
-                }
-            }
-        } else {
-            // Create new window - let tmux auto-assign the index
-            tmux::new_window(&config.name, window_name, None)?;
+/// How many random names to try before giving up.
+const NAME_ATTEMPTS: usize = 5;
+
+/// Generate a random session name, retrying if `taken` says it's
+/// already in use.
+fn rand_session_name<F>(taken: F) -> Result<String>
+where
+    F: Fn(&str) -> bool,
+{
+    for _ in 0..NAME_ATTEMPTS {
+        let name = rand_phrase(None, None)?;
+        if !taken(&name) {
+            return Ok(name);
+        }
+    }
+    Err(SeshError::Other(format!(
+        "Couldn't find an unused session name in {} attempts. Pass --name to choose one.",
+        NAME_ATTEMPTS
+    )))
+}
 
-            if !cli.quiet {
-                let default_name = format!("window {}", idx);
-                let name = window_name.unwrap_or(&default_name);
-                println!("  Created window '{}'", name);
-            }
+pub fn run_init(cli: &Cli, args: &InitArgs) -> Result<()> {
+    run_init_with_backend(cli, args, &real_backend(cli))
+}
 
-            // Execute command if specified
-            if let Some(command) = &window_conf.command {
-                tmux::send_keys(&config.name, idx, command)?;
+fn run_init_with_backend<T: TmuxBackend>(cli: &Cli, args: &InitArgs, backend: &T) -> Result<()> {
+    if cli.config_file_exists() && !args.overwrite {
+        return Err(SeshError::ConfigExists(cli.config.clone()));
+    }
 
-                if !cli.quiet {
-                    let default_name = format!("window {}", idx);
-                    let name = window_name.unwrap_or(&default_name);
-                    println!("  Executed command in {}", name);
-                }
-            }
+    let name = match args.name.as_ref() {
+        Some(n) => n.clone(),
+        None => {
+            // Avoid names already used by running sessions. If tmux isn't
+            // installed (or no server is running) there's nothing to avoid.
+            let sessions = backend
+                .check_available()
+                .and_then(|_| backend.list_sessions())
+                .unwrap_or_default();
+            rand_session_name(|name| sessions.iter().any(|s| s == name))?
         }
-    }
+    };
+    let window = match args.template {
+        Some(template) => templates::windows(template),
+        None => templates::sample(&name),
+    };
+    let conf = Config {
+        name,
+        window,
+        ..Default::default()
+    };
 
-    if !cli.quiet {
-        println!("Session '{}' is up", config.name);
-    }
+    conf.write(&cli.config, cli.config_format)?;
 
+    if args.json {
+        let report = InitReport {
+            config: conf::display_config_path(&cli.config),
+            name: &conf.name,
+        };
+        println!("{}", serde_json::to_string(&report)?);
+    } else if let Some(field) = args.print {
+        if !cli.quiet {
+            eprintln!(
+                "Wrote config file to {}",
+                conf::display_config_path(&cli.config)
+            );
+        }
+        println!("{}", init_field(field, &cli.config, &conf.name));
+    } else if !cli.quiet {
+        println!(
+            "Wrote config file to {}",
+            conf::display_config_path(&cli.config)
+        );
+    }
     Ok(())
 }
 
-/// Kill the session + windows.
-pub fn run_down(cli: &Cli) -> Result<()> {
-    tmux::check_tmux_available()?;
-
-    let config = Config::load(&cli.config)?;
+/// The value `init --print` outputs. The path is absolute, since
+/// `--cwd` may have moved sesh away from where it was run.
+fn init_field(field: InitField, config: &Path, name: &str) -> String {
+    match field {
+        InitField::Name => name.to_string(),
+        InitField::Path => conf::display_config_path(config),
+    }
+}
 
-    // Check if session exists
-    let session_exists = tmux::has_session(&config.name)?;
+/// Check to see if the session is running and if
+/// each of the session's windows are running.
+///
+/// With `--watch`, the status is re-rendered every
+/// `--interval` seconds until interrupted. With `--quiet`,
+/// nothing is printed and the exit code says whether
+/// everything is running.
+pub fn run_status(cli: &Cli, args: &StatusArgs) -> Result<()> {
+    run_status_with_backend(cli, args, &real_backend(cli))
+}
 
-    if !session_exists {
-        if !cli.quiet {
-            println!("Session '{}' is not running", config.name);
+fn run_status_with_backend<T: TmuxBackend>(
+    cli: &Cli,
+    args: &StatusArgs,
+    backend: &T,
+) -> Result<()> {
+    check_tmux(cli, backend)?;
+
+    let config = load_config(cli)?;
+    if !tag_matches(cli, &config, args.tag.as_deref()) {
+        // With `--quiet` only the exit code is seen, and a skipped
+        // session mustn't look like a running one
+        if cli.quiet {
+            return Err(SeshError::NotTagged {
+                session: session_name(cli, &config),
+                tag: args.tag.clone().unwrap_or_default(),
+            });
         }
         return Ok(());
     }
 
-    // Kill the session
-    tmux::kill_session(&config.name)?;
+    if cli.quiet && !args.json && args.format.is_none() && !args.watch {
+        return check_status(cli, &config, args.window.as_deref(), backend);
+    }
 
-    if !cli.quiet {
-        println!("Killed session '{}'", config.name);
+    if !args.watch {
+        return render_status(cli, args, &config, backend);
     }
 
-    Ok(())
+    // Runs until the user hits Ctrl-C
+    watch_status(cli, args, config, backend, || {
+        std::thread::sleep(Duration::from_secs(args.interval));
+        true
+    })
 }
 
-/// Ensure the session + windows are running and
-/// attach to the session.
-pub fn run_attach(cli: &Cli) -> Result<()> {
-    tmux::check_tmux_available()?;
+/// Render the status, then re-render it each time `wait` returns true,
+/// re-loading the config in between so changes show up. With `--json`,
+/// one JSON line is printed per render instead of clearing the screen.
+fn watch_status<T: TmuxBackend>(
+    cli: &Cli,
+    args: &StatusArgs,
+    mut config: Config,
+    backend: &T,
+    mut wait: impl FnMut() -> bool,
+) -> Result<()> {
+    loop {
+        if !args.json && args.format.is_none() {
+            // Clear the screen and move the cursor home
+            print!("\x1b[2J\x1b[H");
+        }
+        render_status(cli, args, &config, backend)?;
+        std::io::stdout().flush()?;
+        if !wait() {
+            return Ok(());
+        }
+        config = load_config(cli)?;
+    }
+}
+
+/// Print the session's status and each of its windows.
+fn render_status<T: TmuxBackend>(
+    cli: &Cli,
+    args: &StatusArgs,
+    config: &Config,
+    backend: &T,
+) -> Result<()> {
+    let (report, info) = status_report(cli, config, args.window.as_deref(), backend)?;
+
+    if args.json {
+        println!("{}", serde_json::to_string(&report)?);
+        return Ok(());
+    }
+
+    if let Some(format) = &args.format {
+        for (idx, window) in report.windows.iter().enumerate() {
+            println!("{}", format_window(format, &report, idx, window));
+        }
+        return Ok(());
+    }
 
-    // First, ensure the session is up
-    run_up(cli)?;
+    if cli.quiet {
+        return Ok(());
+    }
 
-    // Load config to get session name and default window
-    let config = Config::load(&cli.config)?;
+    let color = cli.use_color();
 
-    // Find the default window if specified
-    let default_window = config.window.iter()
-        .find(|w| w.default == Some(true))
-        .and_then(|w| w.name.as_ref());
+    if !report.running {
+        let header = format!("Session '{}' is NOT running", report.session);
+        println!("{}", red(&header, color));
+        print_description(&report);
+        return Ok(());
+    }
+
+    let mut header = format!("Session '{}' is running", report.session);
+    if args.show_age
+        && let Some(info) = &info
+    {
+        header.push_str(&format!(" (up {})", format_age(session_age(info.created))));
+    }
+    println!("{}", green(&header, color));
+    print_description(&report);
 
-    // Attach to the session (this will block until user detaches)
-    if let Some(window_name) = default_window {
-        tmux::attach_session_with_window(&config.name, window_name)?;
+    if report.windows.is_empty() {
+        // Nothing was configured, so tmux is just running its default shell
+        println!("  No windows configured (only the default tmux window is open)");
     } else {
-        tmux::attach_session(&config.name)?;
+        println!("  Windows:");
+        for window in &report.windows {
+            let name = window.name.as_deref().unwrap_or("unnamed");
+            match window.state {
+                WindowState::Running => println!("    {} {}", green("✓", color), name),
+                WindowState::Disabled => println!("    - {} (disabled)", name),
+                _ => println!("    {} {}", red("✗", color), name),
+            }
+        }
     }
 
     Ok(())
 }
 
-/// Kill and re-start the session.
-///
-/// Shorthand for running `down` and then `up`.
-pub fn run_restart(cli: &Cli) -> Result<()> {
-    run_down(&cli)?;
-    run_up(&cli)?;
-    Ok(())
+/// Print the config's `description`, if it has one, under the
+/// session header.
+fn print_description(report: &SessionReport) {
+    if let Some(description) = &report.description {
+        println!("  {}", description);
+    }
 }
 
-/// Add a window to the session config.
-pub fn run_window_add(cli: &Cli, args: &WindowAddArgs) -> Result<()> {
-    let mut config = Config::load(&cli.config)?;
+/// How long a session created at `created` (seconds since
+/// the epoch) has been running.
+fn session_age(created: u64) -> Duration {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    now.saturating_sub(Duration::from_secs(created))
+}
 
-    // Build command vector from cmd + args
-    let mut command = vec![args.cmd.clone()];
-    command.extend(args.args.clone());
+/// Format a duration with its two largest units, like `2h 13m`.
+fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    let (days, hours, mins) = (secs / 86400, secs / 3600 % 24, secs / 60 % 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, mins)
+    } else if mins > 0 {
+        format!("{}m", mins)
+    } else {
+        format!("{}s", secs)
+    }
+}
 
-    // Create window config
-    let window_conf = WindowConf {
-        name: args.name.clone(),
-        command: Some(command),
-        default: None,
-    };
+/// Render a `status --format` line for one of the report's windows.
+fn format_window(
+    format: &Format,
+    report: &SessionReport,
+    idx: usize,
+    window: &WindowReport,
+) -> String {
+    format.render(|token| match token {
+        "session" => report.session.clone(),
+        "name" => window.name.clone().unwrap_or_default(),
+        "index" => idx.to_string(),
+        "state" => window.state.to_string(),
+        "running" => (window.state == WindowState::Running).to_string(),
+        "window_count" => report.windows.len().to_string(),
+        _ => unreachable!("token '{}' isn't in STATUS_TOKENS", token),
+    })
+}
 
-    // Add to config
-    config.window.push(window_conf);
+/// Check if a configured window is running, by name or,
+/// for unnamed windows, by index.
+fn window_running(idx: usize, window_conf: &WindowConf, running_windows: &[String]) -> bool {
+    match window_conf.name.as_deref() {
+        Some(name) => running_windows.iter().any(|w| w == name),
+        None => idx < running_windows.len() || running_windows.iter().any(|w| w == "unnamed"),
+    }
+}
 
-    // Write updated config
-    config.write(&cli.config)?;
+/// The names of a running session's windows, in index order.
+fn window_names(info: &tmux::SessionInfo) -> Vec<String> {
+    info.windows.iter().map(|w| w.name.clone()).collect()
+}
 
-    if !cli.quiet {
-        let name = args.name.as_ref().map(|s| s.as_str()).unwrap_or("unnamed");
-        println!("Added window '{}' to config", name);
+/// The config `status` checks, keeping only the `--window` window if
+/// one was given. Like `up --only`, naming a disabled window checks it
+/// like any other.
+fn status_config(config: &Config, window: Option<&str>) -> Result<Config> {
+    let mut config = config.clone();
+    if let Some(name) = window {
+        config.window.retain(|w| w.name.as_deref() == Some(name));
+        if config.window.is_empty() {
+            return Err(SeshError::WindowNotInConfig(name.to_string()));
+        }
+        config.window[0].disabled = None;
     }
-
-    Ok(())
+    Ok(expanded(config))
 }
 
-/// Remove a window from the session config
-pub fn run_window_remove(cli: &Cli, args: &WindowRemoveArgs) -> Result<()> {
-    let mut config = Config::load(&cli.config)?;
+/// The config's windows that aren't disabled, which `up` starts.
+fn enabled_windows(config: &Config) -> impl Iterator<Item = &WindowConf> {
+    config.window.iter().filter(|w| w.disabled != Some(true))
+}
 
-    if let Some(name) = &args.name {
-        // Find and remove window by name
-        let initial_len = config.window.len();
-        config
-            .window
-            .retain(|w| w.name.as_ref().map(|n| n != name).unwrap_or(true));
+/// The config with its windows' `count`s expanded, to match against the
+/// windows running in the session.
+fn expanded(mut config: Config) -> Config {
+    config.expand_counts();
+    config
+}
 
-        if config.window.len() == initial_len {
-            return Err(anyhow!("Window '{}' not found in config", name));
+/// Check that the session and all its windows are running, without
+/// building a report. Used by `status --quiet`, where only the exit
+/// code matters.
+fn check_status<T: TmuxBackend>(
+    cli: &Cli,
+    config: &Config,
+    window: Option<&str>,
+    backend: &T,
+) -> Result<()> {
+    let config = status_config(config, window)?;
+    let session = &session_name(cli, &config);
+
+    // A `--window` (and its copies, with `count`) is looked up by name,
+    // without listing the rest of the session
+    if window.is_some() {
+        if !backend.has_session(session)? {
+            return Err(SeshError::SessionNotRunning(session.to_string()));
+        }
+        for window_conf in enabled_windows(&config) {
+            let name = window_conf.name.clone().unwrap_or_default();
+            if !backend.has_window(session, &name)? {
+                return Err(SeshError::WindowNotRunning {
+                    session: session.to_string(),
+                    window: name,
+                });
+            }
         }
+        return Ok(());
+    }
 
-        // Write updated config
-        config.write(&cli.config)?;
+    let Some(info) = backend.session_info(session)? else {
+        return Err(SeshError::SessionNotRunning(session.to_string()));
+    };
 
-        if !cli.quiet {
-            println!("Removed window '{}' from config", name);
+    let running_windows = window_names(&info);
+    for (idx, window_conf) in enabled_windows(&config).enumerate() {
+        if !window_running(idx, window_conf, &running_windows) {
+            return Err(SeshError::WindowNotRunning {
+                session: session.to_string(),
+                window: window_conf.name.clone().unwrap_or_default(),
+            });
         }
-    } else {
-        return Err(anyhow!("Must specify --name to remove a window"));
     }
 
     Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::tmux::{MockTmuxBackend, TmuxBackend};
-    use tempfile::TempDir;
-
-    // Helper functions for testing that accept a backend parameter
-
-    fn run_status_with_backend<T: TmuxBackend>(cli: &Cli, backend: &T) -> Result<()> {
-        backend.check_available()?;
+/// Check whether the session and each configured window are running,
+/// returning the session's info too if it is.
+fn status_report<T: TmuxBackend>(
+    cli: &Cli,
+    config: &Config,
+    window: Option<&str>,
+    backend: &T,
+) -> Result<(SessionReport, Option<tmux::SessionInfo>)> {
+    let config = status_config(config, window)?;
+    let session = &session_name(cli, &config);
+
+    let info = backend.session_info(session)?;
+    let running_windows = info.as_ref().map(window_names).unwrap_or_default();
+
+    // Unnamed windows are matched by their position among the enabled
+    // windows, which is where `up` put them. Disabled ones are still
+    // reported running if they were started anyway, e.g. with `up --only`.
+    let mut position = 0;
+    let windows = config
+        .window
+        .iter()
+        .map(|window_conf| {
+            let running = if window_conf.disabled == Some(true) {
+                let name = window_conf.name.as_ref();
+                name.is_some_and(|name| running_windows.contains(name))
+            } else {
+                position += 1;
+                window_running(position - 1, window_conf, &running_windows)
+            };
+            WindowReport {
+                name: window_conf.name.clone(),
+                state: match (info.is_some() && running, window_conf.disabled) {
+                    (true, _) => WindowState::Running,
+                    (false, Some(true)) => WindowState::Disabled,
+                    (false, _) => WindowState::Missing,
+                },
+            }
+        })
+        .collect();
 
-        let config = Config::load(&cli.config)?;
-        let session_exists = backend.has_session(&config.name)?;
+    let report = SessionReport {
+        session: session.to_string(),
+        description: config.description.clone(),
+        running: info.is_some(),
+        windows,
+        ..Default::default()
+    };
+    Ok((report, info))
+}
 
-        if !session_exists {
-            return Ok(());
-        }
+/// Ensure the session + windows are running.
+pub fn run_up(cli: &Cli, args: &UpArgs) -> Result<()> {
+    run_up_with_backend(cli, args, &real_backend(cli))?;
+    Ok(())
+}
 
-        let _running_windows = backend.list_windows(&config.name)?;
-        Ok(())
+fn run_up_with_backend<T: TmuxBackend>(
+    cli: &Cli,
+    args: &UpArgs,
+    backend: &T,
+) -> Result<SessionReport> {
+    check_tmux(cli, backend)?;
+
+    let config = up_config(cli, args)?;
+    if !tag_matches(cli, &config, args.tag.as_deref()) {
+        return Ok(SessionReport {
+            session: session_name(cli, &config),
+            ..Default::default()
+        });
     }
 
-    fn run_up_with_backend<T: TmuxBackend>(cli: &Cli, backend: &T) -> Result<()> {
-        backend.check_available()?;
+    if let Some(window) = &args.attach_window {
+        check_max_windows(&config, args)?;
+        find_window(&expanded(config.clone()), Some(window), None)?;
+    }
 
-        let config = Config::load(&cli.config)?;
-        let session_exists = backend.has_session(&config.name)?;
+    let report = bring_up(cli, args, config.clone(), backend)?;
 
-        if !session_exists {
-            backend.new_session(&config.name, true)?;
-        }
+    for spec in &args.wait {
+        wait_for(cli, args, backend, &report.session, spec)?;
+    }
 
-        let existing_windows = if session_exists {
-            backend.list_windows(&config.name)?
-        } else {
-            vec![]
-        };
+    if args.health_check {
+        std::thread::sleep(Duration::from_secs(args.health_check_delay));
+        let mut config = config.clone();
+        select_windows(&mut config, args)?;
+        report_health(cli, &health_check(backend, &report.session, &config)?);
+    }
 
-        for (idx, window_conf) in config.window.iter().enumerate() {
-            let window_name = window_conf.name.as_deref();
+    if args.foreground || args.attach_window.is_some() {
+        on_attach(cli, &config);
+        attach(
+            backend,
+            &report.session,
+            &expanded(config),
+            args.attach_window.as_deref(),
+            AttachOptions::default(),
+        )?;
+    }
 
-            let window_exists = if let Some(name) = window_name {
-                existing_windows.iter().any(|w| w == name)
-            } else {
-                false
-            };
+    Ok(report)
+}
 
-            if window_exists {
-                continue;
-            }
+/// How often `up --wait` checks a window's output.
+const WAIT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Lines of scrollback `up --wait` searches, on top of the visible pane.
+const WAIT_LINES: usize = 1000;
+
+/// Poll a window's output until it contains the spec's text, failing
+/// once `--wait-timeout` seconds have passed.
+fn wait_for<T: TmuxBackend>(
+    cli: &Cli,
+    args: &UpArgs,
+    backend: &T,
+    session: &str,
+    spec: &WaitSpec,
+) -> Result<()> {
+    if !(cli.quiet || args.json) {
+        println!("Waiting for '{}' to print '{}'", spec.window, spec.text);
+    }
 
-            if idx == 0 && !session_exists {
-                // Rename the default window 0 to match our config
-                if let Some(name) = window_name {
-                    backend.rename_window(&config.name, idx, name)?;
-                }
+    let idx = running_window_index(backend, session, &spec.window)?;
+    let timeout = Duration::from_secs(args.wait_timeout);
+    let start = Instant::now();
+    loop {
+        if backend
+            .capture_pane(session, idx, WAIT_LINES)?
+            .contains(&spec.text)
+        {
+            return Ok(());
+        }
+        if start.elapsed() >= timeout {
+            return Err(SeshError::WaitTimeout {
+                window: spec.window.clone(),
+                text: spec.text.clone(),
+            });
+        }
+        std::thread::sleep(WAIT_INTERVAL);
+    }
+}
 
-                // Execute command if specified
-                if let Some(command) = &window_conf.command {
-                    backend.send_keys(&config.name, idx, command)?;
-                }
-            } else {
-                // Don't specify target_index - let tmux auto-assign indices
-                backend.new_window(&config.name, window_name, None)?;
+/// A window `up --health-check` found back at a shell prompt,
+/// meaning its command exited soon after starting.
+#[derive(Debug, PartialEq, Eq)]
+struct UnhealthyWindow {
+    name: String,
+    /// The command the window was configured to run
+    command: Vec<String>,
+    /// What its first pane is running instead
+    running: String,
+}
 
-                if let Some(command) = &window_conf.command {
-                    backend.send_keys(&config.name, idx, command)?;
-                }
-            }
+/// Find the named windows given a command whose first pane is dead or
+/// just sitting at the session's shell, since the command has exited.
+/// Windows whose command is the shell itself are skipped.
+fn health_check<T: TmuxBackend>(
+    backend: &T,
+    session: &str,
+    config: &Config,
+) -> Result<Vec<UnhealthyWindow>> {
+    let shell = backend.default_shell(session)?;
+    let panes = backend.list_panes(session)?;
+
+    let mut unhealthy = vec![];
+    for window in &config.window {
+        let (Some(name), Some(command)) = (&window.name, config.window_command(window)) else {
+            continue;
+        };
+        let program =
+            conf::command_program(command).and_then(|program| Path::new(program).file_name());
+        if program.is_some_and(|program| shell.file_name() == Some(program)) {
+            continue;
         }
 
-        Ok(())
+        // A window's panes are listed in order, so this is its first one
+        let Some(pane) = panes.iter().find(|pane| &pane.window_name == name) else {
+            continue;
+        };
+        if is_dead(&panes, pane.window_index, &shell) {
+            unhealthy.push(UnhealthyWindow {
+                name: name.clone(),
+                command: command.clone(),
+                running: pane.command.clone(),
+            });
+        }
     }
+    Ok(unhealthy)
+}
 
-    fn run_down_with_backend<T: TmuxBackend>(cli: &Cli, backend: &T) -> Result<()> {
-        backend.check_available()?;
+/// Print what `up --health-check` found.
+fn report_health(cli: &Cli, unhealthy: &[UnhealthyWindow]) {
+    if cli.quiet {
+        return;
+    }
+    if unhealthy.is_empty() {
+        println!("All windows are healthy");
+    }
+    for window in unhealthy {
+        eprintln!(
+            "Warning: window '{}' isn't running '{}' (found '{}')",
+            window.name,
+            window.command.join(" "),
+            window.running
+        );
+    }
+}
 
-        let config = Config::load(&cli.config)?;
-        let session_exists = backend.has_session(&config.name)?;
+/// Run the config's `on_attach` hook, only warning if it fails
+/// so it can't stop the attach.
+fn on_attach(cli: &Cli, config: &Config) {
+    if let Err(err) = run_hook(config.on_attach.as_deref())
+        && !cli.quiet
+    {
+        eprintln!("Warning: {}", err);
+    }
+}
 
-        if !session_exists {
-            return Ok(());
-        }
+/// Run a hook command in the current terminal, outside of tmux.
+fn run_hook(command: Option<&[String]>) -> Result<()> {
+    let Some((program, args)) = command.and_then(|c| c.split_first()) else {
+        return Ok(());
+    };
 
-        backend.kill_session(&config.name)?;
-        Ok(())
+    let status = std::process::Command::new(program)
+        .args(args)
+        .status()
+        .map_err(|e| SeshError::Other(format!("Failed to run hook '{}': {}", program, e)))?;
+    if !status.success() {
+        return Err(SeshError::Other(format!(
+            "Hook '{}' failed ({})",
+            command.unwrap_or_default().join(" "),
+            status
+        )));
+    }
+    Ok(())
+}
+
+/// Attach to the session with `window` selected, or else the config's
+/// default window if there is one and it's running. This blocks until
+/// the user detaches.
+fn attach<T: TmuxBackend>(
+    backend: &T,
+    session: &str,
+    config: &Config,
+    window: Option<&str>,
+    options: AttachOptions,
+) -> Result<()> {
+    let running_windows = backend.list_windows(session)?;
+    let window = window.or_else(|| default_window(config, &running_windows).map(String::as_str));
+
+    if let Some(window_name) = window {
+        backend.attach_session_with_window(session, window_name, options)
+    } else {
+        backend.attach_session(session, options)
+    }
+}
+
+/// How `attach` attaches the client, from its flags.
+fn attach_options(args: &AttachArgs) -> AttachOptions {
+    AttachOptions {
+        detach_others: args.detach_others,
+        read_only: args.read_only,
+    }
+}
+
+/// The config's default window, if it's running.
+fn default_window<'a>(config: &'a Config, running_windows: &[String]) -> Option<&'a String> {
+    config
+        .window
+        .iter()
+        .find(|w| w.default == Some(true))
+        .and_then(|w| w.name.as_ref())
+        .filter(|name| running_windows.contains(name))
+}
+
+/// Create the session and any missing windows, always detached.
+///
+/// With `--print-plan` the steps are printed first, and with
+/// `--dry-run` they're only printed.
+fn bring_up<T: TmuxBackend>(
+    cli: &Cli,
+    args: &UpArgs,
+    config: Config,
+    backend: &T,
+) -> Result<SessionReport> {
+    let plan = plan_up(cli, args, config, backend)?;
+    if plan.report.created == Some(false) && !args.no_config {
+        warn_other_config(cli, backend, &plan.session)?;
+    }
+
+    if args.print_plan || args.dry_run {
+        print_plan(&plan);
+    }
+    if args.dry_run {
+        return Ok(plan.report);
+    }
+
+    execute_up(cli, args, backend, plan)
+}
+
+/// What `up` will do, worked out from what's already running.
+#[derive(Debug)]
+struct UpPlan {
+    session: String,
+    /// Every configured window is already running, so there's nothing to do
+    already_up: bool,
+    actions: Vec<UpAction>,
+    /// What `up` reports once the actions have been carried out
+    report: SessionReport,
+}
+
+/// A step `up` takes to bring the session up.
+///
+/// Windows are planned by their position among the session's windows,
+/// since that's all that's known before they exist. Once they do, the
+/// actions that only affect one window are switched over to its tmux
+/// index (see [`execute_up`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum UpAction {
+    /// Kill the running session first (`--kill-existing`)
+    KillSession,
+    /// Create the session, naming its first window after the first
+    /// configured one
+    CreateSession {
+        dir: Option<PathBuf>,
+        shell: Option<PathBuf>,
+        window_name: Option<String>,
+    },
+    SetOption {
+        key: String,
+        value: String,
+        global: bool,
+    },
+    /// Record the config file a new session was started from
+    /// in its environment (see [`CONFIG_ENV`])
+    RecordConfig(String),
+    /// Start new windows and panes with this shell
+    SetDefaultShell(PathBuf),
+    /// Send the config's `setup` command to a new session's first window
+    SendSetup(Vec<String>),
+    /// Leave a window that's already running alone
+    KeepWindow { label: String },
+    CreateWindow {
+        position: usize,
+        name: Option<String>,
+        label: String,
+        dir: Option<PathBuf>,
+    },
+    /// Turn off tmux's `automatic-rename` for a new window
+    DisableRename { index: usize, label: String },
+    /// Append a new window's output to its `log_file`
+    PipePane {
+        index: usize,
+        label: String,
+        path: PathBuf,
+    },
+    /// Send raw tmux keys, without pressing Enter
+    SendKeys {
+        index: usize,
+        label: String,
+        keys: Vec<String>,
+    },
+    /// Type a window's command and press Enter, `rebuild`ing it if the
+    /// window already existed but its command had exited. With
+    /// `clear_line`, `C-u` is sent first to clear the prompt.
+    SendCommand {
+        index: usize,
+        label: String,
+        command: Vec<String>,
+        rebuild: bool,
+        clear_line: bool,
+    },
+    /// Split off a window's panes and apply its layout, focus and synchronize
+    CreatePanes {
+        index: usize,
+        label: String,
+        window: Box<WindowConf>,
+        dir: Option<PathBuf>,
+    },
+}
+
+impl UpAction {
+    /// The window this only affects, if any. These can be carried out
+    /// in parallel with other windows' actions, once the windows exist.
+    /// Unlike `CreateWindow`, they don't count as affecting one window.
+    fn window_index(&self) -> Option<usize> {
+        match self {
+            Self::DisableRename { index, .. }
+            | Self::PipePane { index, .. }
+            | Self::SendKeys { index, .. }
+            | Self::SendCommand { index, .. }
+            | Self::CreatePanes { index, .. } => Some(*index),
+            _ => None,
+        }
+    }
+
+    /// The label of the window this acts on, if any.
+    fn label(&self) -> Option<&str> {
+        match self {
+            Self::KeepWindow { label }
+            | Self::CreateWindow { label, .. }
+            | Self::DisableRename { label, .. }
+            | Self::PipePane { label, .. }
+            | Self::SendKeys { label, .. }
+            | Self::SendCommand { label, .. }
+            | Self::CreatePanes { label, .. } => Some(label),
+            _ => None,
+        }
+    }
+
+    /// This action with the window it acts on mapped through `to`.
+    fn reindexed(&self, to: impl Fn(usize) -> usize) -> Self {
+        let mut action = self.clone();
+        if let Self::CreateWindow {
+            position: index, ..
+        }
+        | Self::DisableRename { index, .. }
+        | Self::PipePane { index, .. }
+        | Self::SendKeys { index, .. }
+        | Self::SendCommand { index, .. }
+        | Self::CreatePanes { index, .. } = &mut action
+        {
+            *index = to(*index);
+        }
+        action
+    }
+
+    /// A one-line description for `--print-plan`.
+    fn describe(&self, session: &str) -> String {
+        let dir = |dir: &Option<PathBuf>| match dir {
+            Some(dir) => format!(" in {}", dir.display()),
+            None => String::new(),
+        };
+        match self {
+            Self::KillSession => format!("kill session '{}'", session),
+            Self::CreateSession {
+                dir: start,
+                shell,
+                window_name,
+            } => {
+                let window = match window_name {
+                    Some(name) => format!(" with window '{}'", name),
+                    None => String::new(),
+                };
+                let shell = match shell {
+                    Some(shell) => format!(" running {}", shell.display()),
+                    None => String::new(),
+                };
+                format!(
+                    "create session '{}'{}{}{}",
+                    session,
+                    window,
+                    dir(start),
+                    shell
+                )
+            }
+            Self::SetOption { key, value, global } => {
+                let scope = if *global { "global option" } else { "option" };
+                format!("set {} {} = {}", scope, key, value)
+            }
+            Self::RecordConfig(path) => format!("record config {} in {}", path, CONFIG_ENV),
+            Self::SetDefaultShell(shell) => {
+                format!("use {} for new windows and panes", shell.display())
+            }
+            Self::SendSetup(command) => {
+                format!("send setup '{}' to the first window", command.join(" "))
+            }
+            Self::KeepWindow { label } => format!("keep window '{}' (already running)", label),
+            Self::CreateWindow {
+                position,
+                label,
+                dir: start,
+                ..
+            } => format!(
+                "create window '{}' at position {}{}",
+                label,
+                position,
+                dir(start)
+            ),
+            Self::DisableRename { label, .. } => {
+                format!("turn off automatic-rename for '{}'", label)
+            }
+            Self::PipePane { label, path, .. } => {
+                format!("log '{}' to {}", label, path.display())
+            }
+            Self::SendKeys { label, keys, .. } => {
+                format!("send keys {} to '{}'", keys.join(" "), label)
+            }
+            Self::SendCommand {
+                label,
+                command,
+                rebuild,
+                clear_line,
+                ..
+            } => {
+                let verb = if *rebuild { "re-send" } else { "send" };
+                let clear = if *clear_line {
+                    ", clearing the line first"
+                } else {
+                    ""
+                };
+                format!("{} '{}' to '{}'{}", verb, command.join(" "), label, clear)
+            }
+            Self::CreatePanes { label, window, .. } => {
+                let panes = window.pane.as_ref().map_or(0, Vec::len);
+                let mut line = format!("create {} pane(s) in '{}'", panes, label);
+                if let Some(layout) = window.layout.as_ref().filter(|_| panes > 0) {
+                    line.push_str(&format!(" with layout {}", layout));
+                }
+                if window.synchronize == Some(true) {
+                    line.push_str(", synchronized");
+                }
+                line
+            }
+        }
+    }
+}
+
+/// Print the steps `up` will take, numbered in order.
+fn print_plan(plan: &UpPlan) {
+    println!("Plan for session '{}':", plan.session);
+    if plan.actions.is_empty() {
+        println!("  Nothing to do");
+    }
+    for (i, action) in plan.actions.iter().enumerate() {
+        println!("  {}. {}", i + 1, action.describe(&plan.session));
+    }
+}
+
+/// Work out what `up` needs to do, without changing anything.
+fn plan_up<T: TmuxBackend>(
+    cli: &Cli,
+    args: &UpArgs,
+    mut config: Config,
+    backend: &T,
+) -> Result<UpPlan> {
+    select_windows(&mut config, args)?;
+    let session = session_name(cli, &config);
+    let mut actions = vec![];
+
+    // Check if session already exists
+    let mut session_exists = backend.has_session(&session)?;
+
+    if session_exists && args.kill_existing {
+        actions.push(UpAction::KillSession);
+        session_exists = false;
+    }
+
+    let shell = cli
+        .shell
+        .as_deref()
+        .or(config.shell.as_deref())
+        .map(find_shell)
+        .transpose()?;
+
+    // Where each window starts, resolved up front so a bad path fails early
+    let mut window_dirs = config
+        .window
+        .iter()
+        .map(|w| {
+            config
+                .window_cwd(w)
+                .map(|dir| resolve_dir(cli, dir))
+                .transpose()
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // `--preserve-pwd` starts window 0 where sesh was run from,
+    // overriding both its own and the top-level `cwd`
+    let pwd = args.preserve_pwd.then(std::env::current_dir).transpose()?;
+    if let (Some(pwd), Some(first)) = (&pwd, window_dirs.first_mut()) {
+        *first = Some(pwd.clone());
+    }
+
+    if !session_exists {
+        // Create new session (detached). Its default window becomes the
+        // first configured one, so it starts in that window's directory
+        // and is named after it.
+        let session_dir = match (window_dirs.first(), pwd) {
+            (Some(dir), _) => dir.clone(),
+            (None, Some(pwd)) => Some(pwd),
+            (None, None) => config
+                .cwd
+                .as_deref()
+                .map(|dir| resolve_dir(cli, dir))
+                .transpose()?,
+        };
+        actions.push(UpAction::CreateSession {
+            dir: session_dir,
+            shell: shell.clone(),
+            window_name: config.window.first().and_then(|w| config.window_name(w, 0)),
+        });
+        if !args.no_config {
+            actions.push(UpAction::RecordConfig(conf::display_config_path(
+                &cli.config,
+            )));
+        }
+
+        for (options, global) in [(&config.options, false), (&config.global_options, true)] {
+            for (key, value) in options.iter().flatten() {
+                actions.push(UpAction::SetOption {
+                    key: key.clone(),
+                    value: value.clone(),
+                    global,
+                });
+            }
+        }
+
+        // Before any window's own keys or command
+        if let Some(setup) = &config.setup {
+            actions.push(UpAction::SendSetup(setup.clone()));
+        }
+    }
+
+    // The existing windows, in index order
+    let existing = if session_exists {
+        backend
+            .session_info(&session)?
+            .map(|info| info.windows)
+            .unwrap_or_default()
+    } else {
+        vec![]
+    };
+    let existing_windows: Vec<_> = existing.iter().map(|w| w.name.clone()).collect();
+
+    // Nothing to do if every named window is running and there are enough
+    // windows for the unnamed ones, which can't be matched by name
+    if session_exists
+        && !args.rebuild_dead
+        && all_named_windows_running(&config, &existing_windows)
+        && existing_windows.len() >= config.window.len()
+    {
+        let report = SessionReport {
+            session: session.clone(),
+            running: true,
+            created: Some(false),
+            windows: config
+                .window
+                .iter()
+                .map(|w| WindowReport {
+                    name: w.name.clone(),
+                    state: WindowState::Existing,
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        return Ok(UpPlan {
+            session,
+            already_up: true,
+            actions,
+            report,
+        });
+    }
+
+    // Windows added from here on (and their panes) use the shell too
+    if let Some(shell) = &shell {
+        actions.push(UpAction::SetDefaultShell(shell.clone()));
+    }
+
+    // The session's windows in index order, kept up to date as windows
+    // are added so each new window can be placed right after the previous
+    // configured one. Windows that already exist have their tmux index.
+    let mut windows: Vec<(Option<usize>, String)> = if session_exists {
+        existing
+            .iter()
+            .map(|w| (Some(w.index), w.name.clone()))
+            .collect()
+    } else {
+        // A new session always starts with one window
+        vec![(None, String::new())]
+    };
+    let mut next_index = 0;
+    let mut reports = vec![];
+    let named: Vec<_> = config
+        .window
+        .iter()
+        .filter_map(|w| w.name.as_deref())
+        .collect();
+
+    // What's running in each existing window and the shell they fall
+    // back to, to spot dead commands
+    let (panes, default_shell) = if args.rebuild_dead && session_exists {
+        (
+            backend.list_panes(&session)?,
+            backend.default_shell(&session)?,
+        )
+    } else {
+        (vec![], PathBuf::new())
+    };
+
+    // Windows are created (or found) in order first, since each is placed
+    // after the previous one. Their keys, commands and panes only target
+    // their own window, so they come afterwards.
+    let mut window_actions = vec![];
+
+    for (idx, window_conf) in config.window.iter().enumerate() {
+        let window_name = window_conf.name.as_deref();
+        let window_dir = window_dirs[idx].clone();
+        let label = window_label(window_name, idx);
+
+        // Check if window already exists. Unnamed windows can't be found
+        // by name, so they're matched by position instead: the window
+        // after the previous configured one, unless it's a named one.
+        let existing_position = match window_name {
+            Some(name) => Some(name)
+                .filter(|name| existing_windows.iter().any(|w| w == name))
+                .and_then(|name| windows.iter().position(|(_, w)| w == name)),
+            None if session_exists => windows
+                .get(next_index)
+                .filter(|(_, w)| !named.contains(&w.as_str()))
+                .map(|_| next_index),
+            None => None,
+        };
+
+        if let Some(existing_position) = existing_position {
+            let tmux_index = windows[existing_position].0;
+            let state = if config.window_command(window_conf).is_some()
+                && tmux_index.is_some_and(|i| is_dead(&panes, i, &default_shell))
+            {
+                plan_window_setup(
+                    &mut window_actions,
+                    &config,
+                    existing_position,
+                    &label,
+                    window_conf,
+                    None,
+                    true,
+                );
+                WindowState::Rebuilt
+            } else {
+                actions.push(UpAction::KeepWindow { label });
+                WindowState::Existing
+            };
+            next_index = existing_position + 1;
+            reports.push(WindowReport {
+                name: window_conf.name.clone(),
+                state,
+            });
+            continue;
+        }
+
+        // Windows without a name are named by the `window_naming` policy
+        let tmux_name = config.window_name(window_conf, idx);
+
+        // For the first window, we need to handle it differently
+        let window_index = if idx == 0 && !session_exists {
+            // The session was just created with this as its first window
+            windows[0].1 = tmux_name.unwrap_or_default();
+            0
+        } else {
+            // Create the new window right after the previous configured
+            // one, shifting any later windows up
+            let window_index = next_index;
+            actions.push(UpAction::CreateWindow {
+                position: window_index,
+                name: tmux_name.clone(),
+                label: label.clone(),
+                dir: window_dir.clone(),
+            });
+            windows.insert(window_index, (None, tmux_name.unwrap_or_default()));
+            // The windows planned at or after it are shifted up with it
+            for action in &mut window_actions {
+                *action = action.reindexed(|i| if i >= window_index { i + 1 } else { i });
+            }
+            window_index
+        };
+
+        // Before anything is sent, so the log has all of the output
+        if let Some(log_file) = &window_conf.log_file {
+            window_actions.push(UpAction::PipePane {
+                index: window_index,
+                label: label.clone(),
+                path: resolve_dir(cli, log_file)?,
+            });
+        }
+        plan_window_setup(
+            &mut window_actions,
+            &config,
+            window_index,
+            &label,
+            window_conf,
+            window_dir,
+            false,
+        );
+        next_index = window_index + 1;
+        reports.push(WindowReport {
+            name: window_conf.name.clone(),
+            state: WindowState::Created,
+        });
+    }
+
+    // Windows added to a running session are only created, so nothing
+    // already running in it gets started twice
+    if session_exists && args.no_send_on_existing {
+        window_actions.retain_mut(|action| match action {
+            UpAction::SendKeys { .. } | UpAction::SendCommand { .. } => false,
+            UpAction::CreatePanes { window, .. } => {
+                for pane in window.pane.iter_mut().flatten() {
+                    pane.command = None;
+                }
+                true
+            }
+            _ => true,
+        });
+    }
+    actions.extend(window_actions);
+
+    let report = SessionReport {
+        session: session.clone(),
+        running: true,
+        created: Some(!session_exists),
+        windows: reports,
+        ..Default::default()
+    };
+
+    Ok(UpPlan {
+        session,
+        already_up: false,
+        actions,
+        report,
+    })
+}
+
+/// Queue the actions that set up a window once it exists: its keys,
+/// command and panes. When `rebuild`ing a window that was already
+/// running, its panes are left alone.
+fn plan_window_setup(
+    actions: &mut Vec<UpAction>,
+    config: &Config,
+    index: usize,
+    label: &str,
+    window_conf: &WindowConf,
+    dir: Option<PathBuf>,
+    rebuild: bool,
+) {
+    // Before the command starts, so tmux doesn't rename the window after it
+    if !rebuild && window_conf.allow_rename != Some(true) {
+        actions.push(UpAction::DisableRename {
+            index,
+            label: label.to_string(),
+        });
+    }
+    if let Some(keys) = &window_conf.keys {
+        actions.push(UpAction::SendKeys {
+            index,
+            label: label.to_string(),
+            keys: keys.clone(),
+        });
+    }
+    for command in config.window_commands(window_conf) {
+        actions.push(UpAction::SendCommand {
+            index,
+            label: label.to_string(),
+            command: command.clone(),
+            rebuild,
+            clear_line: window_conf.clear_line == Some(true),
+        });
+    }
+
+    let has_panes = window_conf.pane.as_ref().is_some_and(|p| !p.is_empty());
+    if !rebuild && (has_panes || window_conf.synchronize == Some(true)) {
+        actions.push(UpAction::CreatePanes {
+            index,
+            label: label.to_string(),
+            window: Box::new(window_conf.clone()),
+            dir,
+        });
+    }
+}
+
+/// Carry out a plan from [`plan_up`], printing what's done.
+///
+/// Actions which only affect one window are run `--jobs` windows at a
+/// time, after everything else. A window that fails to start doesn't
+/// stop the others (unless `--fail-fast`): every failure is returned
+/// together once the rest are up.
+fn execute_up<T: TmuxBackend>(
+    cli: &Cli,
+    args: &UpArgs,
+    backend: &T,
+    plan: UpPlan,
+) -> Result<SessionReport> {
+    // The JSON report replaces the usual messages
+    let quiet = cli.quiet || args.json;
+    let session = &plan.session;
+    let mut report = plan.report;
+    // Each failed window's label and error
+    let mut failures = vec![];
+
+    if plan.already_up {
+        if !quiet {
+            println!("Session '{}' already up", session);
+        }
+    } else {
+        let (window_actions, actions): (Vec<_>, Vec<_>) = plan
+            .actions
+            .iter()
+            .partition(|action| action.window_index().is_some());
+
+        // Planned positions of windows that couldn't be created, which
+        // later windows move down to fill. Like the windows that were
+        // created, they're shifted up by each window created before them.
+        let mut failed: Vec<usize> = vec![];
+        let position = |failed: &[usize], planned: usize| {
+            planned - failed.iter().filter(|&&f| f < planned).count()
+        };
+        for action in actions {
+            if let UpAction::CreateWindow { position, .. } = action {
+                for f in failed.iter_mut().filter(|f| **f >= *position) {
+                    *f += 1;
+                }
+            }
+            let action_run = action.reindexed(|p| position(&failed, p));
+            match run_action(cli, backend, session, &action_run) {
+                Ok(message) => {
+                    if let Some(message) = message
+                        && !quiet
+                    {
+                        println!("{}", message);
+                    }
+                }
+                Err(err) if !args.fail_fast => match action {
+                    UpAction::CreateWindow {
+                        position, label, ..
+                    } => {
+                        failed.push(*position);
+                        failures.push((label.clone(), err));
+                    }
+                    _ => return Err(err),
+                },
+                Err(err) => return Err(err),
+            }
+        }
+
+        if report.windows.is_empty() && !quiet {
+            println!("  No windows configured; using the default tmux window");
+        }
+
+        // Every window exists now, so the tmux index each ended up at can
+        // be looked up by its position
+        let indices = if window_actions.is_empty() {
+            vec![]
+        } else {
+            window_indices(backend, session)?
+        };
+
+        // Each window's actions stay in order, on one thread
+        let mut by_window: Vec<(usize, Vec<UpAction>)> = vec![];
+        for action in window_actions {
+            let Some(index) = action.window_index().filter(|i| !failed.contains(i)) else {
+                continue;
+            };
+            let action = action.reindexed(|p| {
+                let p = position(&failed, p);
+                indices.get(p).copied().unwrap_or(p)
+            });
+            match by_window.iter_mut().find(|(i, _)| *i == index) {
+                Some((_, actions)) => actions.push(action),
+                None => by_window.push((index, vec![action])),
+            }
+        }
+
+        let results = run_parallel(&by_window, args.jobs.max(1), |(_, actions)| {
+            let mut messages = vec![];
+            for action in actions {
+                messages.extend(run_action(cli, backend, session, action)?);
+            }
+            Ok::<_, SeshError>(messages)
+        });
+        for ((_, actions), messages) in by_window.iter().zip(results) {
+            match messages {
+                Ok(messages) => {
+                    for message in messages {
+                        if !quiet {
+                            println!("{}", message);
+                        }
+                    }
+                }
+                Err(err) if !args.fail_fast => {
+                    let label = actions.first().and_then(UpAction::label);
+                    failures.push((label.unwrap_or_default().to_string(), err));
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        if !quiet {
+            if failures.is_empty() {
+                println!("Session '{}' is up", session);
+            } else {
+                println!(
+                    "Session '{}' is up, but {} window(s) failed to start",
+                    session,
+                    failures.len()
+                );
+            }
+        }
+    }
+
+    for (idx, window) in report.windows.iter_mut().enumerate() {
+        let label = window_label(window.name.as_deref(), idx);
+        if failures.iter().any(|(failed, _)| *failed == label) {
+            window.state = WindowState::Failed;
+        }
+    }
+
+    if args.json {
+        println!("{}", serde_json::to_string(&report)?);
+    }
+
+    if !failures.is_empty() {
+        return Err(SeshError::WindowsFailed {
+            session: session.clone(),
+            failures: failures
+                .iter()
+                .map(|(label, err)| format!("{}: {}", label, err))
+                .collect(),
+        });
+    }
+
+    Ok(report)
+}
+
+/// How `up` refers to a window in its messages: its name, or
+/// `window N` (its index in the config) if it doesn't have one.
+fn window_label(name: Option<&str>, idx: usize) -> String {
+    match name {
+        Some(name) => name.to_string(),
+        None => format!("window {}", idx),
+    }
+}
+
+/// Carry out one of `up`'s actions, returning the message to print.
+fn run_action<T: TmuxBackend>(
+    cli: &Cli,
+    backend: &T,
+    session: &str,
+    action: &UpAction,
+) -> Result<Option<String>> {
+    let message = match action {
+        UpAction::KillSession => {
+            backend.kill_session(session)?;
+            format!("Killed existing session '{}'", session)
+        }
+        UpAction::CreateSession {
+            dir,
+            shell,
+            window_name,
+        } => {
+            backend.new_session(
+                session,
+                true,
+                window_name.as_deref(),
+                dir.as_deref(),
+                shell.as_deref(),
+            )?;
+            format!("Created session '{}'", session)
+        }
+        UpAction::SetOption { key, value, global } => {
+            backend.set_option(session, key, value, *global)?;
+            return Ok(None);
+        }
+        UpAction::RecordConfig(path) => {
+            backend.set_environment(session, CONFIG_ENV, path)?;
+            return Ok(None);
+        }
+        UpAction::SetDefaultShell(shell) => {
+            backend.set_default_shell(session, shell)?;
+            return Ok(None);
+        }
+        UpAction::SendSetup(command) => {
+            let first = window_indices(backend, session)?
+                .first()
+                .copied()
+                .unwrap_or_default();
+            backend.send_keys(session, first, command)?;
+            format!("  Ran setup in window {}", first)
+        }
+        UpAction::KeepWindow { label } => format!("  Window '{}' already exists", label),
+        UpAction::CreateWindow {
+            position,
+            name,
+            label,
+            dir,
+        } => {
+            let target = window_target(&window_indices(backend, session)?, *position);
+            backend.new_window(session, name.as_deref(), target, dir.as_deref())?;
+            format!("  Created window '{}'", label)
+        }
+        UpAction::DisableRename { index, .. } => {
+            backend.set_automatic_rename(session, *index, false)?;
+            return Ok(None);
+        }
+        UpAction::PipePane { index, path, .. } => {
+            // `cat >>` creates the file, but not its directory
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            backend.pipe_pane(session, *index, path)?;
+            return Ok(None);
+        }
+        UpAction::SendKeys { index, keys, .. } => {
+            backend.send_raw_keys(session, *index, keys)?;
+            return Ok(None);
+        }
+        UpAction::SendCommand {
+            index,
+            label,
+            command,
+            rebuild,
+            clear_line,
+        } => {
+            if *clear_line {
+                backend.send_raw_keys(session, *index, &["C-u".to_string()])?;
+            }
+            backend.send_keys(session, *index, command)?;
+            if *rebuild {
+                format!("  Re-sent command to '{}'", label)
+            } else {
+                format!("  Executed command in {}", label)
+            }
+        }
+        UpAction::CreatePanes {
+            index, window, dir, ..
+        } => return create_panes(cli, backend, session, *index, window, dir.as_deref()),
+    };
+    Ok(Some(message))
+}
+
+/// The tmux index of each of a running session's windows, in order.
+fn window_indices<T: TmuxBackend>(backend: &T, session: &str) -> Result<Vec<usize>> {
+    let info = backend
+        .session_info(session)?
+        .ok_or_else(|| SeshError::SessionNotRunning(session.to_string()))?;
+    Ok(info.windows.iter().map(|w| w.index).collect())
+}
+
+/// Where to create a window so it's at `position` among windows at the
+/// tmux `indices`: after the window before it, or before the first.
+fn window_target(indices: &[usize], position: usize) -> Option<WindowTarget> {
+    match position.checked_sub(1) {
+        Some(before) => indices.get(before).map(|&i| WindowTarget::After(i)),
+        None => indices.first().map(|&i| WindowTarget::Before(i)),
+    }
+}
+
+/// Whether a pane is just sitting at a prompt of the session's default
+/// `shell`, which windows fall back to once their command exits.
+fn at_shell(pane: &tmux::PaneInfo, shell: &Path) -> bool {
+    shell.file_name() == Some(pane.command.as_ref())
+}
+
+/// Whether a window's first pane is dead (kept by `remain-on-exit`) or
+/// back at the session's default `shell`, meaning its command exited.
+fn is_dead(panes: &[tmux::PaneInfo], window_index: usize, shell: &Path) -> bool {
+    panes
+        .iter()
+        .find(|pane| pane.window_index == window_index)
+        .is_some_and(|pane| pane.dead || at_shell(pane, shell))
+}
+
+/// Whether every named window in the config is among the `running` ones.
+fn all_named_windows_running(config: &Config, running: &[String]) -> bool {
+    config
+        .window
+        .iter()
+        .filter_map(|w| w.name.as_ref())
+        .all(|name| running.contains(name))
+}
+
+/// Get the config for `up`: the config file, or with `--no-config`
+/// one built from the `--name` and `--window` args.
+fn up_config(cli: &Cli, args: &UpArgs) -> Result<Config> {
+    if !args.no_config {
+        return load_config(cli);
+    }
+
+    let name = args
+        .name
+        .clone()
+        .or_else(|| cli.session_name.clone())
+        .ok_or_else(|| SeshError::Usage("--no-config needs a session --name".to_string()))?;
+    let window = args
+        .windows
+        .iter()
+        .map(|spec| WindowConf {
+            name: Some(spec.name.clone()),
+            command: spec.command.clone().map(|command| vec![command]),
+            ..Default::default()
+        })
+        .collect();
+
+    Ok(Config {
+        name,
+        window,
+        ..Default::default()
+    })
+}
+
+/// Drop the windows filtered out by `up --only` or `--except`, failing
+/// if either names a window that isn't in the config, then expand the
+/// `count`s of the rest, unless that makes more than `--max-windows`.
+fn select_windows(config: &mut Config, args: &UpArgs) -> Result<()> {
+    let names = if args.only.is_empty() {
+        &args.except
+    } else {
+        &args.only
+    };
+
+    if let Some(missing) = names
+        .iter()
+        .find(|name| !config.window.iter().any(|w| w.name.as_ref() == Some(name)))
+    {
+        return Err(SeshError::WindowNotInConfig(missing.to_string()));
+    }
+
+    if !names.is_empty() {
+        let keep = !args.only.is_empty();
+        config
+            .window
+            .retain(|w| w.name.as_ref().is_some_and(|name| names.contains(name)) == keep);
+    }
+
+    // Naming a disabled window in `--only` still starts it
+    if args.only.is_empty() {
+        config.window.retain(|w| w.disabled != Some(true));
+    }
+
+    check_max_windows(config, args)?;
+    config.expand_counts();
+    Ok(())
+}
+
+/// Fail if expanding the config's `count`s would make more than
+/// `--max-windows` windows. Counted before expanding, so a runaway
+/// `count` fails without allocating all of its windows first.
+fn check_max_windows(config: &Config, args: &UpArgs) -> Result<()> {
+    let total = config.window.iter().fold(0usize, |total, w| {
+        total.saturating_add(w.count.unwrap_or(1))
+    });
+    if total > args.max_windows {
+        return Err(SeshError::Usage(format!(
+            "The config has {} windows, more than --max-windows ({})",
+            total, args.max_windows
+        )));
+    }
+    Ok(())
+}
+
+/// Find a shell's full path (tmux needs one for `default-shell`),
+/// looking it up in `PATH` if it's just a name like `bash`.
+fn find_shell(shell: &str) -> Result<PathBuf> {
+    let path = Path::new(shell);
+    let found = if path.components().count() > 1 {
+        path.is_file().then(|| path.to_path_buf())
+    } else {
+        std::env::var_os("PATH").and_then(|paths| {
+            std::env::split_paths(&paths)
+                .map(|dir| dir.join(shell))
+                .find(|candidate| candidate.is_file())
+        })
+    };
+
+    match found {
+        Some(path) => Ok(std::path::absolute(path)?),
+        None => Err(SeshError::ShellNotFound(shell.to_string())),
+    }
+}
+
+/// Resolve a configured directory against the config file's directory.
+fn resolve_dir(cli: &Cli, dir: &Path) -> Result<PathBuf> {
+    let base = cli.config.parent().unwrap_or(Path::new(""));
+    Ok(std::path::absolute(base.join(dir))?)
+}
+
+/// Call `f` on each item using up to `jobs` threads, returning
+/// the results in the same order as the items.
+fn run_parallel<I, R, F>(items: &[I], jobs: usize, f: F) -> Vec<R>
+where
+    I: Sync,
+    R: Send,
+    F: Fn(&I) -> R + Sync,
+{
+    if jobs <= 1 || items.len() <= 1 {
+        return items.iter().map(f).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(items.iter().map(|_| None).collect::<Vec<_>>());
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.min(items.len()) {
+            scope.spawn(|| {
+                loop {
+                    let idx = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(idx) else {
+                        break;
+                    };
+                    let result = f(item);
+                    results.lock().unwrap()[idx] = Some(result);
+                }
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.expect("every item is processed"))
+        .collect()
+}
+
+/// Split a newly created window into its configured panes
+/// and apply the window's layout.
+///
+/// Panes without their own `cwd` start in the window's directory.
+fn create_panes<T: TmuxBackend>(
+    cli: &Cli,
+    backend: &T,
+    session: &str,
+    window_index: usize,
+    window_conf: &WindowConf,
+    window_dir: Option<&Path>,
+) -> Result<Option<String>> {
+    let panes = window_conf.pane.as_deref().unwrap_or_default();
+
+    for pane in panes {
+        let pane_dir = match &pane.cwd {
+            Some(dir) => Some(resolve_dir(cli, dir)?),
+            None => window_dir.map(Path::to_path_buf),
+        };
+
+        // The new pane becomes the active one, so the keys land there
+        backend.split_window(session, window_index, pane_dir.as_deref())?;
+
+        if let Some(command) = &pane.command {
+            backend.send_keys(session, window_index, command)?;
+        }
+    }
+
+    // Layouts only matter once there's more than one pane
+    if let Some(layout) = &window_conf.layout
+        && !panes.is_empty()
+    {
+        backend.select_layout(session, window_index, layout)?;
+    }
+
+    // tmux leaves the last new pane focused, so go back to the first
+    // one unless told otherwise
+    if !panes.is_empty() {
+        let focus = window_conf.focus.unwrap_or(0);
+        backend.select_pane(session, window_index, focus)?;
+    }
+
+    // Only turned on once the panes' own commands have been sent,
+    // so they don't get broadcast to every pane
+    if window_conf.synchronize == Some(true) {
+        backend.set_synchronize_panes(session, window_index, true)?;
+    }
+
+    Ok((!panes.is_empty()).then(|| {
+        format!(
+            "  Created {} pane(s) in window {}",
+            panes.len(),
+            window_index
+        )
+    }))
+}
+
+/// Kill the session + windows.
+pub fn run_down(cli: &Cli, args: &DownArgs) -> Result<()> {
+    run_down_with_backend(cli, args, &real_backend(cli))?;
+    Ok(())
+}
+
+fn run_down_with_backend<T: TmuxBackend>(
+    cli: &Cli,
+    args: &DownArgs,
+    backend: &T,
+) -> Result<SessionReport> {
+    check_tmux(cli, backend)?;
+
+    // The JSON report replaces the usual messages
+    let quiet = cli.quiet || args.json;
+
+    let config = load_config(cli)?;
+    let session = &session_name(cli, &config);
+    if !tag_matches(cli, &config, args.tag.as_deref()) {
+        return Ok(SessionReport {
+            session: session.to_string(),
+            ..Default::default()
+        });
+    }
+
+    // Check if session exists
+    let session_exists = backend.has_session(session)?;
+
+    if session_exists {
+        // Kill the session
+        backend.kill_session(session)?;
+
+        if !quiet {
+            println!("Killed session '{}'", session);
+        }
+    } else if !quiet {
+        println!("Session '{}' is not running", session);
+    }
+
+    let report = SessionReport {
+        session: session.to_string(),
+        running: false,
+        killed: Some(session_exists),
+        ..Default::default()
+    };
+
+    if args.json {
+        println!("{}", serde_json::to_string(&report)?);
+    }
+
+    Ok(report)
+}
+
+/// Ensure the session + windows are running and
+/// attach to the session.
+///
+/// With `--if-exists`, only attach if the session is already running.
+pub fn run_attach(cli: &Cli, args: &AttachArgs) -> Result<()> {
+    let backend = real_backend(cli);
+    if args.attach_or_create {
+        return attach_or_create(cli, args, &backend);
+    }
+    let session = prepare_attach(cli, args, &backend)?;
+    let config = expanded(load_config(cli)?);
+    on_attach(cli, &config);
+    if args.reconnect {
+        return attach_reconnecting(cli, args, &backend, &session, &config, RECONNECT_RESET);
+    }
+    attach(
+        &backend,
+        &session,
+        &config,
+        args.window.as_deref(),
+        attach_options(args),
+    )
+}
+
+/// How long an attach has to stay up before a drop no longer counts
+/// towards `--reconnect-retries`, so only drops in quick succession
+/// make `--reconnect` give up.
+const RECONNECT_RESET: Duration = Duration::from_secs(60);
+
+/// Attach, and with `--reconnect` attach again whenever the connection
+/// drops, up to `--reconnect-retries` times in a row. Any attach that
+/// stays up for `stable` starts the count again.
+///
+/// Only a dropped connection ([`SeshError::AttachLost`]) is retried: a
+/// clean detach (or the session ending) exits tmux with 0, and tmux
+/// failing to attach at all is reported as usual.
+///
+/// When a terminal goes away (e.g. SSH dropping), SIGHUP is sent to
+/// sesh as well as the tmux client, and kills it before it can
+/// reattach. So this only helps when sesh outlives the client's
+/// terminal, e.g. when it's run under `nohup` or another multiplexer.
+fn attach_reconnecting<T: TmuxBackend>(
+    cli: &Cli,
+    args: &AttachArgs,
+    backend: &T,
+    session: &str,
+    config: &Config,
+    stable: Duration,
+) -> Result<()> {
+    let mut attempts = 0;
+    loop {
+        let started = Instant::now();
+        let err = match attach(
+            backend,
+            session,
+            config,
+            args.window.as_deref(),
+            attach_options(args),
+        ) {
+            Err(err @ SeshError::AttachLost(_)) => err,
+            result => return result,
+        };
+        if started.elapsed() >= stable {
+            attempts = 0;
+        }
+        if attempts >= args.reconnect_retries || !backend.has_session(session)? {
+            return Err(err);
+        }
+
+        attempts += 1;
+        if !cli.quiet {
+            eprintln!(
+                "{}; reattaching in {}s ({}/{})",
+                err, args.reconnect_interval, attempts, args.reconnect_retries
+            );
+        }
+        std::thread::sleep(Duration::from_secs(args.reconnect_interval));
+    }
+}
+
+/// Attach with `--attach-or-create`, leaving it to tmux to create a bare
+/// session if needed, without the configured windows.
+fn attach_or_create<T: TmuxBackend>(cli: &Cli, args: &AttachArgs, backend: &T) -> Result<()> {
+    check_tmux(cli, backend)?;
+    let config = load_config(cli)?;
+    let session = session_name(cli, &config);
+    on_attach(cli, &config);
+    backend.attach_or_create(&session, args.detach_others)
+}
+
+/// Decide whether the session can be attached to, bringing it up first
+/// unless `--if-exists` was passed. Returns the session to attach to.
+fn prepare_attach<T: TmuxBackend>(cli: &Cli, args: &AttachArgs, backend: &T) -> Result<String> {
+    check_tmux(cli, backend)?;
+
+    if let Some(window) = &args.window {
+        find_window(&expanded(load_config(cli)?), Some(window), None)?;
+    }
+
+    if args.if_exists {
+        let config = load_config(cli)?;
+        let session = session_name(cli, &config);
+        if !backend.has_session(&session)? {
+            return Err(SeshError::SessionNotRunning(session));
+        }
+        warn_other_config(cli, backend, &session)?;
+        return Ok(session);
+    }
+
+    let args = UpArgs::default();
+    Ok(bring_up(cli, &args, up_config(cli, &args)?, backend)?.session)
+}
+
+/// Kill and re-start the session.
+///
+/// Shorthand for running `down` and then `up`. Asks for
+/// confirmation first if the session is running, unless
+/// `--yes` or `--quiet` is passed.
+pub fn run_restart(cli: &Cli, args: &RestartArgs) -> Result<()> {
+    let stdin = std::io::stdin();
+    run_restart_with_backend(cli, args, &real_backend(cli), &mut stdin.lock())
+}
+
+fn run_restart_with_backend<T: TmuxBackend, R: BufRead>(
+    cli: &Cli,
+    args: &RestartArgs,
+    backend: &T,
+    input: &mut R,
+) -> Result<()> {
+    // Quiet mode is non-interactive, so treat it like `--yes`
+    if !args.yes && !cli.quiet {
+        check_tmux(cli, backend)?;
+
+        let config = load_config(cli)?;
+        let session = &session_name(cli, &config);
+
+        // Only ask if there's actually something to kill
+        if backend.has_session(session)? {
+            let prompt = format!(
+                "Restart will kill session '{}' and all processes. Continue? [y/N] ",
+                session
+            );
+            if !confirm(&prompt, input)? {
+                println!("Restart cancelled");
+                return Ok(());
+            }
+        }
+    }
+
+    // Only remembered if there's a running session to remember it from
+    let previous_window = if args.keep_current_window {
+        check_tmux(cli, backend)?;
+        let config = load_config(cli)?;
+        let session = &session_name(cli, &config);
+        match backend.has_session(session)? {
+            true => Some(backend.current_window(session)?),
+            false => None,
+        }
+    } else {
+        None
+    };
+
+    run_down_with_backend(cli, &DownArgs::default(), backend)?;
+    let report = run_up_with_backend(cli, &UpArgs::default(), backend)?;
+
+    if let Some(window) = previous_window {
+        restore_window(cli, backend, &report.session, &window)?;
+    }
+    Ok(())
+}
+
+/// Re-select the window that was active before `restart`, falling back
+/// to the default window if it's no longer in the config.
+fn restore_window<T: TmuxBackend>(
+    cli: &Cli,
+    backend: &T,
+    session: &str,
+    window: &str,
+) -> Result<()> {
+    let config = expanded(load_config(cli)?);
+    let running_windows = backend.list_windows(session)?;
+
+    let in_config = config
+        .window
+        .iter()
+        .any(|w| w.name.as_deref() == Some(window));
+    let target = if in_config && running_windows.iter().any(|w| w == window) {
+        Some(window)
+    } else {
+        default_window(&config, &running_windows).map(String::as_str)
+    };
+
+    if let Some(target) = target {
+        backend.select_window(session, target)?;
+        if !cli.quiet {
+            println!("  Selected window '{}'", target);
+        }
+    }
+    Ok(())
+}
+
+/// Kill the tmux server and every session on it.
+///
+/// Always asks for confirmation unless `--yes` is passed, since this
+/// kills sessions sesh doesn't manage too.
+pub fn run_nuke(cli: &Cli, args: &NukeArgs) -> Result<()> {
+    let stdin = std::io::stdin();
+    run_nuke_with_backend(cli, args, &real_backend(cli), &mut stdin.lock())
+}
+
+fn run_nuke_with_backend<T: TmuxBackend, R: BufRead>(
+    cli: &Cli,
+    args: &NukeArgs,
+    backend: &T,
+    input: &mut R,
+) -> Result<()> {
+    check_tmux(cli, backend)?;
+
+    if !args.yes {
+        // Listing may fail if the server is in a bad state, which
+        // is exactly when this is needed
+        let sessions = backend.list_sessions().unwrap_or_default();
+        let prompt = format!(
+            "This kills the tmux server and ALL {} tmux session(s), not just sesh's: {}\nContinue? [y/N] ",
+            sessions.len(),
+            sessions.join(", ")
+        );
+        if !confirm(&prompt, input)? {
+            if !cli.quiet {
+                println!("Nuke cancelled");
+            }
+            return Ok(());
+        }
+    }
+
+    backend.kill_server()?;
+
+    if !cli.quiet {
+        println!("Killed the tmux server and all of its sessions");
+    }
+    Ok(())
+}
+
+/// Find the tmux index of a window in the running session.
+fn running_window_index<T: TmuxBackend>(backend: &T, session: &str, window: &str) -> Result<usize> {
+    backend
+        .session_info(session)?
+        .ok_or_else(|| SeshError::SessionNotRunning(session.to_string()))?
+        .windows
+        .iter()
+        .find(|w| w.name == window)
+        .map(|w| w.index)
+        .ok_or_else(|| SeshError::WindowNotRunning {
+            session: session.to_string(),
+            window: window.to_string(),
+        })
+}
+
+/// Send keys to a window in the running session.
+pub fn run_send(cli: &Cli, args: &SendArgs) -> Result<()> {
+    run_send_with_backend(cli, args, &real_backend(cli))
+}
+
+fn run_send_with_backend<T: TmuxBackend>(cli: &Cli, args: &SendArgs, backend: &T) -> Result<()> {
+    check_tmux(cli, backend)?;
+
+    let config = load_config(cli)?;
+    let session = &session_name(cli, &config);
+
+    // Windows are listed in index order
+    let targets: Vec<(usize, String)> = match &args.window {
+        Some(window) => vec![(
+            running_window_index(backend, session, window)?,
+            window.clone(),
+        )],
+        None => backend
+            .session_info(session)?
+            .ok_or_else(|| SeshError::SessionNotRunning(session.to_string()))?
+            .windows
+            .into_iter()
+            .filter(|w| !args.except.contains(&w.name))
+            .map(|w| (w.index, w.name))
+            .collect(),
+    };
+
+    for (idx, window) in &targets {
+        send_to_window(
+            backend,
+            session,
+            *idx,
+            &args.keys,
+            args.literal,
+            !args.no_enter,
+        )?;
+
+        if !cli.quiet {
+            println!("Sent keys to window '{}'", window);
+        }
+    }
+
+    Ok(())
+}
+
+/// Send `keys` to a window as tmux key names, or joined with spaces and
+/// typed exactly as given if `literal`, then press Enter if `enter`.
+fn send_to_window<T: TmuxBackend>(
+    backend: &T,
+    session: &str,
+    window_index: usize,
+    keys: &[String],
+    literal: bool,
+    enter: bool,
+) -> Result<()> {
+    if literal {
+        backend.send_literal(session, window_index, &keys.join(" "))?;
+    } else {
+        backend.send_raw_keys(session, window_index, keys)?;
+    }
+    if enter {
+        backend.send_raw_keys(session, window_index, &["C-m".to_string()])?;
+    }
+    Ok(())
+}
+
+/// Show what's running in each pane of the session.
+pub fn run_ps(cli: &Cli) -> Result<()> {
+    run_ps_with_backend(cli, &real_backend(cli))
+}
+
+fn run_ps_with_backend<T: TmuxBackend>(cli: &Cli, backend: &T) -> Result<()> {
+    check_tmux(cli, backend)?;
+
+    let config = load_config(cli)?;
+    let session = &session_name(cli, &config);
+
+    if !backend.has_session(session)? {
+        if !cli.quiet {
+            println!("Session '{}' is not running", session);
+        }
+        return Ok(());
+    }
+
+    let panes = backend.list_panes(session)?;
+
+    if !cli.quiet {
+        print!("{}", format_ps_table(&panes));
+    }
+
+    Ok(())
+}
+
+/// Print the last lines of a window's output.
+///
+/// With `--follow`, the output is re-captured every
+/// `--interval` seconds until interrupted.
+pub fn run_logs(cli: &Cli, args: &LogsArgs) -> Result<()> {
+    run_logs_with_backend(cli, args, &real_backend(cli))
+}
+
+fn run_logs_with_backend<T: TmuxBackend>(cli: &Cli, args: &LogsArgs, backend: &T) -> Result<()> {
+    check_tmux(cli, backend)?;
+
+    let config = load_config(cli)?;
+    let session = &session_name(cli, &config);
+
+    loop {
+        let idx = running_window_index(backend, session, &args.window)?;
+        let output = backend.capture_pane(session, idx, args.lines)?;
+
+        if !cli.quiet {
+            if args.follow {
+                // Clear the screen and move the cursor home
+                print!("\x1b[2J\x1b[H");
+            }
+            for line in last_lines(&output, args.lines) {
+                println!("{}", line);
+            }
+            std::io::stdout().flush()?;
+        }
+
+        if !args.follow {
+            return Ok(());
+        }
+        std::thread::sleep(Duration::from_secs(args.interval));
+    }
+}
+
+/// Run a command in a popup over the current tmux client.
+pub fn run_popup(cli: &Cli, args: &PopupArgs) -> Result<()> {
+    run_popup_with_backend(cli, args, &real_backend(cli))
+}
+
+fn run_popup_with_backend<T: TmuxBackend>(cli: &Cli, args: &PopupArgs, backend: &T) -> Result<()> {
+    check_tmux(cli, backend)?;
+
+    let version = backend.version()?;
+    if version < tmux::POPUP_VERSION {
+        return Err(SeshError::TmuxTooOld {
+            feature: "popup",
+            found: tmux::format_version(&version),
+            required: tmux::format_version(&tmux::POPUP_VERSION),
+        });
+    }
+
+    backend.display_popup(&args.command, &args.width, &args.height)
+}
+
+/// Save the running session's windows and panes to a snapshot file.
+pub fn run_save(cli: &Cli, args: &SaveArgs) -> Result<()> {
+    run_save_with_backend(cli, args, &real_backend(cli))
+}
+
+fn run_save_with_backend<T: TmuxBackend>(cli: &Cli, args: &SaveArgs, backend: &T) -> Result<()> {
+    check_tmux(cli, backend)?;
+
+    let config = load_config(cli)?;
+    let session = &session_name(cli, &config);
+
+    let Some(info) = backend.session_info(session)? else {
+        return Err(SeshError::SessionNotRunning(session.clone()));
+    };
+    let panes = backend.list_panes(session)?;
+    let shell = backend.default_shell(session)?;
+
+    let windows = info
+        .windows
+        .into_iter()
+        .map(|window| WindowSnapshot {
+            panes: panes
+                .iter()
+                .filter(|pane| pane.window_index == window.index)
+                .map(|pane| PaneSnapshot {
+                    command: (!at_shell(pane, &shell)).then(|| pane.command.clone()),
+                    cwd: Some(pane.path.clone()),
+                })
+                .collect(),
+            index: window.index,
+            name: window.name,
+        })
+        .collect();
+    let snapshot = Snapshot {
+        version: snapshot::SNAPSHOT_VERSION,
+        session: session.clone(),
+        windows,
+    };
+
+    let path = snapshot_path(cli, args.file.as_deref())?;
+    snapshot.write(&path)?;
+
+    if !cli.quiet {
+        println!(
+            "Saved session '{}' ({} windows) to {}",
+            session,
+            snapshot.windows.len(),
+            conf::display_config_path(&path)
+        );
+    }
+
+    Ok(())
+}
+
+/// Recreate a session from a snapshot file.
+pub fn run_restore(cli: &Cli, args: &RestoreArgs) -> Result<()> {
+    run_restore_with_backend(cli, args, &real_backend(cli))
+}
+
+fn run_restore_with_backend<T: TmuxBackend>(
+    cli: &Cli,
+    args: &RestoreArgs,
+    backend: &T,
+) -> Result<()> {
+    check_tmux(cli, backend)?;
+
+    let path = snapshot_path(cli, args.file.as_deref())?;
+    let snapshot = Snapshot::load(&path)?;
+    let session = &snapshot.session;
+
+    if backend.has_session(session)? {
+        return Err(SeshError::SessionExists(session.clone()));
+    }
+
+    // A window starts where its first pane was
+    let window_dir = |window: &WindowSnapshot| window.panes.first().and_then(|p| p.cwd.clone());
+
+    // Windows keep their saved indices. The session's first window starts
+    // at `base-index`, so it's moved to its index before adding the rest.
+    let first = snapshot.windows.first();
+    backend.new_session(
+        session,
+        true,
+        first.map(|w| w.name.as_str()),
+        first.and_then(window_dir).as_deref(),
+        None,
+    )?;
+    if let (Some(first), Some(&base)) = (first, window_indices(backend, session)?.first())
+        && base != first.index
+    {
+        backend.move_window(session, base, first.index)?;
+    }
+    for window in snapshot.windows.iter().skip(1) {
+        backend.new_window(
+            session,
+            Some(&window.name),
+            Some(WindowTarget::At(window.index)),
+            window_dir(window).as_deref(),
+        )?;
+    }
+
+    for window in &snapshot.windows {
+        for (pane_idx, pane) in window.panes.iter().enumerate() {
+            if pane_idx > 0 {
+                backend.split_window(session, window.index, pane.cwd.as_deref())?;
+            }
+            if let (true, Some(command)) = (args.commands, &pane.command) {
+                backend.send_keys(session, window.index, std::slice::from_ref(command))?;
+            }
+        }
+    }
+
+    if !cli.quiet {
+        println!(
+            "Restored session '{}' from {}",
+            session,
+            conf::display_config_path(&path)
+        );
+    }
+
+    Ok(())
+}
+
+/// The snapshot file `save` and `restore` use: `file` if given,
+/// otherwise the default one next to the config.
+fn snapshot_path(cli: &Cli, file: Option<&Path>) -> Result<PathBuf> {
+    match file {
+        Some(file) => Ok(std::path::absolute(file)?),
+        None => resolve_dir(cli, Path::new(snapshot::DEFAULT_SNAPSHOT_FILE)),
+    }
+}
+
+/// Get the last `n` lines of captured output, ignoring the blank
+/// lines tmux pads the bottom of the pane with.
+fn last_lines(output: &str, n: usize) -> Vec<&str> {
+    let lines: Vec<_> = output.trim_end().lines().collect();
+    lines[lines.len().saturating_sub(n)..].to_vec()
+}
+
+/// Format panes as an aligned `WINDOW COMMAND PID` table.
+fn format_ps_table(panes: &[tmux::PaneInfo]) -> String {
+    let width = panes
+        .iter()
+        .map(|p| p.window_name.len())
+        .chain(["WINDOW".len()])
+        .max()
+        .unwrap_or_default();
+    let cmd_width = panes
+        .iter()
+        .map(|p| p.command.len())
+        .chain(["COMMAND".len()])
+        .max()
+        .unwrap_or_default();
+
+    let mut table = format!(
+        "{:<width$}  {:<cmd_width$}  PID\n",
+        "WINDOW",
+        "COMMAND",
+        width = width,
+        cmd_width = cmd_width
+    );
+    for pane in panes {
+        table.push_str(&format!(
+            "{:<width$}  {:<cmd_width$}  {}\n",
+            pane.window_name,
+            pane.command,
+            pane.pid,
+            width = width,
+            cmd_width = cmd_width
+        ));
+    }
+    table
+}
+
+/// Write an edited config back to its file, first copying
+/// the old one to `<config>.bak` if `--backup` was passed.
+fn write_document(cli: &Cli, doc: &ConfigDocument, write: &ConfigWriteArgs) -> Result<()> {
+    if write.backup {
+        let path = conf::backup_path(&cli.config);
+        std::fs::copy(&cli.config, &path).map_err(|err| {
+            SeshError::ConfigWrite(format!(
+                "Failed to back up config to {}: {}",
+                conf::display_config_path(&path),
+                err
+            ))
+        })?;
+        if !cli.quiet {
+            println!("Backed up config to {}", conf::display_config_path(&path));
+        }
+    }
+    doc.write(&cli.config)
+}
+
+/// Add a window to the session config.
+pub fn run_window_add(cli: &Cli, args: &WindowAddArgs) -> Result<()> {
+    let mut doc = editable_config(cli)?;
+    // Make sure the existing config is valid before editing it
+    let config = doc.config()?;
+
+    // Build command vector from cmd + args
+    let command = args.cmd.as_ref().map(|cmd| {
+        let mut command = vec![cmd.clone()];
+        command.extend(args.args.clone());
+        command
+    });
+
+    // Without a `--name`, the window is named by the `window_naming` policy
+    let name = args
+        .name
+        .clone()
+        .or_else(|| config.default_window_name(command.as_deref(), config.window.len()));
+
+    // Two windows with the same name can't be told apart by `up`
+    if let Some(name) = &name
+        && !args.allow_duplicate
+        && let Some(index) = config
+            .window
+            .iter()
+            .position(|w| w.name.as_ref() == Some(name))
+    {
+        return Err(SeshError::DuplicateWindow {
+            window: name.clone(),
+            index,
+        });
+    }
+
+    // Create window config
+    let window_conf = WindowConf {
+        name,
+        command,
+        ..Default::default()
+    };
+
+    // Add to config
+    doc.push_window(&window_conf)?;
+
+    // Write updated config
+    write_document(cli, &doc, &args.write)?;
+
+    if !cli.quiet {
+        let name = window_conf.name.as_deref().unwrap_or("unnamed");
+        println!("Added window '{}' to config", name);
+    }
+
+    Ok(())
+}
+
+/// Remove a window from the session config
+pub fn run_window_remove(cli: &Cli, args: &WindowRemoveArgs) -> Result<()> {
+    let mut doc = editable_config(cli)?;
+    // Make sure the existing config is valid before editing it
+    doc.config()?;
+
+    if let Some(name) = &args.name {
+        // Find and remove windows by name, which may be a glob like `worker-*`
+        let pattern = glob::Pattern::new(name).map_err(|e| {
+            SeshError::Usage(format!("Invalid window name pattern '{}': {}", name, e))
+        })?;
+        let removed = doc.retain_windows(|n| !n.is_some_and(|n| pattern.matches(n)))?;
+
+        if removed == 0 {
+            return Err(SeshError::WindowNotInConfig(name.clone()));
+        }
+
+        // Write updated config
+        write_document(cli, &doc, &args.write)?;
+
+        if !cli.quiet {
+            if removed == 1 {
+                println!("Removed window '{}' from config", name);
+            } else {
+                println!(
+                    "Removed {} windows matching '{}' from config",
+                    removed, name
+                );
+            }
+        }
+    } else {
+        return Err(SeshError::Usage(
+            "Must specify --name to remove a window".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Set a window's `disabled` flag in the session config
+pub fn run_window_toggle(cli: &Cli, args: &WindowToggleArgs, disabled: bool) -> Result<()> {
+    let mut doc = editable_config(cli)?;
+    // Make sure the existing config is valid before editing it
+    doc.config()?;
+
+    doc.set_window_disabled(&args.name, disabled)?;
+    write_document(cli, &doc, &args.write)?;
+
+    if !cli.quiet {
+        let state = if disabled { "Disabled" } else { "Enabled" };
+        println!("{} window '{}' in config", state, args.name);
+    }
+    Ok(())
+}
+
+/// Move a window in the session config, and in the
+/// running session too if there is one
+pub fn run_window_move(cli: &Cli, args: &WindowMoveArgs) -> Result<()> {
+    run_window_move_with_backend(cli, args, &real_backend(cli))
+}
+
+fn run_window_move_with_backend<T: TmuxBackend>(
+    cli: &Cli,
+    args: &WindowMoveArgs,
+    backend: &T,
+) -> Result<()> {
+    let mut doc = editable_config(cli)?;
+    let config = doc.config()?;
+
+    doc.move_window(&args.name, args.to)?;
+    write_document(cli, &doc, &args.write)?;
+
+    if !cli.quiet {
+        println!(
+            "Moved window '{}' to index {} in config",
+            args.name, args.to
+        );
+    }
+
+    // Without tmux (or the session) there's nothing running to reorder
+    let session = &session_name(cli, &config);
+    if backend.check_available().is_err() {
+        return Ok(());
+    }
+    let Some(info) = backend.session_info(session)? else {
+        return Ok(());
+    };
+    let indices: Vec<_> = info.windows.iter().map(|w| w.index).collect();
+    let Some(from) = info.windows.iter().position(|w| w.name == args.name) else {
+        return Ok(());
+    };
+    let to = args.to.min(indices.len() - 1);
+
+    // Swap the window past each one in between, which leaves every index
+    // in place (`move-window -b` would shift them, and needs tmux 3.2)
+    if from < to {
+        for pos in from..to {
+            backend.swap_windows(session, indices[pos], indices[pos + 1])?;
+        }
+    } else {
+        for pos in (to..from).rev() {
+            backend.swap_windows(session, indices[pos + 1], indices[pos])?;
+        }
+    }
+
+    if !cli.quiet {
+        println!(
+            "Moved window '{}' to index {} in session '{}'",
+            args.name, indices[to], session
+        );
+    }
+    Ok(())
+}
+
+/// Renumber the running session's windows so their indices are
+/// contiguous again. Config windows are positional, so only the
+/// live session has indices to compact.
+pub fn run_window_renumber(cli: &Cli) -> Result<()> {
+    run_window_renumber_with_backend(cli, &real_backend(cli))
+}
+
+fn run_window_renumber_with_backend<T: TmuxBackend>(cli: &Cli, backend: &T) -> Result<()> {
+    check_tmux(cli, backend)?;
+
+    let config = load_config(cli)?;
+    let session = &session_name(cli, &config);
+    if !backend.has_session(session)? {
+        return Err(SeshError::SessionNotRunning(session.to_string()));
+    }
+
+    backend.renumber_windows(session)?;
+
+    if !cli.quiet {
+        println!("Renumbered windows in session '{}'", session);
+    }
+    Ok(())
+}
+
+/// Find a configured window by name or by index.
+fn find_window<'a>(
+    config: &'a Config,
+    name: Option<&str>,
+    index: Option<usize>,
+) -> Result<(usize, &'a WindowConf)> {
+    let found = match (name, index) {
+        (Some(name), _) => config
+            .window
+            .iter()
+            .enumerate()
+            .find(|(_, w)| w.name.as_deref() == Some(name)),
+        (None, Some(index)) => config.window.get(index).map(|w| (index, w)),
+        (None, None) => {
+            return Err(SeshError::Usage(
+                "Must specify --name or --index".to_string(),
+            ));
+        }
+    };
+
+    found.ok_or_else(|| {
+        SeshError::WindowNotInConfig(match name {
+            Some(name) => name.to_string(),
+            None => format!("#{}", index.unwrap_or_default()),
+        })
+    })
+}
+
+/// Print everything the config says about one window
+pub fn run_window_show(cli: &Cli, args: &WindowShowArgs) -> Result<()> {
+    let config = load_config(cli)?;
+    let (index, window) = find_window(&config, args.name.as_deref(), args.index)?;
+
+    if args.json {
+        println!("{}", serde_json::to_string(window)?);
+        return Ok(());
+    }
+
+    println!("{}", format_window_details(&config, index, window));
+    Ok(())
+}
+
+/// Print the config's windows, one per line with their index and command
+pub fn run_window_list(cli: &Cli, args: &WindowListArgs) -> Result<()> {
+    let config = load_config(cli)?;
+    for line in window_list(&config, args.sort) {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// Render `window list`'s lines in `sort` order. Windows keep
+/// their config index either way.
+fn window_list(config: &Config, sort: WindowSort) -> Vec<String> {
+    let mut windows: Vec<_> = config.window.iter().enumerate().collect();
+    if sort == WindowSort::Name {
+        // Stable, so unnamed windows stay in config order at the end
+        windows.sort_by_key(|(_, w)| (w.name.is_none(), w.name.as_deref()));
+    }
+
+    windows
+        .into_iter()
+        .map(|(idx, window)| {
+            let command = format_commands(config, window);
+            let disabled = if window.disabled == Some(true) {
+                " [disabled]"
+            } else {
+                ""
+            };
+            format!(
+                "{}: {} ({}){}",
+                idx,
+                window.name.as_deref().unwrap_or("unnamed"),
+                command,
+                disabled
+            )
+        })
+        .collect()
+}
+
+/// Show the command lines a window runs, separated by `; `, or `-`
+/// if there aren't any.
+fn format_commands(config: &Config, window: &WindowConf) -> String {
+    let commands = config.window_commands(window);
+    if commands.is_empty() {
+        return "-".to_string();
+    }
+    commands
+        .iter()
+        .map(|command| command.join(" "))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Render a window's settings as a readable block, filling in the
+/// command and directory it inherits from the top level.
+fn format_window_details(config: &Config, index: usize, window: &WindowConf) -> String {
+    let command = |command: Option<&Vec<String>>| command.map_or("-".to_string(), |c| c.join(" "));
+    let path = |path: Option<&PathBuf>| path.map_or("-".to_string(), |p| p.display().to_string());
+    let or_dash = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+
+    let mut lines = vec![
+        format!(
+            "Window {}: {}",
+            index,
+            window.name.as_deref().unwrap_or("unnamed")
+        ),
+        format!("  keys:         {}", command(window.keys.as_ref())),
+        format!("  command:      {}", format_commands(config, window)),
+        format!("  cwd:          {}", path(config.window_cwd(window))),
+        format!("  default:      {}", window.default.unwrap_or(false)),
+        format!("  layout:       {}", or_dash(window.layout.clone())),
+        format!(
+            "  focus:        {}",
+            or_dash(window.focus.map(|f| f.to_string()))
+        ),
+        format!("  synchronize:  {}", window.synchronize.unwrap_or(false)),
+        format!("  allow_rename: {}", window.allow_rename.unwrap_or(false)),
+        format!("  clear_line:   {}", window.clear_line.unwrap_or(false)),
+    ];
+
+    let panes = window.pane.as_deref().unwrap_or_default();
+    if !panes.is_empty() {
+        lines.push("  panes:".to_string());
+        for (idx, pane) in panes.iter().enumerate() {
+            lines.push(format!(
+                "    {}: {} (cwd: {})",
+                idx + 1,
+                command(pane.command.as_ref()),
+                path(pane.cwd.as_ref().or(config.window_cwd(window)))
+            ));
+        }
+    }
+
+    lines.join("\n")
+}
+
+/// Print the resolved config path, or fail without printing anything
+/// if the file doesn't exist (the error names the path instead).
+pub fn run_config_path(cli: &Cli) -> Result<()> {
+    if !cli.config.exists() {
+        return Err(SeshError::ConfigNotFound(cli.config.clone()));
+    }
+
+    println!("{}", conf::display_config_path(&cli.config));
+    Ok(())
+}
+
+/// Print the effective config, as TOML or JSON
+pub fn run_config_dump(cli: &Cli, args: &ConfigDumpArgs) -> Result<()> {
+    let config = effective_config(cli)?;
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&config)?);
+    } else {
+        print!("{}", toml::to_string(&config)?);
+    }
+    Ok(())
+}
+
+/// Load the config with its defaults applied and the CLI's overrides
+/// (session name, shell) on top, resolving directories against the
+/// config file's directory like `up` does.
+fn effective_config(cli: &Cli) -> Result<Config> {
+    let mut config = expanded(load_config(cli)?);
+    config.apply_defaults();
+    config.name = session_name(cli, &config);
+    if let Some(shell) = &cli.shell {
+        config.shell = Some(shell.clone());
+    }
+
+    let resolve = |dir: &mut Option<PathBuf>| -> Result<()> {
+        if let Some(dir) = dir {
+            *dir = resolve_dir(cli, dir)?;
+        }
+        Ok(())
+    };
+    resolve(&mut config.cwd)?;
+    for window in &mut config.window {
+        resolve(&mut window.cwd)?;
+        resolve(&mut window.log_file)?;
+        for pane in window.pane.iter_mut().flatten() {
+            resolve(&mut pane.cwd)?;
+        }
+    }
+    Ok(config)
+}
+
+/// Print the config file's JSON Schema
+pub fn run_schema() -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(&Config::json_schema())?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tmux::{MockTmuxBackend, TmuxBackend};
+    use tempfile::TempDir;
+
+    fn status_args() -> StatusArgs {
+        StatusArgs {
+            watch: false,
+            interval: 2,
+            json: false,
+            format: None,
+            show_age: false,
+            window: None,
+            tag: None,
+        }
     }
 
     // Helper to create a test CLI with temp config
@@ -381,85 +3007,3497 @@ mod tests {
         let config_path = temp_dir.path().join(".seshconf.toml");
         std::fs::write(&config_path, config_content)?;
 
-        Ok(Cli {
-            command: crate::cli::Command::Status,
-            config: config_path,
-            quiet: true,
-        })
+        Ok(Cli {
+            command: crate::cli::Command::Status(status_args()),
+            config: config_path,
+            config_format: None,
+            config_dir: None,
+            quiet: true,
+            silent: false,
+            color: crate::cli::ColorChoice::Never,
+            session_name: None,
+            prefix: None,
+            overlay: None,
+            shell: None,
+            cwd: None,
+            tmux_bin: PathBuf::from("tmux"),
+            retries: 0,
+            global_config: false,
+        })
+    }
+
+    #[test]
+    fn test_shows_error() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut cli = create_test_cli(&temp_dir, "name = \"errors\"")?;
+        let not_running = SeshError::SessionNotRunning("errors".to_string());
+        let failed = SeshError::Tmux("oops".to_string());
+
+        // --quiet only hides "not running" errors
+        assert!(!cli.shows_error(&not_running));
+        assert!(cli.shows_error(&failed));
+
+        cli.silent = true;
+        assert!(!cli.shows_error(&failed));
+
+        cli.quiet = false;
+        cli.silent = false;
+        assert!(cli.shows_error(&not_running));
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_path() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut cli = create_test_cli(&temp_dir, "name = \"found\"")?;
+        run_config_path(&cli)?;
+
+        cli.config = temp_dir.path().join("missing.toml");
+        assert!(matches!(
+            run_config_path(&cli),
+            Err(SeshError::ConfigNotFound(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_init_with_template() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cli = create_test_cli(&temp_dir, "")?;
+
+        let args = InitArgs {
+            name: Some("rusty".to_string()),
+            overwrite: true,
+            template: Some(crate::cli::Template::Rust),
+            json: false,
+            print: None,
+        };
+        run_init_with_backend(&cli, &args, &MockTmuxBackend::new())?;
+
+        let config = Config::load(&cli.config)?;
+        assert_eq!(config.name, "rusty");
+        let names: Vec<_> = config.window.iter().map(|w| w.name.as_deref()).collect();
+        assert_eq!(names, vec![Some("editor"), Some("watch"), Some("shell")]);
+        assert_eq!(config.window[0].default, Some(true));
+        assert_eq!(config.window[2].command, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_init_print() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cli = create_test_cli(&temp_dir, "")?;
+
+        let args = InitArgs {
+            name: None,
+            overwrite: true,
+            template: None,
+            json: false,
+            print: Some(InitField::Name),
+        };
+        run_init_with_backend(&cli, &args, &MockTmuxBackend::new())?;
+
+        // The printed name is the one written to the config
+        let config = Config::load(&cli.config)?;
+        assert!(!config.name.is_empty());
+        assert_eq!(
+            init_field(InitField::Name, &cli.config, &config.name),
+            config.name
+        );
+        assert_eq!(
+            init_field(InitField::Path, &cli.config, &config.name),
+            conf::display_config_path(&cli.config)
+        );
+        let relative = init_field(InitField::Path, Path::new(".seshconf.toml"), &config.name);
+        assert!(Path::new(&relative).is_absolute());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_init_writes_sample_window() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cli = create_test_cli(&temp_dir, "")?;
+
+        let args = InitArgs {
+            name: Some("fresh".to_string()),
+            overwrite: true,
+            template: None,
+            json: false,
+            print: None,
+        };
+        run_init_with_backend(&cli, &args, &MockTmuxBackend::new())?;
+
+        let config = Config::load(&cli.config)?;
+        assert_eq!(config.window.len(), 1);
+        assert_eq!(config.window[0].name.as_deref(), Some("fresh"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tag_matches() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cli = create_test_cli(&temp_dir, "name = \"tagged\"\ntags = [\"work\", \"api\"]")?;
+        let config = load_config(&cli)?;
+
+        assert!(tag_matches(&cli, &config, None));
+        assert!(tag_matches(&cli, &config, Some("work")));
+        assert!(!tag_matches(&cli, &config, Some("personal")));
+
+        // Untagged configs only match without a filter
+        let cli = create_test_cli(&temp_dir, "name = \"untagged\"")?;
+        let config = load_config(&cli)?;
+        assert!(tag_matches(&cli, &config, None));
+        assert!(!tag_matches(&cli, &config, Some("work")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tag_skips_commands() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "tagged"
+tags = ["work"]
+
+[[window]]
+name = "editor"
+"#;
+        let cli = create_test_cli(&temp_dir, config_content)?;
+
+        // Another tag leaves the session alone
+        let backend = MockTmuxBackend::new();
+        let args = UpArgs {
+            tag: Some("personal".to_string()),
+            ..Default::default()
+        };
+        run_up_with_backend(&cli, &args, &backend)?;
+        assert!(backend.get_sessions().is_empty());
+
+        let backend = MockTmuxBackend::new().with_session("tagged", vec!["editor"]);
+        let args = DownArgs {
+            tag: Some("personal".to_string()),
+            ..Default::default()
+        };
+        run_down_with_backend(&cli, &args, &backend)?;
+        assert!(backend.has_session("tagged")?);
+
+        // The matching tag acts as usual
+        let args = DownArgs {
+            tag: Some("work".to_string()),
+            ..Default::default()
+        };
+        run_down_with_backend(&cli, &args, &backend)?;
+        assert!(!backend.has_session("tagged")?);
+
+        // `status --quiet` can't report a skipped session as running
+        let backend = MockTmuxBackend::new().with_session("tagged", vec!["editor"]);
+        let mut args = StatusArgs {
+            tag: Some("personal".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            run_status_with_backend(&cli, &args, &backend),
+            Err(SeshError::NotTagged { .. })
+        ));
+        args.tag = Some("work".to_string());
+        run_status_with_backend(&cli, &args, &backend)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rand_session_name_retries() -> Result<()> {
+        let tried = std::cell::RefCell::new(vec![]);
+        let name = rand_session_name(|name| {
+            tried.borrow_mut().push(name.to_string());
+            tried.borrow().len() < 3
+        })?;
+        assert_eq!(tried.borrow().len(), 3);
+        assert_eq!(name, tried.borrow()[2]);
+
+        // Gives up after a few attempts, rather than using a taken name
+        tried.borrow_mut().clear();
+        let result = rand_session_name(|name| {
+            tried.borrow_mut().push(name.to_string());
+            true
+        });
+        assert!(result.is_err());
+        assert_eq!(tried.borrow().len(), NAME_ATTEMPTS);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_status_session_not_running() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "test-session"
+
+[[window]]
+name = "editor"
+command = ["vim", "."]
+"#;
+
+        let mut cli = create_test_cli(&temp_dir, config_content)?;
+        cli.quiet = false;
+        let backend = MockTmuxBackend::new();
+
+        // Session doesn't exist, should succeed without error
+        // (with `--quiet` it's reported by the exit code instead)
+        let result = run_status_with_backend(&cli, &status_args(), &backend);
+        assert!(result.is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_status_session_running() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "test-session"
+
+[[window]]
+name = "editor"
+command = ["vim", "."]
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new().with_session("test-session", vec!["editor"]);
+
+        let result = run_status_with_backend(&cli, &status_args(), &backend);
+        assert!(result.is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_creates_new_session() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "new-session"
+
+[[window]]
+name = "terminal"
+command = ["bash"]
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new();
+
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+
+        let sessions = backend.get_sessions();
+        assert!(sessions.contains_key("new-session"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_continues_past_failed_window() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "partial"
+
+[[window]]
+name = "editor"
+command = ["vim"]
+
+[[window]]
+name = "server"
+command = ["no-such-server"]
+
+[[window]]
+name = "logs"
+command = ["tail", "-f", "log"]
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new().with_rejected_command("no-such-server");
+
+        let err = run_up_with_backend(&cli, &UpArgs::default(), &backend).unwrap_err();
+        let SeshError::WindowsFailed { session, failures } = err else {
+            panic!("expected WindowsFailed, got {:?}", err);
+        };
+        assert_eq!(session, "partial");
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].starts_with("server: "));
+
+        // The windows after the failed one were still set up
+        let commands: Vec<_> = backend
+            .get_commands_sent()
+            .into_iter()
+            .map(|(_, idx, command)| (idx, command.join(" ")))
+            .collect();
+        assert!(commands.contains(&(0, "vim".to_string())));
+        assert!(commands.contains(&(2, "tail -f log".to_string())));
+
+        // With --fail-fast, the command's own error comes back as is
+        let backend = MockTmuxBackend::new().with_rejected_command("no-such-server");
+        let args = UpArgs {
+            fail_fast: true,
+            ..Default::default()
+        };
+        let err = run_up_with_backend(&cli, &args, &backend).unwrap_err();
+        assert!(matches!(err, SeshError::Tmux(_)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_skips_window_that_fails_to_create() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "partial"
+
+[[window]]
+name = "editor"
+
+[[window]]
+name = "broken"
+command = ["make"]
+
+[[window]]
+name = "logs"
+command = ["tail", "-f", "log"]
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new().with_rejected_window("broken");
+
+        let err = run_up_with_backend(&cli, &UpArgs::default(), &backend).unwrap_err();
+        assert!(matches!(err, SeshError::WindowsFailed { .. }));
+
+        // `logs` moved down into the failed window's place
+        assert_eq!(backend.list_windows("partial")?, vec!["editor", "logs"]);
+        assert_eq!(
+            backend.get_commands_sent(),
+            vec![(
+                "partial".to_string(),
+                1,
+                vec!["tail".to_string(), "-f".to_string(), "log".to_string()]
+            )]
+        );
+
+        let backend = MockTmuxBackend::new().with_rejected_window("broken");
+        let args = UpArgs {
+            fail_fast: true,
+            ..Default::default()
+        };
+        run_up_with_backend(&cli, &args, &backend).unwrap_err();
+        assert_eq!(backend.list_windows("partial")?, vec!["editor"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_sends_setup_first() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "setup"
+setup = ["source", ".venv/bin/activate"]
+
+[[window]]
+name = "shell"
+command = ["python"]
+
+[[window]]
+name = "server"
+command = ["flask", "run"]
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new();
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+
+        let sent: Vec<_> = backend
+            .get_commands_sent()
+            .into_iter()
+            .map(|(_, idx, command)| (idx, command.join(" ")))
+            .collect();
+        assert_eq!(sent[0], (0, "source .venv/bin/activate".to_string()));
+        assert_eq!(sent.len(), 3);
+        assert!(sent.contains(&(0, "python".to_string())));
+        assert!(sent.contains(&(1, "flask run".to_string())));
+
+        // Not sent again to a session that's already running
+        let backend = MockTmuxBackend::new().with_session("setup", vec!["shell"]);
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+        assert_eq!(
+            backend.get_commands_sent(),
+            vec![(
+                "setup".to_string(),
+                1,
+                vec!["flask".to_string(), "run".to_string()]
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_uses_tmux_window_indices() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "indices"
+setup = ["source", ".venv/bin/activate"]
+
+[[window]]
+name = "shell"
+command = ["python"]
+
+[[window]]
+name = "server"
+command = ["flask", "run"]
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let sent = |backend: &MockTmuxBackend| -> Vec<(usize, String)> {
+            backend
+                .get_commands_sent()
+                .into_iter()
+                .map(|(_, idx, command)| (idx, command.join(" ")))
+                .collect()
+        };
+
+        // With base-index 1, windows start at 1 rather than 0
+        let backend = MockTmuxBackend::new().with_base_index(1);
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+        let mut commands = sent(&backend);
+        commands.sort();
+        assert_eq!(
+            commands,
+            [
+                (1, "python"),
+                (1, "source .venv/bin/activate"),
+                (2, "flask run")
+            ]
+            .map(|(idx, command)| (idx, command.to_string()))
+        );
+
+        // A gap left by a closed window doesn't throw off where the new
+        // window goes or where its command is sent
+        let backend = MockTmuxBackend::new()
+            .with_indexed_session("indices", vec![(1, "shell"), (4, "scratch")]);
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+        let info = backend.session_info("indices")?.unwrap();
+        let windows: Vec<_> = info
+            .windows
+            .iter()
+            .map(|w| (w.index, w.name.as_str()))
+            .collect();
+        assert_eq!(windows, [(1, "shell"), (2, "server"), (4, "scratch")]);
+        assert_eq!(sent(&backend), [(2, "flask run".to_string())]);
+
+        // Or one before the first window
+        let backend = MockTmuxBackend::new().with_indexed_session("indices", vec![(3, "server")]);
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+        assert_eq!(backend.list_windows("indices")?, ["shell", "server"]);
+        assert_eq!(sent(&backend), [(3, "python".to_string())]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_max_windows() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "runaway"
+
+[[window]]
+name = "worker"
+count = 5
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new();
+        let mut args = UpArgs {
+            max_windows: 4,
+            ..Default::default()
+        };
+
+        // Nothing is created once the expanded windows are over the limit
+        let err = run_up_with_backend(&cli, &args, &backend).unwrap_err();
+        assert!(matches!(err, SeshError::Usage(_)));
+        assert!(backend.get_sessions().is_empty());
+
+        args.max_windows = 5;
+        run_up_with_backend(&cli, &args, &backend)?;
+        assert_eq!(backend.list_windows("runaway")?.len(), 5);
+
+        // A huge count is refused before its windows are expanded
+        let cli = create_test_cli(
+            &temp_dir,
+            "name = \"huge\"\n[[window]]\ncount = 100000000\n",
+        )?;
+        let err = run_up_with_backend(&cli, &UpArgs::default(), &backend).unwrap_err();
+        assert!(err.to_string().contains("100000000 windows"));
+        let args = UpArgs {
+            attach_window: Some("window".to_string()),
+            ..Default::default()
+        };
+        assert!(run_up_with_backend(&cli, &args, &backend).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_names_first_window_on_create() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "named"
+
+[[window]]
+name = "editor"
+command = ["vim"]
+
+[[window]]
+name = "server"
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new();
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+
+        assert_eq!(backend.list_windows("named")?, vec!["editor", "server"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_single_window_reuses_window_0() -> Result<()> {
+        let named = r#"
+name = "single"
+
+[[window]]
+name = "editor"
+command = ["vim"]
+"#;
+        let unnamed = r#"
+name = "single"
+
+[[window]]
+command = ["vim"]
+"#;
+
+        for config_content in [named, unnamed] {
+            let temp_dir = TempDir::new()?;
+            let cli = create_test_cli(&temp_dir, config_content)?;
+            let backend = MockTmuxBackend::new();
+
+            run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+
+            // The command goes into the session's initial window
+            assert_eq!(backend.list_windows("single")?.len(), 1);
+            assert_eq!(
+                backend.get_commands_sent(),
+                vec![("single".to_string(), 0, vec!["vim".to_string()])]
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_with_multiple_windows() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "multi-window"
+
+[[window]]
+name = "editor"
+command = ["vim"]
+
+[[window]]
+name = "server"
+command = ["npm", "run", "dev"]
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new();
+
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+
+        let sessions = backend.get_sessions();
+        assert!(sessions.contains_key("multi-window"));
+
+        let windows = &sessions["multi-window"];
+        assert!(windows.contains(&"server".to_string()));
+
+        let commands = backend.get_commands_sent();
+        assert!(!commands.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_window_count() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "workers"
+
+[[window]]
+name = "worker"
+command = ["./worker"]
+count = 3
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new();
+
+        // The config keeps the window as written
+        let config = load_config(&cli)?;
+        assert_eq!(config.window.len(), 1);
+        assert_eq!(window_list(&config, WindowSort::Index).len(), 1);
+
+        // `--only` names the window from the file and gets every copy
+        let args = UpArgs {
+            only: vec!["worker".to_string()],
+            ..Default::default()
+        };
+        run_up_with_backend(&cli, &args, &backend)?;
+
+        let sessions = backend.get_sessions();
+        assert_eq!(
+            sessions["workers"],
+            vec![
+                "worker-1".to_string(),
+                "worker-2".to_string(),
+                "worker-3".to_string()
+            ]
+        );
+        let commands = backend.get_commands_sent();
+        assert_eq!(commands.len(), 3);
+        assert!(commands.iter().all(|(_, _, cmd)| cmd == &["./worker"]));
+
+        let (report, _) = status_report(&cli, &load_config(&cli)?, None, &backend)?;
+        assert!(
+            report
+                .windows
+                .iter()
+                .all(|w| w.state == WindowState::Running)
+        );
+        assert_eq!(report.windows.len(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_creates_panes_with_layout() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "panes"
+
+[[window]]
+name = "single"
+layout = "tiled"
+
+[[window]]
+name = "split"
+command = ["vim"]
+layout = "main-vertical"
+
+[[window.pane]]
+command = ["cargo", "watch"]
+
+[[window.pane]]
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new();
+
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+
+        assert_eq!(
+            backend.get_splits(),
+            vec![("panes".to_string(), 1), ("panes".to_string(), 1)]
+        );
+
+        // The single-pane window's layout is a no-op
+        assert_eq!(
+            backend.get_layouts(),
+            vec![("panes".to_string(), 1, "main-vertical".to_string())]
+        );
+
+        let commands = backend.get_commands_sent();
+        assert!(commands.contains(&(
+            "panes".to_string(),
+            1,
+            vec!["cargo".to_string(), "watch".to_string()]
+        )));
+
+        // Off unless asked for
+        assert!(backend.get_synchronized().is_empty());
+
+        // Back on the window's own pane
+        assert_eq!(backend.get_focused(), vec![("panes".to_string(), 1, 0)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_synchronizes_panes() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "sync"
+
+[[window]]
+name = "hosts"
+command = ["ssh", "web-1"]
+synchronize = true
+focus = 1
+
+[[window.pane]]
+command = ["ssh", "web-2"]
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new();
+
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+
+        assert_eq!(
+            backend.get_synchronized(),
+            vec![("sync".to_string(), 0, true)]
+        );
+        assert_eq!(backend.get_focused(), vec![("sync".to_string(), 0, 1)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_preserves_window_order() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "ordered"
+
+[[window]]
+name = "editor"
+
+[[window]]
+name = "server"
+command = ["npm", "run", "dev"]
+
+[[window]]
+name = "tests"
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new().with_session("ordered", vec!["editor", "tests"]);
+
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+
+        // The missing middle window is inserted between its neighbours
+        assert_eq!(
+            backend.get_sessions()["ordered"],
+            vec!["editor", "server", "tests"]
+        );
+        assert_eq!(
+            backend.get_commands_sent(),
+            vec![(
+                "ordered".to_string(),
+                1,
+                vec!["npm".to_string(), "run".to_string(), "dev".to_string()]
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_reorders_window_setup() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        // The config's order differs from the running session's, so `y`
+        // is created before `x`, after `x`'s command was planned
+        let config_content = r#"
+name = "shuffled"
+
+[[window]]
+name = "b"
+
+[[window]]
+name = "x"
+command = ["echo", "x"]
+
+[[window]]
+name = "a"
+
+[[window]]
+name = "y"
+command = ["echo", "y"]
+"#;
+        let echo = |word: &str| vec!["echo".to_string(), word.to_string()];
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new().with_session("shuffled", vec!["a", "b"]);
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+
+        // Each command still goes to its own window
+        assert_eq!(backend.get_sessions()["shuffled"], vec!["a", "y", "b", "x"]);
+        assert_eq!(
+            backend.get_commands_sent(),
+            vec![
+                ("shuffled".to_string(), 3, echo("x")),
+                ("shuffled".to_string(), 1, echo("y")),
+            ]
+        );
+
+        // The same goes when the window created before it fails
+        let backend = MockTmuxBackend::new()
+            .with_session("shuffled", vec!["a", "b"])
+            .with_rejected_window("y");
+        run_up_with_backend(&cli, &UpArgs::default(), &backend).unwrap_err();
+        assert_eq!(backend.get_sessions()["shuffled"], vec!["a", "b", "x"]);
+        assert_eq!(
+            backend.get_commands_sent(),
+            vec![("shuffled".to_string(), 2, echo("x"))]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_parallel_jobs() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut config_content = "name = \"parallel\"\n".to_string();
+        for i in 0..8 {
+            config_content.push_str(&format!(
+                "\n[[window]]\nname = \"w{i}\"\ncommand = [\"run\", \"{i}\"]\n\n\
+                 [[window.pane]]\ncommand = [\"pane\", \"{i}\"]\n"
+            ));
+        }
+
+        let cli = create_test_cli(&temp_dir, &config_content)?;
+        let serial = MockTmuxBackend::new();
+        let args = UpArgs {
+            jobs: 1,
+            ..Default::default()
+        };
+        run_up_with_backend(&cli, &args, &serial)?;
+
+        let parallel = MockTmuxBackend::new();
+        let args = UpArgs {
+            jobs: 4,
+            ..Default::default()
+        };
+        run_up_with_backend(&cli, &args, &parallel)?;
+
+        // Windows are still created in config order
+        let names: Vec<String> = (0..8).map(|i| format!("w{i}")).collect();
+        assert_eq!(parallel.get_sessions()["parallel"], names);
+
+        // Each window gets the same commands, in the same order,
+        // though windows may be interleaved
+        let mut serial_commands = serial.get_commands_sent();
+        let mut parallel_commands = parallel.get_commands_sent();
+        for i in 0..8 {
+            let window = |commands: &[(String, usize, Vec<String>)]| {
+                commands
+                    .iter()
+                    .filter(|(_, idx, _)| *idx == i)
+                    .cloned()
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(window(&serial_commands), window(&parallel_commands));
+        }
+        serial_commands.sort();
+        parallel_commands.sort();
+        assert_eq!(serial_commands, parallel_commands);
+        assert_eq!(parallel.get_splits().len(), 8);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_up() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "planned"
+
+[[window]]
+name = "editor"
+command = ["vim", "."]
+
+[[window]]
+name = "server"
+keys = ["C-c"]
+command = ["npm", "run", "dev"]
+
+[[window.pane]]
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new().with_session("planned", vec!["editor"]);
+
+        let plan = plan_up(&cli, &UpArgs::default(), load_config(&cli)?, &backend)?;
+        assert!(!plan.already_up);
+        assert_eq!(
+            plan.actions,
+            vec![
+                UpAction::KeepWindow {
+                    label: "editor".to_string()
+                },
+                UpAction::CreateWindow {
+                    position: 1,
+                    name: Some("server".to_string()),
+                    label: "server".to_string(),
+                    dir: None,
+                },
+                UpAction::DisableRename {
+                    index: 1,
+                    label: "server".to_string(),
+                },
+                UpAction::SendKeys {
+                    index: 1,
+                    label: "server".to_string(),
+                    keys: vec!["C-c".to_string()],
+                },
+                UpAction::SendCommand {
+                    index: 1,
+                    label: "server".to_string(),
+                    command: vec!["npm".to_string(), "run".to_string(), "dev".to_string()],
+                    rebuild: false,
+                    clear_line: false,
+                },
+                UpAction::CreatePanes {
+                    index: 1,
+                    label: "server".to_string(),
+                    window: Box::new(Config::load(&cli.config)?.window[1].clone()),
+                    dir: None,
+                },
+            ]
+        );
+
+        let lines: Vec<_> = plan
+            .actions
+            .iter()
+            .map(|action| action.describe(&plan.session))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "keep window 'editor' (already running)",
+                "create window 'server' at position 1",
+                "turn off automatic-rename for 'server'",
+                "send keys C-c to 'server'",
+                "send 'npm run dev' to 'server'",
+                "create 1 pane(s) in 'server'",
+            ]
+        );
+
+        // A new session's first window is named when it's created,
+        // so there's nothing to rename before the next window
+        let plan = plan_up(
+            &cli,
+            &UpArgs::default(),
+            load_config(&cli)?,
+            &MockTmuxBackend::new(),
+        )?;
+        assert_eq!(
+            plan.actions[..3],
+            [
+                UpAction::CreateSession {
+                    dir: None,
+                    shell: None,
+                    window_name: Some("editor".to_string()),
+                },
+                UpAction::RecordConfig(conf::display_config_path(&cli.config)),
+                UpAction::CreateWindow {
+                    position: 1,
+                    name: Some("server".to_string()),
+                    label: "server".to_string(),
+                    dir: None,
+                },
+            ]
+        );
+
+        // Nothing to do once everything is running
+        let backend = MockTmuxBackend::new().with_session("planned", vec!["editor", "server"]);
+        let plan = plan_up(&cli, &UpArgs::default(), load_config(&cli)?, &backend)?;
+        assert!(plan.already_up);
+        assert!(plan.actions.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_records_config() -> Result<()> {
+        let config_content = r#"
+name = "shared"
+
+[[window]]
+name = "editor"
+"#;
+        let temp_dir = TempDir::new()?;
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new();
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+        assert_eq!(
+            backend.show_environment("shared", CONFIG_ENV)?,
+            Some(conf::display_config_path(&cli.config))
+        );
+        assert_eq!(other_config(&cli, &backend, "shared")?, None);
+
+        // Another project's config with the same session name
+        let other_dir = TempDir::new()?;
+        let other_cli = create_test_cli(&other_dir, config_content)?;
+        assert_eq!(
+            other_config(&other_cli, &backend, "shared")?,
+            Some(conf::display_config_path(&cli.config))
+        );
+
+        // Sessions started without a marker aren't flagged
+        let backend = MockTmuxBackend::new().with_session("shared", vec!["editor"]);
+        assert_eq!(other_config(&cli, &backend, "shared")?, None);
+        let backend = backend.with_environment("shared", CONFIG_ENV, "/elsewhere/.seshconf.toml");
+        assert_eq!(
+            other_config(&cli, &backend, "shared")?,
+            Some("/elsewhere/.seshconf.toml".to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_dry_run() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "dry"
+
+[[window]]
+name = "editor"
+command = ["vim"]
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new();
+        let args = UpArgs {
+            dry_run: true,
+            ..Default::default()
+        };
+
+        let report = run_up_with_backend(&cli, &args, &backend)?;
+        assert_eq!(report.created, Some(true));
+        assert!(backend.get_sessions().is_empty());
+        assert!(backend.get_commands_sent().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_disables_automatic_rename() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "renames"
+
+[[window]]
+name = "editor"
+
+[[window]]
+name = "htop"
+command = ["htop"]
+allow_rename = true
+
+[[window]]
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new();
+
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+
+        // Off for every new window unless it opts in
+        assert_eq!(
+            backend.get_automatic_rename(),
+            vec![
+                ("renames".to_string(), 0, false),
+                ("renames".to_string(), 2, false),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_uses_default_command() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "defaults"
+default_command = ["zsh", "-l"]
+
+[[window]]
+name = "inherits"
+
+[[window]]
+name = "overrides"
+command = ["htop"]
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new();
+
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+
+        assert_eq!(
+            backend.get_commands_sent(),
+            vec![
+                (
+                    "defaults".to_string(),
+                    0,
+                    vec!["zsh".to_string(), "-l".to_string()]
+                ),
+                ("defaults".to_string(), 1, vec!["htop".to_string()]),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_and_down_reports() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "report"
+
+[[window]]
+name = "editor"
+
+[[window]]
+name = "server"
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new().with_session("report", vec!["editor"]);
+
+        let report = run_up_with_backend(
+            &cli,
+            &UpArgs {
+                json: true,
+                ..Default::default()
+            },
+            &backend,
+        )?;
+        assert_eq!(
+            serde_json::to_value(&report)?,
+            serde_json::json!({
+                "session": "report",
+                "running": true,
+                "created": false,
+                "windows": [
+                    {"name": "editor", "state": "existing"},
+                    {"name": "server", "state": "created"},
+                ],
+            })
+        );
+
+        let report = run_down_with_backend(
+            &cli,
+            &DownArgs {
+                json: true,
+                ..Default::default()
+            },
+            &backend,
+        )?;
+        assert_eq!(report.killed, Some(true));
+        assert!(!report.running);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_start_dirs() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "dirs"
+cwd = "app"
+
+[[window]]
+name = "editor"
+
+[[window]]
+name = "logs"
+cwd = "/var/log"
+
+[[window.pane]]
+command = ["tail", "-f", "syslog"]
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new();
+
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+
+        let app_dir = std::path::absolute(temp_dir.path().join("app"))?;
+        assert_eq!(
+            backend.get_start_dirs(),
+            vec![
+                ("dirs:0".to_string(), app_dir),
+                ("dirs:1".to_string(), PathBuf::from("/var/log")),
+                ("dirs:1".to_string(), PathBuf::from("/var/log")),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_with_cwd() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "elsewhere"
+cwd = "app"
+
+[[window]]
+name = "editor"
+cwd = "src"
+
+[[window]]
+name = "shell"
+"#;
+
+        // `--cwd project/sub` makes main look for the config from there,
+        // which finds it in the project
+        let mut cli = create_test_cli(&temp_dir, config_content)?;
+        let sub = temp_dir.path().join("sub");
+        std::fs::create_dir(&sub)?;
+        let (config, global) =
+            conf::resolve_config(Some(&sub), Path::new(".seshconf.toml"), false, None);
+        assert!(!global);
+        cli.config = config;
+
+        // Relative directories are still resolved against the config
+        let backend = MockTmuxBackend::new();
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+        assert_eq!(
+            backend.get_start_dirs(),
+            vec![
+                ("elsewhere:0".to_string(), temp_dir.path().join("src")),
+                ("elsewhere:1".to_string(), temp_dir.path().join("app")),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_preserve_pwd() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "pwd"
+cwd = "app"
+
+[[window]]
+name = "editor"
+cwd = "src"
+
+[[window]]
+name = "logs"
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new();
+        let args = UpArgs {
+            preserve_pwd: true,
+            ..Default::default()
+        };
+
+        run_up_with_backend(&cli, &args, &backend)?;
+
+        // Only window 0 moves; the rest still use the config's `cwd`
+        let app_dir = std::path::absolute(temp_dir.path().join("app"))?;
+        assert_eq!(
+            backend.get_start_dirs(),
+            vec![
+                ("pwd:0".to_string(), std::env::current_dir()?),
+                ("pwd:1".to_string(), app_dir),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_pane_start_dirs() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "panes"
+cwd = "app"
+
+[[window]]
+name = "repo"
+cwd = "/srv/repo"
+
+[[window.pane]]
+command = ["git", "log"]
+
+[[window.pane]]
+command = ["make"]
+cwd = "build"
+
+[[window]]
+name = "shell"
+
+[[window.pane]]
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new();
+
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+
+        // Pane, then window, then session. Both windows are created
+        // before either is split.
+        let app_dir = std::path::absolute(temp_dir.path().join("app"))?;
+        let build_dir = std::path::absolute(temp_dir.path().join("build"))?;
+        let repo_dir = PathBuf::from("/srv/repo");
+        assert_eq!(
+            backend.get_start_dirs(),
+            vec![
+                ("panes:0".to_string(), repo_dir.clone()),
+                ("panes:1".to_string(), app_dir.clone()),
+                ("panes:0".to_string(), repo_dir),
+                ("panes:0".to_string(), build_dir),
+                ("panes:1".to_string(), app_dir),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_with_shell() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "shell"
+shell = "/bin/sh"
+
+[[window]]
+name = "one"
+
+[[window]]
+name = "two"
+"#;
+
+        let mut cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new();
+
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+        let sh = PathBuf::from("/bin/sh");
+        assert_eq!(
+            backend.get_shells(),
+            vec![
+                ("shell:0".to_string(), sh.clone()),
+                ("shell".to_string(), sh)
+            ]
+        );
+
+        // The flag wins, and has to exist
+        cli.shell = Some("/no/such/shell".to_string());
+        let err = run_up_with_backend(&cli, &UpArgs::default(), &MockTmuxBackend::new());
+        assert!(matches!(err, Err(SeshError::ShellNotFound(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_only_and_except() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "subset"
+
+[[window]]
+name = "editor"
+
+[[window]]
+name = "server"
+command = ["npm", "run", "dev"]
+
+[[window]]
+name = "db"
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let windows = |args: UpArgs| -> Result<Vec<String>> {
+            let backend = MockTmuxBackend::new();
+            run_up_with_backend(&cli, &args, &backend)?;
+            Ok(backend.get_sessions()["subset"].clone())
+        };
+
+        let only = UpArgs {
+            only: vec!["server".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(windows(only)?, vec!["server".to_string()]);
+
+        let except = UpArgs {
+            except: vec!["server".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            windows(except)?,
+            vec!["editor".to_string(), "db".to_string()]
+        );
+
+        let unknown = UpArgs {
+            only: vec!["bogus".to_string()],
+            ..Default::default()
+        };
+        let backend = MockTmuxBackend::new();
+        let err = run_up_with_backend(&cli, &unknown, &backend).unwrap_err();
+        assert!(matches!(err, SeshError::WindowNotInConfig(name) if name == "bogus"));
+        assert!(backend.get_sessions().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_wait() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "waiting"
+
+[[window]]
+name = "editor"
+
+[[window]]
+name = "server"
+command = ["npm", "run", "dev"]
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new()
+            .with_session("waiting", vec!["editor", "server"])
+            .with_capture("waiting", 1, "> dev\nListening on :3000\n");
+
+        let args = UpArgs {
+            wait: vec!["server:Listening on".parse().unwrap()],
+            wait_timeout: 0,
+            ..Default::default()
+        };
+        run_up_with_backend(&cli, &args, &backend)?;
+
+        let args = UpArgs {
+            wait: vec![
+                "server:Listening on".parse().unwrap(),
+                "editor:ready".parse().unwrap(),
+            ],
+            wait_timeout: 0,
+            ..Default::default()
+        };
+        let err = run_up_with_backend(&cli, &args, &backend).unwrap_err();
+        assert!(matches!(err, SeshError::WaitTimeout { window, .. } if window == "editor"));
+
+        assert!("server".parse::<WaitSpec>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_health_check() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "health"
+
+[[window]]
+name = "editor"
+command = ["vim"]
+
+[[window]]
+name = "server"
+command = ["npm", "run", "dev"]
+
+[[window]]
+name = "repl"
+command = ["bash"]
+
+[[window]]
+name = "notes"
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let pane = |window_index: usize, window_name: &str, command: &str| tmux::PaneInfo {
+            window_index,
+            window_name: window_name.to_string(),
+            command: command.to_string(),
+            pid: 1000,
+            dead: false,
+            path: PathBuf::from("/"),
+        };
+        let backend = MockTmuxBackend::new()
+            .with_session("health", vec!["editor", "server", "repl", "notes"])
+            .with_panes(
+                "health",
+                vec![
+                    pane(0, "editor", "vim"),
+                    pane(1, "server", "bash"),
+                    pane(2, "repl", "bash"),
+                    pane(3, "notes", "bash"),
+                ],
+            );
+
+        // Only the server's command died; the repl is meant to be a
+        // shell and the notes window has no command
+        let config = load_config(&cli)?;
+        assert_eq!(
+            health_check(&backend, "health", &config)?,
+            vec![UnhealthyWindow {
+                name: "server".to_string(),
+                command: vec!["npm".to_string(), "run".to_string(), "dev".to_string()],
+                running: "bash".to_string(),
+            }]
+        );
+
+        // A pane kept open by remain-on-exit is dead too, while a shell
+        // other than the session's default one is left alone
+        let mut panes = vec![pane(0, "editor", "vim"), pane(1, "server", "zsh")];
+        panes[0].dead = true;
+        let dead = MockTmuxBackend::new()
+            .with_session("health", vec!["editor", "server"])
+            .with_panes("health", panes);
+        let names: Vec<_> = health_check(&dead, "health", &config)?
+            .into_iter()
+            .map(|w| w.name)
+            .collect();
+        assert_eq!(names, ["editor"]);
+
+        // Reported without failing `up`
+        let args = UpArgs {
+            health_check: true,
+            health_check_delay: 0,
+            ..Default::default()
+        };
+        run_up_with_backend(&cli, &args, &backend)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_rebuild_dead() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "rebuild"
+
+[[window]]
+name = "editor"
+command = ["vim"]
+
+[[window]]
+name = "server"
+command = ["npm", "run", "dev"]
+
+[[window]]
+name = "shell"
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let pane = |window_index: usize, window_name: &str, command: &str| tmux::PaneInfo {
+            window_index,
+            window_name: window_name.to_string(),
+            command: command.to_string(),
+            pid: 1000,
+            dead: false,
+            path: PathBuf::from("/"),
+        };
+        let backend = MockTmuxBackend::new()
+            .with_session("rebuild", vec!["editor", "server", "shell"])
+            .with_panes(
+                "rebuild",
+                vec![
+                    pane(0, "editor", "vim"),
+                    pane(1, "server", "bash"),
+                    pane(2, "shell", "bash"),
+                ],
+            );
+
+        // Plain `up` leaves the dead server alone
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+        assert!(backend.get_commands_sent().is_empty());
+
+        let args = UpArgs {
+            rebuild_dead: true,
+            ..Default::default()
+        };
+        let report = run_up_with_backend(&cli, &args, &backend)?;
+
+        // Only the server had a command to re-send; the shell window never had one
+        assert_eq!(
+            backend.get_commands_sent(),
+            vec![(
+                "rebuild".to_string(),
+                1,
+                vec!["npm".to_string(), "run".to_string(), "dev".to_string()]
+            )]
+        );
+        let states: Vec<_> = report.windows.iter().map(|w| &w.state).collect();
+        assert_eq!(
+            states,
+            vec![
+                &WindowState::Existing,
+                &WindowState::Rebuilt,
+                &WindowState::Existing
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_unnamed_windows_not_resent() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "unnamed"
+
+[[window]]
+name = "editor"
+command = ["vim"]
+
+[[window]]
+command = ["npm", "run", "dev"]
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let pane = |window_index: usize, window_name: &str, command: &str| tmux::PaneInfo {
+            window_index,
+            window_name: window_name.to_string(),
+            command: command.to_string(),
+            pid: 1000,
+            dead: false,
+            path: PathBuf::from("/"),
+        };
+        let backend = MockTmuxBackend::new().with_panes(
+            "unnamed",
+            vec![pane(0, "editor", "vim"), pane(1, "npm", "node")],
+        );
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+        let sent = backend.get_commands_sent();
+        assert_eq!(sent.len(), 2);
+
+        // Skipping the "already up" shortcut, the unnamed window is
+        // still found at its index rather than started again
+        let args = UpArgs {
+            rebuild_dead: true,
+            ..Default::default()
+        };
+        let report = run_up_with_backend(&cli, &args, &backend)?;
+        assert_eq!(backend.get_commands_sent(), sent);
+        assert_eq!(backend.get_sessions()["unnamed"].len(), 2);
+        assert_eq!(report.windows[1].state, WindowState::Existing);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_no_send_on_existing() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "nosend"
+
+[[window]]
+name = "editor"
+command = ["vim"]
+
+[[window]]
+name = "server"
+keys = ["C-c"]
+command = ["npm", "run", "dev"]
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new().with_session("nosend", vec!["editor"]);
+        let args = UpArgs {
+            no_send_on_existing: true,
+            ..Default::default()
+        };
+        run_up_with_backend(&cli, &args, &backend)?;
+
+        // The server window is added, but nothing is sent to it
+        assert_eq!(backend.get_sessions()["nosend"], vec!["editor", "server"]);
+        assert!(backend.get_commands_sent().is_empty());
+        assert!(backend.get_keys_sent().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_skips_disabled_window() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "partial"
+
+[[window]]
+name = "editor"
+command = ["vim"]
+
+[[window]]
+name = "worker"
+command = ["celery", "worker"]
+disabled = true
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new();
+        let report = run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+
+        assert_eq!(backend.get_sessions()["partial"], vec!["editor"]);
+        assert_eq!(backend.get_commands_sent().len(), 1);
+        assert_eq!(report.windows.len(), 1);
+
+        // ...and `status` reports it disabled rather than missing
+        let config = load_config(&cli)?;
+        let states = |window| -> Result<Vec<WindowState>> {
+            let (report, _) = status_report(&cli, &config, window, &backend)?;
+            Ok(report.windows.into_iter().map(|w| w.state).collect())
+        };
+        assert_eq!(states(None)?, [WindowState::Running, WindowState::Disabled]);
+        assert_eq!(states(Some("worker"))?, [WindowState::Missing]);
+        check_status(&cli, &config, None, &backend)?;
+        assert!(check_status(&cli, &config, Some("worker"), &backend).is_err());
+
+        // Unless it's asked for by name
+        let args = UpArgs {
+            only: vec!["worker".to_string()],
+            ..Default::default()
+        };
+        run_up_with_backend(&cli, &args, &backend)?;
+        assert!(backend.get_sessions()["partial"].contains(&"worker".to_string()));
+        assert_eq!(states(None)?, [WindowState::Running, WindowState::Running]);
+        check_status(&cli, &config, Some("worker"), &backend)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_commands_in_order() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "scripted"
+
+[[window]]
+name = "setup"
+commands = [["cd", "api"], ["source", ".venv/bin/activate"], ["make", "migrate"]]
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new();
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+
+        let command = |args: &[&str]| -> (String, usize, Vec<String>) {
+            (
+                "scripted".to_string(),
+                0,
+                args.iter().map(|arg| arg.to_string()).collect(),
+            )
+        };
+        assert_eq!(
+            backend.get_commands_sent(),
+            vec![
+                command(&["cd", "api"]),
+                command(&["source", ".venv/bin/activate"]),
+                command(&["make", "migrate"]),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_clear_line() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "clear"
+
+[[window]]
+name = "editor"
+command = ["vim"]
+
+[[window]]
+name = "server"
+command = ["npm", "run", "dev"]
+clear_line = true
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new();
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+
+        // Only the opted-in window gets a C-u, right before its command
+        let sent = backend.get_sent();
+        assert_eq!(
+            sent,
+            vec![
+                (0, vec!["vim".to_string()], true),
+                (1, vec!["C-u".to_string()], false),
+                (
+                    1,
+                    vec!["npm".to_string(), "run".to_string(), "dev".to_string()],
+                    true
+                ),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_log_file() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "logged"
+
+[[window]]
+name = "editor"
+command = ["vim"]
+
+[[window]]
+name = "server"
+command = ["npm", "run", "dev"]
+log_file = "logs/server.log"
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new();
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+
+        // Only the server is logged, relative to the config's directory
+        assert!(temp_dir.path().join("logs").is_dir());
+        assert_eq!(
+            backend.get_pipes(),
+            vec![(
+                "logged".to_string(),
+                1,
+                std::path::absolute(temp_dir.path().join("logs/server.log"))?
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_no_config() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut cli = create_test_cli(&temp_dir, "")?;
+        cli.config = temp_dir.path().join("missing.toml");
+        let backend = MockTmuxBackend::new();
+
+        let args = UpArgs {
+            no_config: true,
+            name: Some("scratch".to_string()),
+            windows: vec![
+                "editor:vim".parse().unwrap(),
+                "web:python3 -m http.server 8000 --bind ::1"
+                    .parse()
+                    .unwrap(),
+                "shell".parse().unwrap(),
+            ],
+            ..Default::default()
+        };
+        run_up_with_backend(&cli, &args, &backend)?;
+
+        assert_eq!(
+            backend.get_sessions()["scratch"],
+            vec!["editor".to_string(), "web".to_string(), "shell".to_string()]
+        );
+        let commands: Vec<_> = backend
+            .get_commands_sent()
+            .into_iter()
+            .map(|(_, idx, command)| (idx, command.join(" ")))
+            .collect();
+        assert_eq!(
+            commands,
+            vec![
+                (0, "vim".to_string()),
+                (1, "python3 -m http.server 8000 --bind ::1".to_string())
+            ]
+        );
+
+        // A name is required
+        let args = UpArgs {
+            no_config: true,
+            ..Default::default()
+        };
+        let err = run_up_with_backend(&cli, &args, &backend).unwrap_err();
+        assert!(matches!(err, SeshError::Usage(_)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_sends_keys_before_command() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "keys"
+
+[[window]]
+name = "editor"
+keys = ["Escape"]
+
+[[window]]
+name = "server"
+keys = ["C-c", "C-l"]
+command = ["npm", "run", "dev"]
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new();
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+
+        let strings = |keys: &[&str]| keys.iter().map(|k| k.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            backend.get_sent(),
+            vec![
+                (0, strings(&["Escape"]), false),
+                (1, strings(&["C-c", "C-l"]), false),
+                (1, strings(&["npm", "run", "dev"]), true),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_hook() {
+        let command = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+
+        assert!(run_hook(None).is_ok());
+        assert!(run_hook(Some(&command(&["true"]))).is_ok());
+
+        let err = run_hook(Some(&command(&["false"]))).unwrap_err();
+        assert!(err.to_string().contains("Hook 'false' failed"));
+        assert!(run_hook(Some(&command(&["/no/such/hook"]))).is_err());
+    }
+
+    #[test]
+    fn test_up_sets_options() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "opts"
+
+[options]
+mouse = "on"
+status-style = "bg=blue"
+
+[global_options]
+escape-time = "0"
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new();
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+
+        let option = |key: &str, value: &str, global| {
+            (
+                "opts".to_string(),
+                key.to_string(),
+                value.to_string(),
+                global,
+            )
+        };
+        assert_eq!(
+            backend.get_options(),
+            vec![
+                option("mouse", "on", false),
+                option("status-style", "bg=blue", false),
+                option("escape-time", "0", true),
+            ]
+        );
+
+        // Not reapplied to a running session
+        let backend = MockTmuxBackend::new().with_session("opts", vec!["bash"]);
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+        assert!(backend.get_options().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_shell_in_path() -> Result<()> {
+        let shell = find_shell("sh")?;
+        assert!(shell.is_absolute());
+        assert!(shell.ends_with("sh"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_already_running_sends_nothing() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "running"
+
+[[window]]
+name = "editor"
+command = ["vim"]
+
+[[window]]
+command = ["htop"]
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new().with_session("running", vec!["editor", "htop"]);
+
+        let report = run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+        assert!(backend.get_commands_sent().is_empty());
+        assert_eq!(backend.get_sessions()["running"].len(), 2);
+        assert!(
+            report
+                .windows
+                .iter()
+                .all(|w| w.state == WindowState::Existing)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_attach_window() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "hotkey"
+
+[[window]]
+name = "editor"
+default = true
+
+[[window]]
+name = "logs"
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let mut args = UpArgs {
+            attach_window: Some("nope".to_string()),
+            ..Default::default()
+        };
+
+        // An unknown window fails before anything is started
+        let backend = MockTmuxBackend::new();
+        let err = run_up_with_backend(&cli, &args, &backend).unwrap_err();
+        assert!(matches!(err, SeshError::WindowNotInConfig(_)));
+        assert!(backend.get_sessions().is_empty());
+
+        // Brought up and attached with the window selected over the default
+        args.attach_window = Some("logs".to_string());
+        run_up_with_backend(&cli, &args, &backend)?;
+        assert_eq!(
+            backend.get_attached(),
+            vec![("hotkey".to_string(), Some("logs".to_string()), false, false)]
+        );
+
+        // `attach --window` shares the same selection
+        let attach_args = AttachArgs {
+            if_exists: true,
+            window: Some("logs".to_string()),
+            ..Default::default()
+        };
+        let session = prepare_attach(&cli, &attach_args, &backend)?;
+        attach(
+            &backend,
+            &session,
+            &load_config(&cli)?,
+            Some("logs"),
+            AttachOptions::default(),
+        )?;
+        assert_eq!(backend.get_attached().len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_foreground_attaches() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "fg"
+
+[[window]]
+name = "editor"
+
+[[window]]
+name = "shell"
+default = true
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new();
+        let args = UpArgs {
+            foreground: true,
+            ..Default::default()
+        };
+
+        // Detached by default
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+        assert!(backend.get_attached().is_empty());
+
+        // Attaches even though the session is already up
+        run_up_with_backend(&cli, &args, &backend)?;
+        assert_eq!(
+            backend.get_attached(),
+            vec![("fg".to_string(), Some("shell".to_string()), false, false)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_kill_existing() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "fresh"
+
+[[window]]
+name = "editor"
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let args = UpArgs {
+            kill_existing: true,
+            ..Default::default()
+        };
+
+        // Stale windows are gone after a clean-slate start
+        let backend = MockTmuxBackend::new().with_session("fresh", vec!["editor", "stale"]);
+        let report = run_up_with_backend(&cli, &args, &backend)?;
+        assert_eq!(report.created, Some(true));
+        assert_eq!(backend.get_sessions()["fresh"], vec!["editor".to_string()]);
+
+        // Nothing to kill
+        let backend = MockTmuxBackend::new();
+        run_up_with_backend(&cli, &args, &backend)?;
+        assert_eq!(backend.get_sessions()["fresh"], vec!["editor".to_string()]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prepare_attach() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "existing"
+
+[[window]]
+name = "editor"
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let strict = AttachArgs {
+            if_exists: true,
+            ..Default::default()
+        };
+
+        // Brought up by default
+        let backend = MockTmuxBackend::new();
+        let args = AttachArgs::default();
+        let session = prepare_attach(&cli, &args, &backend)?;
+        assert_eq!(session, "existing");
+        assert_eq!(
+            backend.get_sessions()["existing"],
+            vec!["editor".to_string()]
+        );
+
+        // Not brought up with --if-exists
+        let backend = MockTmuxBackend::new();
+        let err = prepare_attach(&cli, &strict, &backend).unwrap_err();
+        assert!(matches!(err, SeshError::SessionNotRunning(_)));
+        assert!(backend.get_sessions().is_empty());
+
+        // Left as-is, without creating the missing window
+        let backend = MockTmuxBackend::new().with_session("existing", vec!["bash"]);
+        let session = prepare_attach(&cli, &strict, &backend)?;
+        assert_eq!(session, "existing");
+        assert_eq!(backend.get_sessions()["existing"], vec!["bash".to_string()]);
+
+        // Nothing attaches until the real command does
+        assert!(backend.get_attached().is_empty());
+        attach(
+            &backend,
+            &session,
+            &load_config(&cli)?,
+            None,
+            AttachOptions::default(),
+        )?;
+        assert_eq!(
+            backend.get_attached(),
+            vec![("existing".to_string(), None, false, false)]
+        );
+
+        // --detach-others is passed through
+        let args = AttachArgs {
+            detach_others: true,
+            ..Default::default()
+        };
+        attach(
+            &backend,
+            &session,
+            &load_config(&cli)?,
+            None,
+            attach_options(&args),
+        )?;
+        assert_eq!(
+            backend.get_attached()[1],
+            ("existing".to_string(), None, true, false)
+        );
+
+        // So is --read-only
+        let args = AttachArgs {
+            read_only: true,
+            ..Default::default()
+        };
+        attach(
+            &backend,
+            &session,
+            &load_config(&cli)?,
+            None,
+            attach_options(&args),
+        )?;
+        assert_eq!(
+            backend.get_attached()[2],
+            ("existing".to_string(), None, false, true)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_attach_or_create() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "quick"
+
+[[window]]
+name = "editor"
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let args = AttachArgs {
+            detach_others: true,
+            attach_or_create: true,
+            ..Default::default()
+        };
+
+        // Created bare, without the configured windows
+        let backend = MockTmuxBackend::new();
+        attach_or_create(&cli, &args, &backend)?;
+        assert_eq!(backend.get_sessions()["quick"], vec!["bash".to_string()]);
+        assert_eq!(
+            backend.get_attached(),
+            vec![("quick".to_string(), None, true, false)]
+        );
+
+        // A running session is attached to as-is
+        let backend = MockTmuxBackend::new().with_session("quick", vec!["editor", "logs"]);
+        attach_or_create(&cli, &args, &backend)?;
+        assert_eq!(backend.get_sessions()["quick"], vec!["editor", "logs"]);
+        assert_eq!(backend.get_attached().len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_attach_reconnect() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "flaky"
+
+[[window]]
+name = "editor"
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let config = load_config(&cli)?;
+        let args = AttachArgs {
+            reconnect: true,
+            reconnect_interval: 0,
+            reconnect_retries: 2,
+            ..Default::default()
+        };
+
+        // Reattaches after each drop until a clean detach
+        let backend = MockTmuxBackend::new()
+            .with_session("flaky", vec!["editor"])
+            .with_dropped_attaches(2);
+        attach_reconnecting(&cli, &args, &backend, "flaky", &config, Duration::MAX)?;
+        assert_eq!(backend.get_attached().len(), 3);
+
+        // Gives up once the retries run out
+        let backend = MockTmuxBackend::new()
+            .with_session("flaky", vec!["editor"])
+            .with_dropped_attaches(3);
+        assert!(matches!(
+            attach_reconnecting(&cli, &args, &backend, "flaky", &config, Duration::MAX),
+            Err(SeshError::AttachLost(_))
+        ));
+        assert_eq!(backend.get_attached().len(), 3);
+
+        // Attaches that stay up start the count again
+        let backend = MockTmuxBackend::new()
+            .with_session("flaky", vec!["editor"])
+            .with_dropped_attaches(5);
+        attach_reconnecting(&cli, &args, &backend, "flaky", &config, Duration::ZERO)?;
+        assert_eq!(backend.get_attached().len(), 6);
+
+        // Failing to attach at all isn't retried
+        let backend = MockTmuxBackend::new().with_session("flaky", vec!["editor"]);
+        let args = AttachArgs {
+            window: Some("missing".to_string()),
+            ..args
+        };
+        assert!(matches!(
+            attach_reconnecting(&cli, &args, &backend, "flaky", &config, Duration::MAX),
+            Err(SeshError::WindowNotRunning { .. })
+        ));
+        assert_eq!(backend.get_attached().len(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_with_no_windows() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "empty"
+window = []
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new();
+
+        let report = run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+        assert!(report.windows.is_empty());
+
+        // Just the default window, left untouched
+        let sessions = backend.get_sessions();
+        assert_eq!(sessions.get("empty"), Some(&vec!["bash".to_string()]));
+        assert!(backend.get_commands_sent().is_empty());
+
+        let (report, _) = status_report(&cli, &load_config(&cli)?, None, &backend)?;
+        assert!(report.running);
+        assert!(report.windows.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_status_quiet_exit_code() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "quiet"
+
+[[window]]
+name = "editor"
+
+[[window]]
+name = "server"
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+
+        let backend = MockTmuxBackend::new();
+        let err = run_status_with_backend(&cli, &status_args(), &backend).unwrap_err();
+        assert!(matches!(err, SeshError::SessionNotRunning(_)));
+        assert_eq!(err.exit_code(), 5);
+
+        let backend = MockTmuxBackend::new().with_session("quiet", vec!["editor"]);
+        let err = run_status_with_backend(&cli, &status_args(), &backend).unwrap_err();
+        assert!(matches!(err, SeshError::WindowNotRunning { window, .. } if window == "server"));
+
+        let backend = MockTmuxBackend::new().with_session("quiet", vec!["editor", "server"]);
+        run_status_with_backend(&cli, &status_args(), &backend)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_status_watch_reloads_config() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "watched"
+
+[[window]]
+name = "server"
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let args = StatusArgs {
+            watch: true,
+            json: true,
+            window: Some("server".to_string()),
+            ..status_args()
+        };
+        let backend = MockTmuxBackend::new().with_session("watched", vec!["server"]);
+
+        // Renders until told to stop
+        let mut renders = 0;
+        watch_status(&cli, &args, load_config(&cli)?, &backend, || {
+            renders += 1;
+            renders < 3
+        })?;
+        assert_eq!(renders, 3);
+
+        // The window goes from the config between renders, and the next
+        // render sees that
+        let mut renders = 0;
+        let err = watch_status(&cli, &args, load_config(&cli)?, &backend, || {
+            renders += 1;
+            std::fs::write(&cli.config, "name = \"watched\"\n").is_ok()
+        })
+        .unwrap_err();
+        assert!(matches!(err, SeshError::WindowNotInConfig(ref name) if name == "server"));
+        assert_eq!(renders, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_status_window() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "single"
+
+[[window]]
+name = "editor"
+
+[[window]]
+name = "server"
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let args = StatusArgs {
+            window: Some("server".to_string()),
+            ..status_args()
+        };
+
+        // Only the server window counts, even though editor is missing
+        let backend = MockTmuxBackend::new().with_session("single", vec!["server"]);
+        run_status_with_backend(&cli, &args, &backend)?;
+
+        let (report, _) = status_report(&cli, &load_config(&cli)?, Some("server"), &backend)?;
+        assert_eq!(report.windows.len(), 1);
+        assert_eq!(report.windows[0].name.as_deref(), Some("server"));
+        assert_eq!(report.windows[0].state, WindowState::Running);
+
+        // Configured but not running
+        let backend = MockTmuxBackend::new().with_session("single", vec!["editor"]);
+        let err = run_status_with_backend(&cli, &args, &backend).unwrap_err();
+        assert!(
+            matches!(err, SeshError::WindowNotRunning { ref window, .. } if window == "server")
+        );
+        assert_eq!(err.exit_code(), 5);
+
+        let (report, _) = status_report(&cli, &load_config(&cli)?, Some("server"), &backend)?;
+        assert_eq!(report.windows[0].state, WindowState::Missing);
+
+        // Or the session isn't running at all
+        let err = run_status_with_backend(&cli, &args, &MockTmuxBackend::new()).unwrap_err();
+        assert!(matches!(err, SeshError::SessionNotRunning(_)));
+
+        // Not in the config
+        let args = StatusArgs {
+            window: Some("logs".to_string()),
+            ..status_args()
+        };
+        let err = run_status_with_backend(&cli, &args, &backend).unwrap_err();
+        assert!(matches!(err, SeshError::WindowNotInConfig(name) if name == "logs"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_window() -> Result<()> {
+        let report = SessionReport {
+            session: "fmt".to_string(),
+            running: true,
+            windows: vec![
+                WindowReport {
+                    name: Some("editor".to_string()),
+                    state: WindowState::Running,
+                },
+                WindowReport {
+                    name: None,
+                    state: WindowState::Missing,
+                },
+            ],
+            ..Default::default()
+        };
+
+        let format = crate::format::parse_status_format(
+            "{session}:{index} {name} {state} {running} {window_count}",
+        )
+        .map_err(SeshError::Usage)?;
+        let lines: Vec<_> = report
+            .windows
+            .iter()
+            .enumerate()
+            .map(|(idx, window)| format_window(&format, &report, idx, window))
+            .collect();
+        assert_eq!(
+            lines,
+            vec!["fmt:0 editor running true 2", "fmt:1  missing false 2"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(Duration::from_secs(42)), "42s");
+        assert_eq!(format_age(Duration::from_secs(5 * 60 + 9)), "5m");
+        assert_eq!(
+            format_age(Duration::from_secs(2 * 3600 + 13 * 60)),
+            "2h 13m"
+        );
+        assert_eq!(
+            format_age(Duration::from_secs(3 * 86400 + 4 * 3600)),
+            "3d 4h"
+        );
+    }
+
+    #[test]
+    fn test_session_age() -> Result<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let backend = MockTmuxBackend::new()
+            .with_session("old", vec!["bash"])
+            .with_created("old", now.as_secs() - 3600);
+
+        let created = backend.session_info("old")?.unwrap().created;
+        let age = session_age(created);
+        assert!(age >= Duration::from_secs(3600) && age < Duration::from_secs(3660));
+        Ok(())
+    }
+
+    #[test]
+    fn test_popup() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cli = create_test_cli(&temp_dir, "name = \"popup\"\n")?;
+        let args = PopupArgs {
+            command: "lazygit".to_string(),
+            width: "80%".to_string(),
+            height: "20".to_string(),
+        };
+
+        let backend = MockTmuxBackend::new();
+        run_popup_with_backend(&cli, &args, &backend)?;
+        assert_eq!(
+            backend.get_popups(),
+            vec![("lazygit".to_string(), "80%".to_string(), "20".to_string())]
+        );
+
+        // Popups were added in tmux 3.2
+        let backend = MockTmuxBackend::new().with_version((3, 1, Some('c')));
+        let err = run_popup_with_backend(&cli, &args, &backend).unwrap_err();
+        assert!(matches!(err, SeshError::TmuxTooOld { .. }));
+        assert_eq!(
+            err.to_string(),
+            "popup needs tmux 3.2 or later, but 3.1c is installed"
+        );
+        assert!(backend.get_popups().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_logs() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "logs"
+
+[[window]]
+name = "server"
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let mut args = LogsArgs {
+            window: "server".to_string(),
+            lines: 2,
+            follow: false,
+            interval: 2,
+        };
+
+        let backend = MockTmuxBackend::new();
+        let err = run_logs_with_backend(&cli, &args, &backend).unwrap_err();
+        assert!(matches!(err, SeshError::SessionNotRunning(_)));
+
+        let backend = MockTmuxBackend::new()
+            .with_session("logs", vec!["server"])
+            .with_capture("logs", 0, "starting\nlistening on :8080\n\n\n");
+        run_logs_with_backend(&cli, &args, &backend)?;
+
+        // The window's tmux index, not its position, is captured
+        let backend = MockTmuxBackend::new()
+            .with_indexed_session("logs", vec![(3, "server")])
+            .with_capture("logs", 3, "listening on :8080\n");
+        run_logs_with_backend(&cli, &args, &backend)?;
+
+        args.window = "worker".to_string();
+        let err = run_logs_with_backend(&cli, &args, &backend).unwrap_err();
+        assert!(matches!(err, SeshError::WindowNotRunning { .. }));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_last_lines() {
+        let output = "one\ntwo\nthree\n\n\n";
+        assert_eq!(last_lines(output, 2), vec!["two", "three"]);
+        assert_eq!(last_lines(output, 10), vec!["one", "two", "three"]);
+        assert!(last_lines("\n\n", 5).is_empty());
+    }
+
+    #[test]
+    fn test_status_report() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "report"
+
+[[window]]
+name = "editor"
+
+[[window]]
+name = "server"
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new().with_session("report", vec!["editor"]);
+
+        let (report, info) = status_report(&cli, &load_config(&cli)?, None, &backend)?;
+        assert!(report.running);
+        let states: Vec<_> = report.windows.iter().map(|w| &w.state).collect();
+        assert_eq!(states, vec![&WindowState::Running, &WindowState::Missing]);
+        assert_eq!(info.map(|info| info.windows.len()), Some(1));
+
+        let (report, info) =
+            status_report(&cli, &load_config(&cli)?, None, &MockTmuxBackend::new())?;
+        assert!(!report.running);
+        assert!(info.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_status_report_description() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "described"
+description = "API server and its worker"
+
+[[window]]
+name = "server"
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let (report, _) = status_report(&cli, &load_config(&cli)?, None, &MockTmuxBackend::new())?;
+        let json = serde_json::to_value(&report)?;
+        assert_eq!(json["description"], "API server and its worker");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_idempotent() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "existing-session"
+
+[[window]]
+name = "terminal"
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new().with_session("existing-session", vec!["terminal"]);
+
+        // Running up on existing session should succeed
+        let result = run_up_with_backend(&cli, &UpArgs::default(), &backend);
+        assert!(result.is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_down_kills_session() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "kill-me"
+window = []
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new().with_session("kill-me", vec![]);
+
+        run_down_with_backend(&cli, &DownArgs::default(), &backend)?;
+
+        let sessions = backend.get_sessions();
+        assert!(!sessions.contains_key("kill-me"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_down_nonexistent_session() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "nonexistent"
+window = []
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new();
+
+        // Should succeed even if session doesn't exist
+        let result = run_down_with_backend(&cli, &DownArgs::default(), &backend);
+        assert!(result.is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_confirm() -> Result<()> {
+        assert!(confirm("", &mut "y\n".as_bytes())?);
+        assert!(confirm("", &mut "YES\n".as_bytes())?);
+        assert!(!confirm("", &mut "n\n".as_bytes())?);
+        assert!(!confirm("", &mut "\n".as_bytes())?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_restart_declined_keeps_session() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "keep-me"
+
+[[window]]
+name = "editor"
+command = ["vim"]
+"#;
+
+        let mut cli = create_test_cli(&temp_dir, config_content)?;
+        cli.quiet = false;
+        let backend = MockTmuxBackend::new().with_session("keep-me", vec!["editor"]);
+        let args = RestartArgs {
+            yes: false,
+            keep_current_window: false,
+        };
+
+        run_restart_with_backend(&cli, &args, &backend, &mut "n\n".as_bytes())?;
+
+        // Nothing was killed or re-sent
+        assert!(backend.get_sessions().contains_key("keep-me"));
+        assert!(backend.get_commands_sent().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_nuke() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cli = create_test_cli(&temp_dir, "name = \"mine\"")?;
+        let backend = MockTmuxBackend::new()
+            .with_session("mine", vec!["editor"])
+            .with_session("other", vec!["bash"]);
+
+        // Asks even with --quiet
+        run_nuke_with_backend(
+            &cli,
+            &NukeArgs { yes: false },
+            &backend,
+            &mut "n\n".as_bytes(),
+        )?;
+        assert_eq!(backend.get_sessions().len(), 2);
+
+        run_nuke_with_backend(
+            &cli,
+            &NukeArgs { yes: false },
+            &backend,
+            &mut "y\n".as_bytes(),
+        )?;
+        assert!(backend.get_sessions().is_empty());
+
+        let backend = MockTmuxBackend::new().with_session("other", vec!["bash"]);
+        run_nuke_with_backend(&cli, &NukeArgs { yes: true }, &backend, &mut "".as_bytes())?;
+        assert!(backend.get_sessions().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_restart_with_yes_skips_prompt() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "restart-me"
+
+[[window]]
+name = "editor"
+command = ["vim"]
+"#;
+
+        let mut cli = create_test_cli(&temp_dir, config_content)?;
+        cli.quiet = false;
+        let backend = MockTmuxBackend::new().with_session("restart-me", vec!["editor"]);
+        let args = RestartArgs {
+            yes: true,
+            keep_current_window: false,
+        };
+
+        // No input available, so this would fail to confirm if it prompted
+        run_restart_with_backend(&cli, &args, &backend, &mut "".as_bytes())?;
+
+        assert_eq!(backend.get_sessions()["restart-me"], vec!["editor"]);
+        assert_eq!(backend.get_commands_sent().len(), 1);
+
+        Ok(())
     }
 
     #[test]
-    fn test_status_session_not_running() -> Result<()> {
+    fn test_restart_keep_current_window() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "keep"
+
+[[window]]
+name = "editor"
+default = true
+
+[[window]]
+name = "server"
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let args = RestartArgs {
+            yes: true,
+            keep_current_window: true,
+        };
+
+        // Not running, so there's nothing to keep
+        let backend = MockTmuxBackend::new();
+        run_restart_with_backend(&cli, &args, &backend, &mut "".as_bytes())?;
+        assert!(backend.get_selected().is_empty());
+
+        let backend = MockTmuxBackend::new()
+            .with_session("keep", vec!["editor", "server"])
+            .with_current("keep", "server");
+        run_restart_with_backend(&cli, &args, &backend, &mut "".as_bytes())?;
+        assert_eq!(
+            backend.get_selected(),
+            vec![("keep".to_string(), "server".to_string())]
+        );
+
+        // Once it's gone from the config, the default window is used
+        std::fs::write(
+            &cli.config,
+            "name = \"keep\"\n\n[[window]]\nname = \"editor\"\ndefault = true\n",
+        )?;
+        let backend = MockTmuxBackend::new()
+            .with_session("keep", vec!["editor", "server"])
+            .with_current("keep", "server");
+        run_restart_with_backend(&cli, &args, &backend, &mut "".as_bytes())?;
+        assert_eq!(
+            backend.get_selected(),
+            vec![("keep".to_string(), "editor".to_string())]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_send_to_running_window() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "send-test"
+window = []
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let keys = |keys: &[&str]| keys.iter().map(|k| k.to_string()).collect::<Vec<_>>();
+        let mut args = SendArgs {
+            window: Some("server".to_string()),
+            all: false,
+            except: vec![],
+            no_enter: false,
+            literal: false,
+            keys: keys(&["C-c", "rs"]),
+        };
+
+        // Each argument is a tmux key name by default, then Enter
+        let backend = MockTmuxBackend::new().with_session("send-test", vec!["editor", "server"]);
+        run_send_with_backend(&cli, &args, &backend)?;
+        assert_eq!(
+            backend.get_keys_sent(),
+            vec![
+                ("send-test".to_string(), 1, keys(&["C-c", "rs"])),
+                ("send-test".to_string(), 1, keys(&["C-m"])),
+            ]
+        );
+        assert!(backend.get_literal_sent().is_empty());
+
+        // --literal types them as text instead, Enter and all
+        let backend = MockTmuxBackend::new().with_session("send-test", vec!["editor", "server"]);
+        args.literal = true;
+        args.keys = keys(&["echo", "done;"]);
+        run_send_with_backend(&cli, &args, &backend)?;
+        assert_eq!(
+            backend.get_literal_sent(),
+            vec![("send-test".to_string(), 1, "echo done;".to_string())]
+        );
+        assert_eq!(
+            backend.get_keys_sent(),
+            vec![("send-test".to_string(), 1, keys(&["C-m"]))]
+        );
+
+        // --no-enter leaves out Enter in either mode
+        for literal in [false, true] {
+            let backend =
+                MockTmuxBackend::new().with_session("send-test", vec!["editor", "server"]);
+            args.literal = literal;
+            args.no_enter = true;
+            run_send_with_backend(&cli, &args, &backend)?;
+            assert_eq!(backend.get_sent().len(), 1);
+            assert!(!backend.get_keys_sent().contains(&(
+                "send-test".to_string(),
+                1,
+                keys(&["C-m"])
+            )));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_send_to_all_windows() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cli = create_test_cli(&temp_dir, "name = \"send-test\"")?;
+        let backend =
+            MockTmuxBackend::new().with_session("send-test", vec!["editor", "server", "logs"]);
+
+        let mut args = SendArgs {
+            window: None,
+            all: true,
+            except: vec![],
+            no_enter: true,
+            literal: false,
+            keys: vec!["clear".to_string()],
+        };
+        run_send_with_backend(&cli, &args, &backend)?;
+        let indices: Vec<_> = backend
+            .get_keys_sent()
+            .into_iter()
+            .map(|(_, idx, keys)| (idx, keys.join(" ")))
+            .collect();
+        assert_eq!(
+            indices,
+            [0, 1, 2].map(|idx| (idx, "clear".to_string())).to_vec()
+        );
+
+        // --except skips windows by name
+        let backend =
+            MockTmuxBackend::new().with_session("send-test", vec!["editor", "server", "logs"]);
+        args.except = vec!["editor".to_string(), "logs".to_string()];
+        run_send_with_backend(&cli, &args, &backend)?;
+        assert_eq!(
+            backend.get_keys_sent(),
+            vec![("send-test".to_string(), 1, vec!["clear".to_string()])]
+        );
+
+        // Keys go to each window's tmux index, even with gaps
+        let backend = MockTmuxBackend::new()
+            .with_indexed_session("send-test", vec![(1, "editor"), (3, "server"), (4, "logs")]);
+        run_send_with_backend(&cli, &args, &backend)?;
+        assert_eq!(
+            backend.get_keys_sent(),
+            vec![("send-test".to_string(), 3, vec!["clear".to_string()])]
+        );
+
+        let backend = MockTmuxBackend::new();
+        let err = run_send_with_backend(&cli, &args, &backend).unwrap_err();
+        assert!(matches!(err, SeshError::SessionNotRunning(_)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_send_errors_when_not_running() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "send-test"
+window = []
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let args = SendArgs {
+            window: Some("server".to_string()),
+            all: false,
+            except: vec![],
+            no_enter: false,
+            literal: false,
+            keys: vec!["rs".to_string()],
+        };
+
+        let backend = MockTmuxBackend::new();
+        let result = run_send_with_backend(&cli, &args, &backend);
+        assert!(result.unwrap_err().to_string().contains("Session"));
+
+        let backend = MockTmuxBackend::new().with_session("send-test", vec!["editor"]);
+        let result = run_send_with_backend(&cli, &args, &backend);
+        assert!(result.unwrap_err().to_string().contains("Window 'server'"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ps_table() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "ps-test"
+window = []
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new()
+            .with_session("ps-test", vec!["editor", "server"])
+            .with_panes(
+                "ps-test",
+                vec![
+                    tmux::PaneInfo {
+                        window_index: 0,
+                        window_name: "editor".to_string(),
+                        command: "nvim".to_string(),
+                        pid: 10,
+                        dead: false,
+                        path: PathBuf::from("/"),
+                    },
+                    tmux::PaneInfo {
+                        window_index: 1,
+                        window_name: "server".to_string(),
+                        command: "zsh".to_string(),
+                        pid: 11,
+                        dead: false,
+                        path: PathBuf::from("/"),
+                    },
+                ],
+            );
+
+        run_ps_with_backend(&cli, &backend)?;
+
+        let panes = backend.list_panes("ps-test")?;
+        assert_eq!(
+            format_ps_table(&panes),
+            "WINDOW  COMMAND  PID\neditor  nvim     10\nserver  zsh      11\n"
+        );
+
+        // Not running is reported, not an error
+        let backend = MockTmuxBackend::new();
+        assert!(run_ps_with_backend(&cli, &backend).is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_and_restore() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "saved"
+window = []
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let pane =
+            |window_index: usize, window_name: &str, command: &str, path: &str| tmux::PaneInfo {
+                window_index,
+                window_name: window_name.to_string(),
+                command: command.to_string(),
+                pid: 1,
+                dead: false,
+                path: PathBuf::from(path),
+            };
+        let backend = MockTmuxBackend::new()
+            .with_indexed_session("saved", vec![(1, "editor"), (3, "server")])
+            .with_panes(
+                "saved",
+                vec![
+                    pane(1, "editor", "nvim", "/src"),
+                    pane(1, "editor", "bash", "/src/docs"),
+                    pane(3, "server", "cargo", "/srv"),
+                ],
+            );
+
+        run_save_with_backend(&cli, &SaveArgs { file: None }, &backend)?;
+
+        let path = temp_dir.path().join(snapshot::DEFAULT_SNAPSHOT_FILE);
+        let saved = Snapshot::load(&path)?;
+        assert_eq!(saved.session, "saved");
+        assert_eq!(saved.windows.len(), 2);
+        assert_eq!(saved.windows[0].panes.len(), 2);
+        assert_eq!(saved.windows[0].panes[1].command, None);
+        assert_eq!(
+            saved.windows[0].panes[1].cwd,
+            Some(PathBuf::from("/src/docs"))
+        );
+
+        // Restoring over the running session is refused
+        let mut args = RestoreArgs {
+            file: None,
+            commands: false,
+        };
+        assert!(matches!(
+            run_restore_with_backend(&cli, &args, &backend),
+            Err(SeshError::SessionExists(_))
+        ));
+
+        // Windows keep their indices and directories, but nothing is run
+        let backend = MockTmuxBackend::new();
+        run_restore_with_backend(&cli, &args, &backend)?;
+
+        assert_eq!(window_indices(&backend, "saved")?, [1, 3]);
+        assert_eq!(
+            backend.get_sessions().get("saved"),
+            Some(&vec!["editor".to_string(), "server".to_string()])
+        );
+        assert_eq!(backend.get_splits(), vec![("saved".to_string(), 1)]);
+        assert_eq!(
+            backend.get_start_dirs(),
+            vec![
+                ("saved:0".to_string(), PathBuf::from("/src")),
+                ("saved:3".to_string(), PathBuf::from("/srv")),
+                ("saved:1".to_string(), PathBuf::from("/src/docs")),
+            ]
+        );
+        assert!(backend.get_commands_sent().is_empty());
+
+        // `--commands` re-runs the saved programs
+        let backend = MockTmuxBackend::new().with_base_index(1);
+        args.commands = true;
+        run_restore_with_backend(&cli, &args, &backend)?;
+        assert_eq!(
+            backend.get_commands_sent(),
+            vec![
+                ("saved".to_string(), 1, vec!["nvim".to_string()]),
+                ("saved".to_string(), 3, vec!["cargo".to_string()]),
+            ]
+        );
+
+        // Saving needs the session to be running
+        let backend = MockTmuxBackend::new();
+        assert!(run_save_with_backend(&cli, &SaveArgs { file: None }, &backend).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_window_commands_leave_global_config() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = "name = \"global\"\n\n[[window]]\nname = \"shell\"\n";
+        let mut cli = create_test_cli(&temp_dir, config_content)?;
+        cli.global_config = true;
+
+        let add = WindowAddArgs {
+            name: Some("newwin".to_string()),
+            empty: true,
+            cmd: None,
+            args: vec![],
+            allow_duplicate: false,
+            write: ConfigWriteArgs::default(),
+        };
+        let toggle = WindowToggleArgs {
+            name: "shell".to_string(),
+            write: ConfigWriteArgs::default(),
+        };
+        assert!(matches!(
+            run_window_add(&cli, &add),
+            Err(SeshError::ConfigWrite(_))
+        ));
+        assert!(matches!(
+            run_window_toggle(&cli, &toggle, true),
+            Err(SeshError::ConfigWrite(_))
+        ));
+        assert_eq!(std::fs::read_to_string(&cli.config)?, config_content);
+
+        // It can still be read
+        run_window_list(
+            &cli,
+            &WindowListArgs {
+                sort: WindowSort::Index,
+            },
+        )?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_window_add() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "test-session"
+
+[[window]]
+name = "existing"
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+
+        let args = WindowAddArgs {
+            name: Some("newwin".to_string()),
+            empty: false,
+            cmd: Some("htop".to_string()),
+            args: vec![],
+            allow_duplicate: false,
+            write: ConfigWriteArgs::default(),
+        };
+
+        run_window_add(&cli, &args)?;
+
+        // Verify the config was updated
+        let config = Config::load(&cli.config)?;
+        assert_eq!(config.window.len(), 2);
+        assert_eq!(config.window[1].name, Some("newwin".to_string()));
+        assert_eq!(config.window[1].command, Some(vec!["htop".to_string()]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_window_add_names_by_policy() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cli = create_test_cli(&temp_dir, "name = \"naming\"")?;
+        let args = |cmd: &str| WindowAddArgs {
+            name: None,
+            empty: false,
+            cmd: Some(cmd.to_string()),
+            args: vec!["run".to_string(), "dev".to_string()],
+            allow_duplicate: false,
+            write: ConfigWriteArgs::default(),
+        };
+
+        // Named after the program by default
+        run_window_add(&cli, &args("npm"))?;
+        assert_eq!(
+            Config::load(&cli.config)?.window[0].name.as_deref(),
+            Some("npm")
+        );
+
+        // The picked name still can't be a duplicate
+        let err = run_window_add(&cli, &args("npm")).unwrap_err();
+        assert!(matches!(err, SeshError::DuplicateWindow { .. }));
+
+        std::fs::write(
+            &cli.config,
+            "name = \"naming\"\nwindow_naming = \"full-command\"\n",
+        )?;
+        run_window_add(&cli, &args("npm"))?;
+        assert_eq!(
+            Config::load(&cli.config)?.window[0].name.as_deref(),
+            Some("npm-run-dev")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_up_names_unnamed_windows_by_policy() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "naming"
+
+[[window]]
+command = ["vim", "."]
+
+[[window]]
+name = "server"
+command = ["npm", "run", "dev"]
+
+[[window]]
+command = ["htop"]
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new();
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+        assert_eq!(
+            backend.list_windows("naming")?,
+            vec!["vim", "server", "htop"]
+        );
+
+        let cli = create_test_cli(
+            &temp_dir,
+            &format!("window_naming = \"index\"\n{}", config_content),
+        )?;
+        let backend = MockTmuxBackend::new();
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+        assert_eq!(
+            backend.list_windows("naming")?,
+            vec!["window-0", "server", "window-2"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_window_add_backup() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "backed-up"
+
+# Hand-tuned
+[[window]]
+name = "editor"
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let backup = temp_dir.path().join(".seshconf.toml.bak");
+        let mut args = WindowAddArgs {
+            name: Some("logs".to_string()),
+            empty: true,
+            cmd: None,
+            args: vec![],
+            allow_duplicate: false,
+            write: ConfigWriteArgs::default(),
+        };
+
+        run_window_add(&cli, &args)?;
+        assert!(!backup.exists());
+
+        let before = std::fs::read_to_string(&cli.config)?;
+        args.name = Some("server".to_string());
+        args.write.backup = true;
+        run_window_add(&cli, &args)?;
+
+        assert_eq!(std::fs::read_to_string(&backup)?, before);
+        assert_eq!(Config::load(&cli.config)?.window.len(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_window_add_duplicate() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "test-session"
+
+[[window]]
+name = "shell"
+
+[[window]]
+name = "editor"
+command = ["vim"]
+"#;
+
+        let cli = create_test_cli(&temp_dir, config_content)?;
+
+        let mut args = WindowAddArgs {
+            name: Some("editor".to_string()),
+            empty: false,
+            cmd: Some("nvim".to_string()),
+            args: vec![],
+            allow_duplicate: false,
+            write: ConfigWriteArgs::default(),
+        };
+
+        let err = run_window_add(&cli, &args).unwrap_err();
+        assert!(err.to_string().contains("index 1"));
+        assert_eq!(Config::load(&cli.config)?.window.len(), 2);
+
+        args.allow_duplicate = true;
+        run_window_add(&cli, &args)?;
+        assert_eq!(Config::load(&cli.config)?.window.len(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_window_add_with_args() -> Result<()> {
         let temp_dir = TempDir::new()?;
         let config_content = r#"
 name = "test-session"
-
-[[window]]
-name = "editor"
-command = ["vim", "."]
+window = []
 "#;
 
         let cli = create_test_cli(&temp_dir, config_content)?;
-        let backend = MockTmuxBackend::new();
 
-        // Session doesn't exist, should succeed without error
-        let result = run_status_with_backend(&cli, &backend);
-        assert!(result.is_ok());
+        let args = WindowAddArgs {
+            name: Some("server".to_string()),
+            empty: false,
+            cmd: Some("npm".to_string()),
+            args: vec!["run".to_string(), "dev".to_string()],
+            allow_duplicate: false,
+            write: ConfigWriteArgs::default(),
+        };
+
+        run_window_add(&cli, &args)?;
+
+        let config = Config::load(&cli.config)?;
+        assert_eq!(config.window.len(), 1);
+        assert_eq!(
+            config.window[0].command,
+            Some(vec![
+                "npm".to_string(),
+                "run".to_string(),
+                "dev".to_string()
+            ])
+        );
 
         Ok(())
     }
 
     #[test]
-    fn test_status_session_running() -> Result<()> {
+    fn test_window_add_empty() -> Result<()> {
         let temp_dir = TempDir::new()?;
         let config_content = r#"
 name = "test-session"
-
-[[window]]
-name = "editor"
-command = ["vim", "."]
+window = []
 "#;
 
         let cli = create_test_cli(&temp_dir, config_content)?;
-        let backend = MockTmuxBackend::new().with_session("test-session", vec!["editor"]);
 
-        let result = run_status_with_backend(&cli, &backend);
-        assert!(result.is_ok());
+        let args = WindowAddArgs {
+            name: Some("shell".to_string()),
+            empty: true,
+            cmd: None,
+            args: vec![],
+            allow_duplicate: false,
+            write: ConfigWriteArgs::default(),
+        };
+
+        run_window_add(&cli, &args)?;
+
+        let config = Config::load(&cli.config)?;
+        assert_eq!(config.window.len(), 1);
+        assert_eq!(config.window[0].name, Some("shell".to_string()));
+        assert_eq!(config.window[0].command, None);
 
         Ok(())
     }
 
     #[test]
-    fn test_up_creates_new_session() -> Result<()> {
+    fn test_window_remove() -> Result<()> {
         let temp_dir = TempDir::new()?;
         let config_content = r#"
-name = "new-session"
+name = "test-session"
 
 [[window]]
-name = "terminal"
-command = ["bash"]
+name = "keep-me"
+
+[[window]]
+name = "remove-me"
 "#;
 
         let cli = create_test_cli(&temp_dir, config_content)?;
-        let backend = MockTmuxBackend::new();
 
-        run_up_with_backend(&cli, &backend)?;
+        let args = WindowRemoveArgs {
+            name: Some("remove-me".to_string()),
+            write: ConfigWriteArgs::default(),
+        };
 
-        let sessions = backend.get_sessions();
-        assert!(sessions.contains_key("new-session"));
+        run_window_remove(&cli, &args)?;
+
+        let config = Config::load(&cli.config)?;
+        assert_eq!(config.window.len(), 1);
+        assert_eq!(config.window[0].name, Some("keep-me".to_string()));
 
         Ok(())
     }
 
     #[test]
-    fn test_up_with_multiple_windows() -> Result<()> {
+    fn test_window_toggle() -> Result<()> {
         let temp_dir = TempDir::new()?;
         let config_content = r#"
-name = "multi-window"
+name = "toggled"
 
 [[window]]
 name = "editor"
-command = ["vim"]
 
 [[window]]
 name = "server"
@@ -467,164 +6505,284 @@ command = ["npm", "run", "dev"]
 "#;
 
         let cli = create_test_cli(&temp_dir, config_content)?;
-        let backend = MockTmuxBackend::new();
-
-        run_up_with_backend(&cli, &backend)?;
+        let args = WindowToggleArgs {
+            name: "server".to_string(),
+            write: ConfigWriteArgs::default(),
+        };
 
-        let sessions = backend.get_sessions();
-        assert!(sessions.contains_key("multi-window"));
+        run_window_toggle(&cli, &args, true)?;
+        let config = Config::load(&cli.config)?;
+        assert_eq!(config.window[1].disabled, Some(true));
+        assert_eq!(config.window.len(), 2);
 
-        let windows = &sessions["multi-window"];
-        assert!(windows.contains(&"server".to_string()));
+        // Enabling drops the flag again, leaving the file as it was
+        run_window_toggle(&cli, &args, false)?;
+        assert_eq!(std::fs::read_to_string(&cli.config)?, config_content);
 
-        let commands = backend.get_commands_sent();
-        assert!(!commands.is_empty());
+        let missing = WindowToggleArgs {
+            name: "missing".to_string(),
+            write: ConfigWriteArgs::default(),
+        };
+        assert!(matches!(
+            run_window_toggle(&cli, &missing, true),
+            Err(SeshError::WindowNotInConfig(_))
+        ));
 
         Ok(())
     }
 
     #[test]
-    fn test_up_idempotent() -> Result<()> {
+    fn test_window_remove_glob() -> Result<()> {
         let temp_dir = TempDir::new()?;
         let config_content = r#"
-name = "existing-session"
+name = "test-session"
 
 [[window]]
-name = "terminal"
-"#;
-
-        let cli = create_test_cli(&temp_dir, config_content)?;
-        let backend = MockTmuxBackend::new().with_session("existing-session", vec!["terminal"]);
+name = "worker-1"
 
-        // Running up on existing session should succeed
-        let result = run_up_with_backend(&cli, &backend);
-        assert!(result.is_ok());
+[[window]]
+name = "editor"
 
-        Ok(())
-    }
+[[window]]
+name = "worker-2"
 
-    #[test]
-    fn test_down_kills_session() -> Result<()> {
-        let temp_dir = TempDir::new()?;
-        let config_content = r#"
-name = "kill-me"
-window = []
+[[window]]
+command = ["htop"]
 "#;
 
         let cli = create_test_cli(&temp_dir, config_content)?;
-        let backend = MockTmuxBackend::new().with_session("kill-me", vec![]);
 
-        run_down_with_backend(&cli, &backend)?;
+        let args = WindowRemoveArgs {
+            name: Some("worker-*".to_string()),
+            write: ConfigWriteArgs::default(),
+        };
+        run_window_remove(&cli, &args)?;
+
+        let config = Config::load(&cli.config)?;
+        let names: Vec<_> = config.window.iter().map(|w| w.name.as_deref()).collect();
+        assert_eq!(names, vec![Some("editor"), None]);
 
-        let sessions = backend.get_sessions();
-        assert!(!sessions.contains_key("kill-me"));
+        // Nothing left to match
+        let err = run_window_remove(&cli, &args).unwrap_err();
+        assert!(matches!(err, SeshError::WindowNotInConfig(_)));
 
         Ok(())
     }
 
     #[test]
-    fn test_down_nonexistent_session() -> Result<()> {
+    fn test_window_move() -> Result<()> {
         let temp_dir = TempDir::new()?;
         let config_content = r#"
-name = "nonexistent"
-window = []
+name = "reorder"
+
+[[window]]
+name = "editor"
+
+[[window]]
+name = "server"
+
+[[window]]
+name = "logs"
 "#;
 
         let cli = create_test_cli(&temp_dir, config_content)?;
-        let backend = MockTmuxBackend::new();
+        let args = WindowMoveArgs {
+            name: "logs".to_string(),
+            to: 0,
+            write: ConfigWriteArgs::default(),
+        };
 
-        // Should succeed even if session doesn't exist
-        let result = run_down_with_backend(&cli, &backend);
-        assert!(result.is_ok());
+        // Not running, so only the config changes
+        let backend = MockTmuxBackend::new();
+        run_window_move_with_backend(&cli, &args, &backend)?;
+        let names: Vec<_> = Config::load(&cli.config)?
+            .window
+            .into_iter()
+            .filter_map(|w| w.name)
+            .collect();
+        assert_eq!(names, vec!["logs", "editor", "server"]);
+
+        // Running, so the session's windows move too, keeping their
+        // tmux indices (here from base-index 1, with a gap)
+        let backend = MockTmuxBackend::new()
+            .with_indexed_session("reorder", vec![(1, "logs"), (2, "editor"), (4, "server")]);
+        let mut args = WindowMoveArgs {
+            name: "editor".to_string(),
+            to: 2,
+            write: ConfigWriteArgs::default(),
+        };
+        run_window_move_with_backend(&cli, &args, &backend)?;
+        let info = backend.session_info("reorder")?.unwrap();
+        let windows: Vec<_> = info
+            .windows
+            .iter()
+            .map(|w| (w.index, w.name.as_str()))
+            .collect();
+        assert_eq!(windows, [(1, "logs"), (2, "server"), (4, "editor")]);
+
+        args.to = 0;
+        run_window_move_with_backend(&cli, &args, &backend)?;
+        assert_eq!(
+            backend.list_windows("reorder")?,
+            ["editor", "logs", "server"]
+        );
 
         Ok(())
     }
 
     #[test]
-    fn test_window_add() -> Result<()> {
+    fn test_window_list_sort() -> Result<()> {
         let temp_dir = TempDir::new()?;
         let config_content = r#"
-name = "test-session"
+name = "sorted"
 
 [[window]]
-name = "existing"
-"#;
+name = "server"
+command = ["npm", "run", "dev"]
 
-        let cli = create_test_cli(&temp_dir, config_content)?;
+[[window]]
+command = ["htop"]
 
-        let args = WindowAddArgs {
-            name: Some("newwin".to_string()),
-            cmd: "htop".to_string(),
-            args: vec![],
-        };
+[[window]]
+name = "editor"
+command = ["vim"]
 
-        run_window_add(&cli, &args)?;
+[[window]]
+"#;
 
-        // Verify the config was updated
-        let config = Config::load(&cli.config)?;
-        assert_eq!(config.window.len(), 2);
-        assert_eq!(config.window[1].name, Some("newwin".to_string()));
-        assert_eq!(config.window[1].command, Some(vec!["htop".to_string()]));
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let config = load_config(&cli)?;
+        assert_eq!(
+            window_list(&config, WindowSort::Index),
+            vec![
+                "0: server (npm run dev)",
+                "1: unnamed (htop)",
+                "2: editor (vim)",
+                "3: unnamed (-)",
+            ]
+        );
+        assert_eq!(
+            window_list(&config, WindowSort::Name),
+            vec![
+                "2: editor (vim)",
+                "0: server (npm run dev)",
+                "1: unnamed (htop)",
+                "3: unnamed (-)",
+            ]
+        );
 
         Ok(())
     }
 
     #[test]
-    fn test_window_add_with_args() -> Result<()> {
+    fn test_window_show() -> Result<()> {
         let temp_dir = TempDir::new()?;
         let config_content = r#"
-name = "test-session"
-window = []
-"#;
+name = "details"
+default_command = ["bash"]
 
-        let cli = create_test_cli(&temp_dir, config_content)?;
+[[window]]
+name = "editor"
+command = ["vim", "."]
+default = true
 
-        let args = WindowAddArgs {
-            name: Some("server".to_string()),
-            cmd: "npm".to_string(),
-            args: vec!["run".to_string(), "dev".to_string()],
-        };
+[[window]]
+name = "logs"
+cwd = "/var/log"
+layout = "tiled"
 
-        run_window_add(&cli, &args)?;
+[[window.pane]]
+command = ["tail", "-f", "syslog"]
+"#;
 
-        let config = Config::load(&cli.config)?;
-        assert_eq!(config.window.len(), 1);
+        let cli = create_test_cli(&temp_dir, config_content)?;
+        let config = load_config(&cli)?;
+
+        let (index, window) = find_window(&config, Some("logs"), None)?;
+        assert_eq!(index, 1);
         assert_eq!(
-            config.window[0].command,
-            Some(vec![
-                "npm".to_string(),
-                "run".to_string(),
-                "dev".to_string()
-            ])
+            format_window_details(&config, index, window),
+            [
+                "Window 1: logs",
+                "  keys:         -",
+                "  command:      bash",
+                "  cwd:          /var/log",
+                "  default:      false",
+                "  layout:       tiled",
+                "  focus:        -",
+                "  synchronize:  false",
+                "  allow_rename: false",
+                "  clear_line:   false",
+                "  panes:",
+                "    1: tail -f syslog (cwd: /var/log)",
+            ]
+            .join("\n")
         );
 
+        let (_, window) = find_window(&config, None, Some(0))?;
+        assert_eq!(window.name.as_deref(), Some("editor"));
+
+        assert!(matches!(
+            find_window(&config, Some("nope"), None),
+            Err(SeshError::WindowNotInConfig(_))
+        ));
+        assert!(find_window(&config, None, Some(2)).is_err());
+
         Ok(())
     }
 
     #[test]
-    fn test_window_remove() -> Result<()> {
+    fn test_effective_config() -> Result<()> {
         let temp_dir = TempDir::new()?;
         let config_content = r#"
-name = "test-session"
+name = "effective"
+default_command = ["bash"]
+cwd = "app"
 
 [[window]]
-name = "keep-me"
+name = "editor"
+command = ["vim", "."]
 
 [[window]]
-name = "remove-me"
+name = "worker"
+count = 2
 "#;
 
-        let cli = create_test_cli(&temp_dir, config_content)?;
+        let mut cli = create_test_cli(&temp_dir, config_content)?;
+        cli.prefix = Some("me-".to_string());
+        cli.shell = Some("zsh".to_string());
+        let config = effective_config(&cli)?;
 
-        let args = WindowRemoveArgs {
-            name: Some("remove-me".to_string()),
-        };
+        let app = std::path::absolute(temp_dir.path().join("app"))?;
+        assert_eq!(config.name, "me-effective");
+        assert_eq!(config.shell.as_deref(), Some("zsh"));
+        assert_eq!(config.cwd.as_ref(), Some(&app));
 
-        run_window_remove(&cli, &args)?;
+        let names: Vec<_> = config.window.iter().map(|w| w.name.as_deref()).collect();
+        assert_eq!(names, [Some("editor"), Some("worker-1"), Some("worker-2")]);
+        assert_eq!(config.window[1].command, Some(vec!["bash".to_string()]));
+        assert!(config.window.iter().all(|w| w.cwd.as_ref() == Some(&app)));
 
-        let config = Config::load(&cli.config)?;
-        assert_eq!(config.window.len(), 1);
-        assert_eq!(config.window[0].name, Some("keep-me".to_string()));
+        // The dump round-trips as a config
+        let dumped: Config = toml::from_str(&toml::to_string(&config)?)
+            .map_err(|e| SeshError::ConfigParse(e.to_string()))?;
+        assert_eq!(dumped, config);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_window_renumber() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cli = create_test_cli(&temp_dir, "name = \"gaps\"")?;
+
+        let backend = MockTmuxBackend::new();
+        let err = run_window_renumber_with_backend(&cli, &backend).unwrap_err();
+        assert!(matches!(err, SeshError::SessionNotRunning(_)));
+
+        let backend = MockTmuxBackend::new().with_session("gaps", vec!["a", "b"]);
+        run_window_renumber_with_backend(&cli, &backend)?;
+        assert_eq!(backend.get_renumbered(), vec!["gaps".to_string()]);
 
         Ok(())
     }
@@ -643,6 +6801,7 @@ name = "only-window"
 
         let args = WindowRemoveArgs {
             name: Some("nonexistent".to_string()),
+            write: ConfigWriteArgs::default(),
         };
 
         let result = run_window_remove(&cli, &args);
@@ -662,7 +6821,10 @@ window = []
 
         let cli = create_test_cli(&temp_dir, config_content)?;
 
-        let args = WindowRemoveArgs { name: None };
+        let args = WindowRemoveArgs {
+            name: None,
+            write: ConfigWriteArgs::default(),
+        };
 
         let result = run_window_remove(&cli, &args);
         assert!(result.is_err());
@@ -671,6 +6833,40 @@ window = []
         Ok(())
     }
 
+    #[test]
+    fn test_session_name_override() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_content = r#"
+name = "from-config"
+window = []
+"#;
+
+        let mut cli = create_test_cli(&temp_dir, config_content)?;
+        let backend = MockTmuxBackend::new();
+        let config = Config::load(&cli.config)?;
+        assert_eq!(session_name(&cli, &config), "from-config");
+
+        cli.session_name = Some("override".to_string());
+        assert_eq!(session_name(&cli, &config), "override");
+
+        cli.prefix = Some("me-".to_string());
+        assert_eq!(session_name(&cli, &config), "me-override");
+        cli.prefix = None;
+
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+        let sessions = backend.get_sessions();
+        assert!(sessions.contains_key("override"));
+        assert!(!sessions.contains_key("from-config"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_paint_respects_color_flag() {
+        assert_eq!(green("✓", false), "✓");
+        assert_eq!(red("✗", true), "\x1b[31m✗\x1b[0m");
+    }
+
     // Integration tests using real tmux binary
     // Run with: cargo test -- --ignored
     //
@@ -699,7 +6895,7 @@ window = []
     #[test]
     #[ignore]
     fn test_real_tmux_status_session_not_running() -> Result<()> {
-        let backend = RealTmuxBackend;
+        let backend = RealTmuxBackend::default();
 
         // Check tmux is available
         backend.check_available()?;
@@ -717,10 +6913,11 @@ command = ["vim", "."]
             session_name
         );
 
-        let cli = create_test_cli(&temp_dir, &config_content)?;
+        let mut cli = create_test_cli(&temp_dir, &config_content)?;
+        cli.quiet = false;
 
         // Session doesn't exist, should succeed without error
-        let result = run_status_with_backend(&cli, &backend);
+        let result = run_status_with_backend(&cli, &status_args(), &backend);
         assert!(result.is_ok());
 
         Ok(())
@@ -729,7 +6926,7 @@ command = ["vim", "."]
     #[test]
     #[ignore]
     fn test_real_tmux_status_session_running() -> Result<()> {
-        let backend = RealTmuxBackend;
+        let backend = RealTmuxBackend::default();
         backend.check_available()?;
 
         let session_name = create_unique_session_name();
@@ -745,12 +6942,13 @@ command = ["echo", "test"]
             session_name
         );
 
-        let cli = create_test_cli(&temp_dir, &config_content)?;
+        let mut cli = create_test_cli(&temp_dir, &config_content)?;
+        cli.quiet = false;
 
         // Create the session
-        backend.new_session(&session_name, true)?;
+        backend.new_session(&session_name, true, None, None, None)?;
 
-        let result = run_status_with_backend(&cli, &backend);
+        let result = run_status_with_backend(&cli, &status_args(), &backend);
         assert!(result.is_ok());
 
         // Cleanup
@@ -762,7 +6960,7 @@ command = ["echo", "test"]
     #[test]
     #[ignore]
     fn test_real_tmux_up_creates_new_session() -> Result<()> {
-        let backend = RealTmuxBackend;
+        let backend = RealTmuxBackend::default();
         backend.check_available()?;
 
         let session_name = create_unique_session_name();
@@ -785,7 +6983,7 @@ command = ["echo", "hello"]
         assert!(!exists_before, "Session should not exist before test");
 
         // Run up
-        run_up_with_backend(&cli, &backend)?;
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
 
         // Verify session was created
         let exists_after = backend.has_session(&session_name)?;
@@ -800,7 +6998,7 @@ command = ["echo", "hello"]
     #[test]
     #[ignore]
     fn test_real_tmux_up_with_multiple_windows() -> Result<()> {
-        let backend = RealTmuxBackend;
+        let backend = RealTmuxBackend::default();
         backend.check_available()?;
 
         let session_name = create_unique_session_name();
@@ -822,7 +7020,7 @@ command = ["echo", "window2"]
 
         let cli = create_test_cli(&temp_dir, &config_content)?;
 
-        run_up_with_backend(&cli, &backend)?;
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
 
         // Verify session exists
         assert!(backend.has_session(&session_name)?);
@@ -837,10 +7035,42 @@ command = ["echo", "window2"]
         Ok(())
     }
 
+    #[test]
+    #[ignore]
+    fn test_real_tmux_up_single_window() -> Result<()> {
+        let backend = RealTmuxBackend::default();
+        backend.check_available()?;
+
+        let session_name = create_unique_session_name();
+        let temp_dir = TempDir::new()?;
+        let config_content = format!(
+            r#"
+name = "{}"
+
+[[window]]
+name = "only"
+command = ["echo", "single"]
+"#,
+            session_name
+        );
+
+        let cli = create_test_cli(&temp_dir, &config_content)?;
+
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+
+        let windows = backend.list_windows(&session_name)?;
+        assert_eq!(windows, vec!["only"], "Should reuse the initial window");
+
+        // Cleanup
+        cleanup_test_session(&backend, &session_name);
+
+        Ok(())
+    }
+
     #[test]
     #[ignore]
     fn test_real_tmux_up_idempotent() -> Result<()> {
-        let backend = RealTmuxBackend;
+        let backend = RealTmuxBackend::default();
         backend.check_available()?;
 
         let session_name = create_unique_session_name();
@@ -858,8 +7088,8 @@ name = "terminal"
         let cli = create_test_cli(&temp_dir, &config_content)?;
 
         // Run up twice
-        run_up_with_backend(&cli, &backend)?;
-        let result = run_up_with_backend(&cli, &backend);
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
+        let result = run_up_with_backend(&cli, &UpArgs::default(), &backend);
 
         // Second run should also succeed
         assert!(result.is_ok(), "Running up twice should be idempotent");
@@ -873,7 +7103,7 @@ name = "terminal"
     #[test]
     #[ignore]
     fn test_real_tmux_down_kills_session() -> Result<()> {
-        let backend = RealTmuxBackend;
+        let backend = RealTmuxBackend::default();
         backend.check_available()?;
 
         let session_name = create_unique_session_name();
@@ -889,11 +7119,11 @@ window = []
         let cli = create_test_cli(&temp_dir, &config_content)?;
 
         // Create session first
-        backend.new_session(&session_name, true)?;
+        backend.new_session(&session_name, true, None, None, None)?;
         assert!(backend.has_session(&session_name)?);
 
         // Kill it
-        run_down_with_backend(&cli, &backend)?;
+        run_down_with_backend(&cli, &DownArgs::default(), &backend)?;
 
         // Verify it's gone
         let exists = backend.has_session(&session_name)?;
@@ -905,7 +7135,7 @@ window = []
     #[test]
     #[ignore]
     fn test_real_tmux_down_nonexistent_session() -> Result<()> {
-        let backend = RealTmuxBackend;
+        let backend = RealTmuxBackend::default();
         backend.check_available()?;
 
         let session_name = create_unique_session_name();
@@ -921,7 +7151,7 @@ window = []
         let cli = create_test_cli(&temp_dir, &config_content)?;
 
         // Should succeed even if session doesn't exist
-        let result = run_down_with_backend(&cli, &backend);
+        let result = run_down_with_backend(&cli, &DownArgs::default(), &backend);
         assert!(result.is_ok());
 
         Ok(())
@@ -930,7 +7160,7 @@ window = []
     #[test]
     #[ignore]
     fn test_real_tmux_full_lifecycle() -> Result<()> {
-        let backend = RealTmuxBackend;
+        let backend = RealTmuxBackend::default();
         backend.check_available()?;
 
         let session_name = create_unique_session_name();
@@ -951,13 +7181,13 @@ command = ["echo", "lifecycle-test"]
         // Full lifecycle: up -> status -> down
         assert!(!backend.has_session(&session_name)?);
 
-        run_up_with_backend(&cli, &backend)?;
+        run_up_with_backend(&cli, &UpArgs::default(), &backend)?;
         assert!(backend.has_session(&session_name)?);
 
-        let status_result = run_status_with_backend(&cli, &backend);
+        let status_result = run_status_with_backend(&cli, &status_args(), &backend);
         assert!(status_result.is_ok());
 
-        run_down_with_backend(&cli, &backend)?;
+        run_down_with_backend(&cli, &DownArgs::default(), &backend)?;
         assert!(!backend.has_session(&session_name)?);
 
         Ok(())
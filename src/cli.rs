@@ -1,7 +1,11 @@
 //! App's CLI code.
 
-use clap::{Args, Parser, Subcommand};
+use crate::error::SeshError;
+use crate::format::{Format, parse_status_format};
+use clap::{Args, FromArgMatches, Parser, Subcommand, ValueEnum};
+use std::io::IsTerminal;
 use std::path::PathBuf;
+use std::str::FromStr;
 
 #[derive(Debug, Parser)]
 #[command(
@@ -14,18 +18,124 @@ pub struct Cli {
     pub command: Command,
 
     /// Path to the session configuration file
+    ///
+    /// If it isn't found, parent directories are searched for it, up to
+    /// the first one containing a `.sesh-root` marker file. Failing that,
+    /// and unless `--config` was passed, `default.toml` in `--config-dir`
+    /// is used. `init` always writes to this path.
     #[arg(long, global = true, default_value = ".seshconf.toml")]
     pub config: PathBuf,
 
+    /// Format of the config file, whatever its extension
+    ///
+    /// Without this, `.json` files are read as JSON and anything else as
+    /// TOML. Only applies to `--config`; `--overlay` goes by its extension.
+    #[arg(long, global = true, value_enum, value_name = "FORMAT")]
+    pub config_format: Option<ConfigFormat>,
+
+    /// Directory holding the global `default.toml` config, used when
+    /// there's no project config
+    ///
+    /// Defaults to `$XDG_CONFIG_HOME/sesh` (usually `~/.config/sesh`).
+    #[arg(long, global = true, env = "SESH_CONFIG_DIR")]
+    pub config_dir: Option<PathBuf>,
+
+    /// Path to a second config file merged on top of `--config`
+    ///
+    /// Windows in it replace the base config's windows with the same name,
+    /// and the rest are appended. Its `name`, if set, wins.
+    #[arg(long, global = true)]
+    pub overlay: Option<PathBuf>,
+
+    /// Shell to run the session's windows in, overriding the config's `shell`
+    #[arg(long, global = true)]
+    pub shell: Option<String>,
+
     /// Suppress output messages
+    ///
+    /// Errors are still printed, except ones that only say the session
+    /// or a window isn't running (the exit code says so).
     #[arg(short, long, global = true, action)]
     pub quiet: bool,
+
+    /// Like `--quiet`, but suppress error messages too, leaving only
+    /// the exit code
+    #[arg(long, global = true, action)]
+    pub silent: bool,
+
+    /// When to color output
+    #[arg(long, global = true, value_enum, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
+    /// Override the session name from the config file
+    #[arg(long, global = true)]
+    pub session_name: Option<String>,
+
+    /// Prepend this to the session name, e.g. to avoid clashing with
+    /// other users on a shared tmux server
+    ///
+    /// Only the tmux session name changes, not the config file.
+    #[arg(long, global = true, env = "SESH_PREFIX")]
+    pub prefix: Option<String>,
+
+    /// Run as if sesh was started in this directory
+    #[arg(long, global = true)]
+    pub cwd: Option<PathBuf>,
+
+    /// The tmux executable to use, if it isn't `tmux` on the PATH
+    #[arg(long, global = true, env = "SESH_TMUX_BIN", default_value = "tmux")]
+    pub tmux_bin: PathBuf,
+
+    /// Times to retry tmux commands that fail transiently
+    #[arg(long, global = true, default_value_t = 2)]
+    pub retries: u32,
+
+    /// Set when `config` is the global `default.toml` fallback, which
+    /// is shared by every project and so isn't edited
+    #[arg(skip)]
+    pub global_config: bool,
 }
 
 impl Cli {
     pub fn config_file_exists(&self) -> bool {
         self.config.exists()
     }
+
+    /// Whether to print `err` before exiting with its code.
+    pub fn shows_error(&self, err: &SeshError) -> bool {
+        !(self.silent || (self.quiet && err.is_not_running()))
+    }
+
+    /// Whether output should be colored.
+    ///
+    /// In `auto` mode, color is used when stdout is a terminal
+    /// and the `NO_COLOR` env var isn't set.
+    pub fn use_color(&self) -> bool {
+        match self.color {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal()
+            }
+        }
+    }
+}
+
+/// A format the config file can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Color when writing to a terminal (and `NO_COLOR` isn't set)
+    Auto,
+    /// Always color output
+    Always,
+    /// Never color output
+    Never,
 }
 
 #[derive(Debug, Subcommand)]
@@ -34,22 +144,66 @@ pub enum Command {
     Init(InitArgs),
 
     /// Check the status of the session and its windows
-    Status,
+    Status(StatusArgs),
 
     /// Start the TMUX session and all configured windows
-    Up,
+    Up(UpArgs),
 
     /// Stop the TMUX session
-    Down,
+    Down(DownArgs),
 
     /// Start the session and attach to it (selects default window if configured)
-    Attach,
+    ///
+    /// Same as `up --foreground`.
+    Attach(AttachArgs),
 
     /// Restart the session (runs down then up)
-    Restart,
+    Restart(RestartArgs),
+
+    /// Kill the tmux server, and with it ALL tmux sessions (not just sesh's)
+    ///
+    /// Asks for confirmation first, even with `--quiet`.
+    Nuke(NukeArgs),
+
+    /// Send keys to a window in the running session
+    Send(SendArgs),
+
+    /// Show the processes running in each of the session's windows
+    Ps,
+
+    /// Print the last lines of a window's output
+    Logs(LogsArgs),
+
+    /// Run a one-off command in a tmux popup (tmux 3.2+)
+    ///
+    /// The popup opens over the current tmux client, so this needs to
+    /// be run from inside an attached session (e.g. from a key binding).
+    Popup(PopupArgs),
+
+    /// Save the running session's windows and panes to a snapshot file
+    ///
+    /// Each pane's working directory is saved, along with the name of
+    /// the program running in it (tmux doesn't report its arguments).
+    Save(SaveArgs),
+
+    /// Recreate a session from a snapshot written by `save`
+    ///
+    /// This doesn't need the config: the session's name, windows, panes
+    /// and their directories all come from the snapshot. Windows keep
+    /// their saved indices. Programs are only re-run with `--commands`.
+    Restore(RestoreArgs),
 
     /// Manage windows in the session configuration
     Window(WindowArgs),
+
+    /// Inspect the session configuration file
+    Config(ConfigArgs),
+
+    /// Print a JSON Schema for the config file
+    ///
+    /// Point your editor at it (e.g. with Taplo) to validate and
+    /// autocomplete `.seshconf.toml`.
+    Schema,
 }
 
 #[derive(Debug, Args)]
@@ -63,6 +217,450 @@ pub struct InitArgs {
     /// Overwrite existing file if it already exists.
     #[arg(long, action)]
     pub overwrite: bool,
+
+    /// Pre-populate the config with a built-in set of windows.
+    #[arg(short, long, value_enum)]
+    pub template: Option<Template>,
+
+    /// Print the config path and session name as JSON, e.g. to
+    /// capture a randomly generated name.
+    #[arg(long, action)]
+    pub json: bool,
+
+    /// Print just this value to stdout, e.g. `NAME=$(sesh init -o name)`.
+    /// Other messages go to stderr.
+    #[arg(
+        short = 'o',
+        long = "print",
+        visible_alias = "output",
+        value_enum,
+        conflicts_with = "json"
+    )]
+    pub print: Option<InitField>,
+}
+
+/// A value `init --print` can output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum InitField {
+    /// The session name, useful when it was randomly generated
+    Name,
+    /// The config file's path
+    Path,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Template {
+    /// Editor, `npm run dev` server, and a shell
+    Node,
+    /// Editor, `cargo watch`, and a shell
+    Rust,
+    /// Editor, `python3` REPL, and a shell
+    Python,
+}
+
+#[derive(Debug, Args, Default)]
+pub struct StatusArgs {
+    /// Keep re-rendering the status until interrupted with Ctrl-C.
+    #[arg(short, long, action)]
+    pub watch: bool,
+
+    /// Seconds between refreshes in watch mode.
+    #[arg(long, default_value_t = 2, requires = "watch")]
+    pub interval: u64,
+
+    /// Print the status as JSON.
+    ///
+    /// In watch mode, one JSON line is printed per refresh.
+    #[arg(long, action)]
+    pub json: bool,
+
+    /// Print one line per window from a template, e.g. `{name}: {state}`.
+    ///
+    /// Available tokens: {session}, {name}, {index}, {state},
+    /// {running} and {window_count}. Use `{{` and `}}` for literal braces.
+    #[arg(long, value_parser = parse_status_format, conflicts_with = "json")]
+    pub format: Option<Format>,
+
+    /// Show how long the session has been running, e.g. "up 2h 13m".
+    #[arg(long, action, conflicts_with_all = ["json", "format"])]
+    pub show_age: bool,
+
+    /// Only check this window. With `--quiet`, the exit code says
+    /// whether it (and the session) is running.
+    #[arg(long, value_name = "NAME")]
+    pub window: Option<String>,
+
+    /// Only act if the config is tagged with this, otherwise do nothing.
+    #[arg(long)]
+    pub tag: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct UpArgs {
+    /// Print the session and each window's state as JSON.
+    #[arg(long, action)]
+    pub json: bool,
+
+    /// Attach to the session once it's up, even if it was already running.
+    ///
+    /// The session is still created detached, so its windows can
+    /// be set up before attaching.
+    #[arg(long, action, conflicts_with = "json")]
+    pub foreground: bool,
+
+    /// Attach to the session once it's up with this window selected, like
+    /// `--foreground`. Inside tmux, the current client is switched to it.
+    #[arg(long, value_name = "NAME", conflicts_with = "json")]
+    pub attach_window: Option<String>,
+
+    /// Kill the session first if it's already running, so it's
+    /// recreated from scratch.
+    #[arg(long, action)]
+    pub kill_existing: bool,
+
+    /// Only start these windows (comma-separated). The session is
+    /// still created if it isn't running.
+    #[arg(
+        long,
+        value_name = "NAME",
+        value_delimiter = ',',
+        conflicts_with = "except"
+    )]
+    pub only: Vec<String>,
+
+    /// Start every window except these (comma-separated).
+    #[arg(long, value_name = "NAME", value_delimiter = ',')]
+    pub except: Vec<String>,
+
+    /// Wait until a window prints some text before returning, e.g.
+    /// `server:Listening on`. Can be repeated; each is checked in order.
+    #[arg(long, value_name = "WINDOW:TEXT")]
+    pub wait: Vec<WaitSpec>,
+
+    /// Seconds to wait for each `--wait` before giving up.
+    #[arg(long, default_value_t = 30, requires = "wait")]
+    pub wait_timeout: u64,
+
+    /// Start window 0 in the current directory instead of its
+    /// configured `cwd` (or the top-level `cwd`). Other windows
+    /// are unaffected.
+    #[arg(long, action)]
+    pub preserve_pwd: bool,
+
+    /// Re-send the command to existing windows whose command has exited,
+    /// leaving them at a shell prompt. A middle ground between `up`
+    /// and `restart`.
+    #[arg(long, action)]
+    pub rebuild_dead: bool,
+
+    /// Only send keys and commands when `up` creates the session. Windows
+    /// missing from a running session are still created, but left at a
+    /// prompt.
+    #[arg(long, action, conflicts_with = "rebuild_dead")]
+    pub no_send_on_existing: bool,
+
+    /// Print the steps `up` will take (creating the session and windows,
+    /// sending commands, ...) before taking them.
+    #[arg(long, action, conflicts_with = "json")]
+    pub print_plan: bool,
+
+    /// Once the session is up, check for windows whose command has
+    /// already exited (leaving them at a shell prompt) and report them.
+    #[arg(long, action, conflicts_with_all = ["json", "dry_run"])]
+    pub health_check: bool,
+
+    /// Seconds to give commands to start before `--health-check` runs.
+    #[arg(long, default_value_t = 1, requires = "health_check")]
+    pub health_check_delay: u64,
+
+    /// Only print the steps `up` would take, without changing anything.
+    #[arg(long, action, conflicts_with_all = ["json", "foreground", "attach_window", "wait"])]
+    pub dry_run: bool,
+
+    /// How many windows to send commands to and split into panes at
+    /// once. By default they're set up one at a time.
+    #[arg(long, short = 'j', default_value_t = 1)]
+    pub jobs: usize,
+
+    /// Refuse to start a session with more than this many windows
+    /// (after `count` is expanded), in case the config is malformed.
+    #[arg(long, value_name = "N", default_value_t = 50)]
+    pub max_windows: usize,
+
+    /// Stop at the first window that fails to start. Otherwise the
+    /// rest are still started and every failure is reported at the end.
+    #[arg(long, action)]
+    pub fail_fast: bool,
+
+    /// Don't read a config file; build the session from `--name`
+    /// and `--window` instead, for a throwaway session.
+    #[arg(long, action)]
+    pub no_config: bool,
+
+    /// Session name for `--no-config`.
+    #[arg(long, requires = "no_config")]
+    pub name: Option<String>,
+
+    /// A window for `--no-config`, as `name:command` (or just `name` for a
+    /// shell). Everything after the first `:` is the command. Can be repeated.
+    #[arg(long = "window", value_name = "NAME:COMMAND", requires = "no_config")]
+    pub windows: Vec<WindowSpec>,
+
+    /// Only act if the config is tagged with this, otherwise do nothing.
+    #[arg(long)]
+    pub tag: Option<String>,
+}
+
+impl Default for UpArgs {
+    /// The same as `sesh up` with no flags.
+    fn default() -> Self {
+        let matches = Self::augment_args(clap::Command::new("up")).get_matches_from(["up"]);
+        Self::from_arg_matches(&matches).expect("up has no required flags")
+    }
+}
+
+/// A `up --wait` readiness check: text a window should print.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WaitSpec {
+    pub window: String,
+    pub text: String,
+}
+
+/// An `up --no-config --window` spec: a window name and optional command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowSpec {
+    pub name: String,
+    pub command: Option<String>,
+}
+
+impl FromStr for WindowSpec {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        // Commands can contain colons too, so only split on the first one
+        let (name, command) = match spec.split_once(':') {
+            Some((name, command)) => (name, Some(command).filter(|c| !c.is_empty())),
+            None => (spec, None),
+        };
+        if name.is_empty() {
+            return Err(format!("expected NAME:COMMAND, got '{}'", spec));
+        }
+        Ok(Self {
+            name: name.to_string(),
+            command: command.map(str::to_string),
+        })
+    }
+}
+
+impl FromStr for WaitSpec {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        match spec.split_once(':') {
+            Some((window, text)) if !window.is_empty() && !text.is_empty() => Ok(Self {
+                window: window.to_string(),
+                text: text.to_string(),
+            }),
+            _ => Err(format!("expected WINDOW:TEXT, got '{}'", spec)),
+        }
+    }
+}
+
+#[derive(Debug, Args, Default)]
+pub struct DownArgs {
+    /// Print whether the session was killed as JSON.
+    #[arg(long, action)]
+    pub json: bool,
+
+    /// Only act if the config is tagged with this, otherwise do nothing.
+    #[arg(long)]
+    pub tag: Option<String>,
+}
+
+#[derive(Debug, Args, Default)]
+pub struct AttachArgs {
+    /// Only attach if the session is already running, instead of
+    /// starting it first.
+    #[arg(long, action)]
+    pub if_exists: bool,
+
+    /// Select this window instead of the config's default window.
+    #[arg(short, long, conflicts_with = "attach_or_create")]
+    pub window: Option<String>,
+
+    /// Detach any other clients attached to the session, e.g. one
+    /// left open on another machine.
+    #[arg(long, action)]
+    pub detach_others: bool,
+
+    /// Attach with a single `tmux new-session -A`, creating a bare session
+    /// if it isn't running. This is a lightweight attach: the configured
+    /// windows aren't created, but there's no gap between checking for
+    /// the session and attaching to it.
+    #[arg(long, action, conflicts_with = "if_exists")]
+    pub attach_or_create: bool,
+
+    /// Attach read-only, so nothing typed reaches the session, e.g. to
+    /// watch a pairing partner. Can't be used from inside tmux.
+    #[arg(short, long, action, conflicts_with = "attach_or_create")]
+    pub read_only: bool,
+
+    /// Reattach if the tmux client loses its terminal instead of
+    /// exiting. A clean detach still exits.
+    ///
+    /// If the terminal sesh runs in goes away too (e.g. SSH
+    /// disconnecting), sesh gets the same SIGHUP and exits with it,
+    /// unless it's run under `nohup` or similar.
+    #[arg(long, action, conflicts_with = "attach_or_create")]
+    pub reconnect: bool,
+
+    /// Seconds to wait before each `--reconnect` attempt.
+    #[arg(long, default_value_t = 2, requires = "reconnect")]
+    pub reconnect_interval: u64,
+
+    /// How many times in a row `--reconnect` reattaches before giving
+    /// up. An attach that stays up for a minute resets the count.
+    #[arg(long, default_value_t = 5, requires = "reconnect")]
+    pub reconnect_retries: u32,
+}
+
+#[derive(Debug, Args)]
+pub struct RestartArgs {
+    /// Don't ask for confirmation before killing a running session.
+    #[arg(short, long, action)]
+    pub yes: bool,
+
+    /// Re-select the window that was active before restarting, or the
+    /// default window if it's no longer in the config.
+    #[arg(long, action)]
+    pub keep_current_window: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct NukeArgs {
+    /// Don't ask for confirmation before killing every session.
+    #[arg(short, long, action)]
+    pub yes: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct SendArgs {
+    /// Name of the window to send keys to
+    #[arg(short, long, required_unless_present = "all", conflicts_with = "all")]
+    pub window: Option<String>,
+
+    /// Send the keys to every running window in the session, e.g. to
+    /// `clear` them all.
+    #[arg(long, action)]
+    pub all: bool,
+
+    /// With `--all`, skip these windows (comma-separated).
+    #[arg(long, value_name = "NAME", value_delimiter = ',', requires = "all")]
+    pub except: Vec<String>,
+
+    /// Don't press Enter after the keys.
+    #[arg(long, action)]
+    pub no_enter: bool,
+
+    /// Type the keys exactly as given, joined with spaces, instead of
+    /// reading key names like `C-c` or `Escape` (tmux `send-keys -l`).
+    ///
+    /// Useful for text that tmux would otherwise treat specially,
+    /// like a lone `Enter` or a trailing `;`.
+    #[arg(long, action)]
+    pub literal: bool,
+
+    /// Keys to send. Each argument is a separate tmux key, so key names
+    /// like `C-c` work and anything else is typed as is (quote a command
+    /// to keep its spaces)
+    #[arg(required = true)]
+    pub keys: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    pub command: ConfigCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommands {
+    /// Print the path of the config file sesh would use
+    ///
+    /// Exits with an error naming the path, without printing it, if
+    /// the file doesn't exist.
+    Path,
+
+    /// Print the config as sesh sees it, with defaults filled in
+    ///
+    /// Unlike the raw file, this includes the `--overlay`, expanded
+    /// `count`s, inherited commands and directories (as absolute paths)
+    /// and the `--session-name`/`--prefix`/`--shell` overrides.
+    Dump(ConfigDumpArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct ConfigDumpArgs {
+    /// Print the config as JSON instead of TOML.
+    #[arg(long, action)]
+    pub json: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct LogsArgs {
+    /// Name of the window to print the output of
+    #[arg(short, long)]
+    pub window: String,
+
+    /// Number of lines to print.
+    #[arg(short = 'n', long, default_value_t = 50)]
+    pub lines: usize,
+
+    /// Keep re-capturing the output until interrupted with Ctrl-C.
+    #[arg(short, long, action)]
+    pub follow: bool,
+
+    /// Seconds between captures in follow mode.
+    #[arg(long, default_value_t = 2, requires = "follow")]
+    pub interval: u64,
+}
+
+#[derive(Debug, Args)]
+pub struct PopupArgs {
+    /// Command to run in the popup, e.g. `lazygit`. The popup closes
+    /// when it exits.
+    #[arg(short, long)]
+    pub command: String,
+
+    /// Popup width, in cells or as a percentage.
+    #[arg(long, default_value = "80%")]
+    pub width: String,
+
+    /// Popup height, in cells or as a percentage.
+    #[arg(long, default_value = "80%")]
+    pub height: String,
+}
+
+#[derive(Debug, Args)]
+pub struct SaveArgs {
+    /// Snapshot file to write. Defaults to `.sesh-state.json` next to
+    /// the config.
+    #[arg(short, long)]
+    pub file: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct RestoreArgs {
+    /// Snapshot file to read. Defaults to `.sesh-state.json` next to
+    /// the config.
+    #[arg(short, long)]
+    pub file: Option<PathBuf>,
+
+    /// Also re-run the program saved for each pane. Only its name was
+    /// saved, not its arguments, so e.g. `nvim src/main.rs` comes back
+    /// as `nvim`.
+    #[arg(long, action)]
+    pub commands: bool,
 }
 
 #[derive(Debug, Args)]
@@ -78,26 +676,131 @@ pub enum WindowCommands {
 
     /// Remove a window from the session configuration
     Remove(WindowRemoveArgs),
+
+    /// Move a window to another position in the session configuration,
+    /// and in the session too if it's running
+    Move(WindowMoveArgs),
+
+    /// Show all of a window's settings from the session configuration
+    Show(WindowShowArgs),
+
+    /// List the windows in the session configuration
+    List(WindowListArgs),
+
+    /// Include a disabled window in `up` and `status` again
+    Enable(WindowToggleArgs),
+
+    /// Leave a window out of `up` and `status`, keeping it in the config
+    Disable(WindowToggleArgs),
+
+    /// Renumber the running session's windows to close gaps in their indices
+    Renumber,
+}
+
+/// Options shared by the `window` commands that rewrite the config.
+#[derive(Debug, Args, Default)]
+pub struct ConfigWriteArgs {
+    /// Copy the config to `<config>.bak` before rewriting it.
+    #[arg(long, action)]
+    pub backup: bool,
 }
 
 #[derive(Debug, Args)]
 pub struct WindowAddArgs {
-    /// Optional name of the command
+    /// Optional name of the window
     ///
-    /// Defaults to `cmd`
+    /// Defaults to one picked by the config's `window_naming`
+    /// (by default, the command's program, like `npm`)
     #[arg(short, long)]
     pub name: Option<String>,
 
+    /// Add a window that just opens a shell, without a command
+    #[arg(long, visible_alias = "no-command", action, conflicts_with = "cmd")]
+    pub empty: bool,
+
     /// Name of the command to run
-    pub cmd: String,
+    #[arg(required_unless_present = "empty")]
+    pub cmd: Option<String>,
 
     /// Arguments to the command
     pub args: Vec<String>,
+
+    /// Add the window even if the config already has one with the same name
+    #[arg(long, action)]
+    pub allow_duplicate: bool,
+
+    #[command(flatten)]
+    pub write: ConfigWriteArgs,
 }
 
 #[derive(Debug, Args)]
 pub struct WindowRemoveArgs {
-    /// Name of the window to remove
+    /// Name of the window to remove. Can be a glob like `worker-*`
+    /// to remove every matching window.
+    #[arg(short, long)]
+    pub name: Option<String>,
+
+    #[command(flatten)]
+    pub write: ConfigWriteArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct WindowMoveArgs {
+    /// Name of the window to move
     #[arg(short, long)]
+    pub name: String,
+
+    /// Index to move the window to, where 0 is the first window
+    #[arg(long)]
+    pub to: usize,
+
+    #[command(flatten)]
+    pub write: ConfigWriteArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct WindowShowArgs {
+    /// Name of the window to show
+    #[arg(
+        short,
+        long,
+        required_unless_present = "index",
+        conflicts_with = "index"
+    )]
     pub name: Option<String>,
+
+    /// Index of the window to show, where 0 is the first window
+    #[arg(short, long)]
+    pub index: Option<usize>,
+
+    /// Print the window's settings as JSON.
+    #[arg(long, action)]
+    pub json: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct WindowToggleArgs {
+    /// Name of the window to enable or disable
+    #[arg(short, long)]
+    pub name: String,
+
+    #[command(flatten)]
+    pub write: ConfigWriteArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct WindowListArgs {
+    /// Order to list the windows in. Only the output is sorted,
+    /// not the config.
+    #[arg(long, value_enum, default_value_t = WindowSort::Index)]
+    pub sort: WindowSort,
+}
+
+/// An order `window list` can show windows in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum WindowSort {
+    /// In config order
+    Index,
+    /// Alphabetically by name, with unnamed windows last
+    Name,
 }
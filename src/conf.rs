@@ -1,47 +1,850 @@
 //! App configuration structs.
-use anyhow::{Result, anyhow};
+use crate::cli::ConfigFormat;
+use crate::error::{Result, SeshError};
+use schemars::{JsonSchema, Schema, schema_for};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs::{self, read_to_string};
-use std::path::PathBuf;
-use validator::Validate;
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+use toml_edit::{ArrayOfTables, DocumentMut, Item};
+use validator::{Validate, ValidationError};
 
-#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Validate, Default)]
+/// tmux's built-in window layouts.
+pub const LAYOUTS: [&str; 5] = [
+    "even-horizontal",
+    "even-vertical",
+    "main-horizontal",
+    "main-vertical",
+    "tiled",
+];
+
+/// How windows without a `name` can be named (see `Config::window_naming`).
+pub const WINDOW_NAMINGS: [&str; 3] = ["command", "full-command", "index"];
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Validate, Default, JsonSchema)]
 pub struct Config {
+    /// Name of the tmux session. Required, except in `--overlay` files.
+    // Defaulted so overlays can leave it out; `load` still requires it.
+    #[serde(default)]
+    #[validate(length(min = 1, message = "missing session `name`"))]
     pub name: String,
+    /// What the session is for, shown by `status`. Purely informational.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Command to run in any window without its own `command`, written
+    /// either way a window's `command` can be.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_command",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schemars(with = "Option<CommandInput>")]
+    #[validate(custom(function = "validate_command"))]
+    pub default_command: Option<Vec<String>>,
+    /// How to name windows without a `name`: after their command's
+    /// program (`command`, the default), their whole command
+    /// (`full-command`, e.g. `npm-run-dev`) or their position
+    /// (`index`, e.g. `window-2`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(custom(function = "validate_window_naming"))]
+    #[schemars(extend("enum" = WINDOW_NAMINGS))]
+    pub window_naming: Option<String>,
+    /// Shell for the session's windows and panes, instead of the
+    /// login shell, so commands run with predictable syntax.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shell: Option<String>,
+    /// Directory to start windows in, unless they set their own `cwd`.
+    /// Relative paths are resolved against the config file's directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<PathBuf>,
+    /// Command sent to window 0 when `up` creates the session, before any
+    /// window's own command, e.g. to activate a virtualenv. Unlike
+    /// `on_attach`, it runs inside tmux.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(custom(function = "validate_command"))]
+    pub setup: Option<Vec<String>>,
+    /// Command run in the current terminal (not in tmux) just before
+    /// attaching, e.g. `["git", "status"]`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_attach: Option<Vec<String>>,
+    /// Labels for grouping configs, e.g. `work`, matched by `--tag`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    /// tmux options (`set-option -t <session>`) applied when the
+    /// session is created, e.g. `mouse = "on"`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<BTreeMap<String, String>>,
+    /// Like `options`, but set globally with `set-option -g`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub global_options: Option<BTreeMap<String, String>>,
+    /// The session's windows, in order.
+    #[serde(default)]
+    #[validate(nested)]
     pub window: Vec<WindowConf>,
 }
 
-#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Validate)]
+// Fields are serialized in declaration order (`name`, `command`, then
+// the optional extras) and absent ones are skipped, so rewriting a
+// config with `window add` doesn't reshuffle windows that were already
+// there.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Validate, Default, JsonSchema)]
+#[validate(schema(function = "validate_focus"))]
+#[validate(schema(function = "validate_one_command"))]
 pub struct WindowConf {
+    /// Name of the tmux window.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
+    /// Command to run in the window, e.g. `["npm", "run", "dev"]`, or
+    /// as one string, `"npm run dev"`, which is typed into the shell as-is.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_command",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schemars(with = "Option<CommandInput>")]
+    #[validate(custom(function = "validate_command"))]
     pub command: Option<Vec<String>>,
-    #[serde(default)]
+    /// Command lines to run one after another, each followed by Enter,
+    /// e.g. for a setup script. Use either this or `command`, not both.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(custom(function = "validate_commands"))]
+    pub commands: Option<Vec<Vec<String>>>,
+    /// Raw tmux key names (e.g. `C-c`, `Escape`) sent before `command`,
+    /// which is then typed and followed by Enter.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keys: Option<Vec<String>>,
+    /// Select this window when attaching. Only one window should set it.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub default: Option<bool>,
+    /// One of tmux's built-in layouts, applied once the panes are created.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(custom(function = "validate_layout"))]
+    #[schemars(extend("enum" = LAYOUTS))]
+    pub layout: Option<String>,
+    /// Extra panes split off from the window. The window's own
+    /// `command` runs in the first pane.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(nested)]
+    pub pane: Option<Vec<PaneConf>>,
+    /// Index of the pane to focus once the panes are created, where 0 is
+    /// the window's own pane. Defaults to 0 when there are extra panes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub focus: Option<usize>,
+    /// Broadcast keystrokes to all of the window's panes at once (tmux's
+    /// `synchronize-panes`). Off by default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub synchronize: Option<bool>,
+    /// Let tmux rename the window after the program running in it (tmux's
+    /// `automatic-rename`). Off by default, so the configured name sticks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_rename: Option<bool>,
+    /// Clear the prompt's line (`C-u`) before typing `command`, so a
+    /// half-typed line doesn't mangle it. Off by default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clear_line: Option<bool>,
+    /// Directory the window and its panes start in, overriding the
+    /// top-level `cwd`. Relative paths are resolved against the
+    /// config file's directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<PathBuf>,
+    /// File to append the window's output to (with tmux's `pipe-pane`),
+    /// relative to the config file's directory. This captures the raw
+    /// terminal bytes, escape sequences and all.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_file: Option<PathBuf>,
+    /// Leave the window out of `up` and `status` without removing it
+    /// from the config. Off by default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disabled: Option<bool>,
+    /// Create this many copies of the window, all running the same
+    /// command. When more than 1, names are suffixed with their
+    /// number (`worker-1`, `worker-2`, ...).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(range(min = 1, message = "window `count` must be at least 1"))]
+    pub count: Option<usize>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Validate, Default, JsonSchema)]
+pub struct PaneConf {
+    /// Command to run in the pane, as an array or one string like
+    /// a window's `command`.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_command",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schemars(with = "Option<CommandInput>")]
+    #[validate(custom(function = "validate_command"))]
+    pub command: Option<Vec<String>>,
+    /// Directory the pane starts in, overriding the window's `cwd`.
+    /// Relative paths are resolved against the config file's directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<PathBuf>,
+}
+
+fn validate_focus(window: &WindowConf) -> std::result::Result<(), ValidationError> {
+    let Some(focus) = window.focus else {
+        return Ok(());
+    };
+
+    // The window's own pane plus any extra ones
+    let panes = 1 + window.pane.as_ref().map_or(0, Vec::len);
+    if focus < panes {
+        return Ok(());
+    }
+
+    let mut err = ValidationError::new("focus");
+    err.message = Some(
+        format!(
+            "focus pane {} is out of range (window has {} pane(s))",
+            focus, panes
+        )
+        .into(),
+    );
+    Err(err)
+}
+
+/// A command as it can be written in the config.
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum CommandInput {
+    /// A whole command line, e.g. `"npm run dev"`
+    Line(String),
+    /// A program and its arguments, e.g. `["npm", "run", "dev"]`
+    Args(Vec<String>),
+}
+
+/// Read a command written either way (see [`CommandInput`]). A command
+/// line is kept whole, as a single element, since the shell it's typed
+/// into splits it up anyway.
+fn deserialize_command<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Option<Vec<String>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(
+        Option::<CommandInput>::deserialize(deserializer)?.map(|command| match command {
+            // Left empty so it fails validation like `[]` does
+            CommandInput::Line(line) if line.trim().is_empty() => vec![],
+            CommandInput::Line(line) => vec![line],
+            CommandInput::Args(args) => args,
+        }),
+    )
+}
+
+/// The program a command runs: its first word, even when the
+/// command was written as one string.
+pub fn command_program(command: &[String]) -> Option<&str> {
+    command.first()?.split_whitespace().next()
+}
+
+/// A command can be left out, but not empty, which would
+/// just send a bare Enter.
+fn validate_command(command: &[String]) -> std::result::Result<(), ValidationError> {
+    if !command.is_empty() {
+        return Ok(());
+    }
+    let mut err = ValidationError::new("command");
+    err.message = Some("`command` can't be empty; leave it out to run nothing".into());
+    Err(err)
+}
+
+/// A `commands` list can't be empty, and neither can its commands.
+fn validate_commands(commands: &[Vec<String>]) -> std::result::Result<(), ValidationError> {
+    if commands.is_empty() {
+        let mut err = ValidationError::new("commands");
+        err.message = Some("`commands` can't be empty; leave it out to run nothing".into());
+        return Err(err);
+    }
+    commands
+        .iter()
+        .try_for_each(|command| validate_command(command))
+}
+
+fn validate_one_command(window: &WindowConf) -> std::result::Result<(), ValidationError> {
+    if window.command.is_none() || window.commands.is_none() {
+        return Ok(());
+    }
+    let mut err = ValidationError::new("commands");
+    err.message = Some("a window can set `command` or `commands`, not both".into());
+    Err(err)
+}
+
+fn validate_layout(layout: &str) -> std::result::Result<(), ValidationError> {
+    if LAYOUTS.contains(&layout) {
+        return Ok(());
+    }
+    let mut err = ValidationError::new("layout");
+    err.message = Some(
+        format!(
+            "unknown layout '{}' (expected one of: {})",
+            layout,
+            LAYOUTS.join(", ")
+        )
+        .into(),
+    );
+    Err(err)
+}
+
+fn validate_window_naming(naming: &str) -> std::result::Result<(), ValidationError> {
+    if WINDOW_NAMINGS.contains(&naming) {
+        return Ok(());
+    }
+    let mut err = ValidationError::new("window_naming");
+    err.message = Some(
+        format!(
+            "unknown window_naming '{}' (expected one of: {})",
+            naming,
+            WINDOW_NAMINGS.join(", ")
+        )
+        .into(),
+    );
+    Err(err)
+}
+
+/// Make a command usable as a window name: anything but letters, digits,
+/// `-` and `_` becomes a `-`, without repeats or any at either end.
+fn sanitize_window_name(command: &str) -> String {
+    command
+        .split(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+        .flat_map(|word| word.split('-'))
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
 }
 
 impl Config {
-    /// Load a config file from path.
+    /// A JSON Schema describing the config file, for editors to
+    /// validate and autocomplete it.
+    pub fn json_schema() -> Schema {
+        schema_for!(Config)
+    }
+
+    /// Get the command a window should run, falling back to `default_command`.
+    /// For a window with a list of `commands`, this is the first one.
+    pub fn window_command<'a>(&'a self, window: &'a WindowConf) -> Option<&'a Vec<String>> {
+        match &window.commands {
+            Some(commands) => commands.first(),
+            None => window.command.as_ref().or(self.default_command.as_ref()),
+        }
+    }
+
+    /// Get every command line a window should run, in order: its
+    /// `commands`, or else just its one command.
+    pub fn window_commands<'a>(&'a self, window: &'a WindowConf) -> Vec<&'a Vec<String>> {
+        match &window.commands {
+            Some(commands) => commands.iter().collect(),
+            None => self.window_command(window).into_iter().collect(),
+        }
+    }
+
+    /// Get the name a window is given in tmux: its own `name`, or else
+    /// one picked by `window_naming` (see [`Config::default_window_name`]).
+    pub fn window_name(&self, window: &WindowConf, index: usize) -> Option<String> {
+        window.name.clone().or_else(|| {
+            self.default_window_name(self.window_command(window).map(Vec::as_slice), index)
+        })
+    }
+
+    /// Name a window running `command` at `index` by the `window_naming`
+    /// policy. Returns `None` when the policy goes by the command and
+    /// there isn't one.
+    pub fn default_window_name(&self, command: Option<&[String]>, index: usize) -> Option<String> {
+        match self.window_naming.as_deref() {
+            Some("index") => Some(format!("window-{}", index)),
+            Some("full-command") => command
+                .map(|command| sanitize_window_name(&command.join(" ")))
+                .filter(|name| !name.is_empty()),
+            _ => command.and_then(command_program).map(|program| {
+                Path::new(program)
+                    .file_name()
+                    .map_or(program.to_string(), |name| {
+                        name.to_string_lossy().into_owned()
+                    })
+            }),
+        }
+    }
+
+    /// Get the directory a window should start in, falling back to `cwd`.
+    pub fn window_cwd<'a>(&'a self, window: &'a WindowConf) -> Option<&'a PathBuf> {
+        window.cwd.as_ref().or(self.cwd.as_ref())
+    }
+
+    /// Fill in everything windows otherwise inherit or default to: the
+    /// top-level `default_command` and `cwd`, the window's `cwd` for its
+    /// panes, `focus` when there are panes, and the off-by-default flags.
+    pub fn apply_defaults(&mut self) {
+        for window in &mut self.window {
+            if window.command.is_none() && window.commands.is_none() {
+                window.command = self.default_command.clone();
+            }
+            if window.cwd.is_none() {
+                window.cwd = self.cwd.clone();
+            }
+            for pane in window.pane.iter_mut().flatten() {
+                if pane.cwd.is_none() {
+                    pane.cwd = window.cwd.clone();
+                }
+            }
+            if window.pane.as_ref().is_some_and(|panes| !panes.is_empty()) {
+                window.focus.get_or_insert(0);
+            }
+            window.default.get_or_insert(false);
+            window.synchronize.get_or_insert(false);
+            window.allow_rename.get_or_insert(false);
+            window.clear_line.get_or_insert(false);
+            window.disabled.get_or_insert(false);
+        }
+    }
+
+    /// Whether the config is tagged with `tag`.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().flatten().any(|t| t == tag)
+    }
+
+    /// Load a config file from path, without an overlay.
+    #[cfg(test)]
     pub fn load(path: &PathBuf) -> Result<Self> {
-        if !path.exists() {
-            return Err(anyhow!("File does not exist"));
+        Self::load_with_overlay(path, None, None)
+    }
+
+    /// Load a config file from path, merging an optional
+    /// overlay file on top of it (see [`Config::merge`]).
+    ///
+    /// `format` overrides the config file's extension (see
+    /// [`config_format`]); the overlay always goes by its own.
+    pub fn load_with_overlay(
+        path: &PathBuf,
+        overlay: Option<&PathBuf>,
+        format: Option<ConfigFormat>,
+    ) -> Result<Self> {
+        let mut conf = Self::parse(path, format)?;
+        if let Some(overlay) = overlay {
+            conf = conf.merge(Self::parse(overlay, None)?);
         }
-        let txt = read_to_string(&path)?;
-        let conf = toml::from_str(&txt)?;
+        conf.validate()?;
         Ok(conf)
     }
 
+    /// Replace each window that has a `count` with that many copies.
+    /// Only the first copy keeps `default`.
+    ///
+    /// Configs are loaded as written, so this is only done where the
+    /// windows are matched to a running session.
+    pub fn expand_counts(&mut self) {
+        self.window = std::mem::take(&mut self.window)
+            .into_iter()
+            .flat_map(|window| match window.count {
+                Some(count) if count > 1 => (1..=count)
+                    .map(|i| WindowConf {
+                        name: window.name.as_ref().map(|name| format!("{}-{}", name, i)),
+                        default: window.default.filter(|_| i == 1),
+                        count: None,
+                        ..window.clone()
+                    })
+                    .collect(),
+                _ => vec![window],
+            })
+            .collect();
+    }
+
+    /// Parse a config file without validating it.
+    fn parse(path: &PathBuf, format: Option<ConfigFormat>) -> Result<Self> {
+        if !path.exists() {
+            return Err(SeshError::ConfigNotFound(path.clone()));
+        }
+        let txt = read_to_string(path)?;
+        match config_format(path, format) {
+            ConfigFormat::Toml => Self::parse_str(path, &txt),
+            ConfigFormat::Json => Self::parse_json(path, &txt),
+        }
+    }
+
+    /// Parse the contents of the TOML config file at `path`, which is
+    /// only used in errors, without validating it.
+    fn parse_str(path: &Path, txt: &str) -> Result<Self> {
+        toml::from_str(txt).map_err(|e| parse_error(path, txt, e.span(), e.message()))
+    }
+
+    /// Like [`Config::parse_str`], for a JSON config file.
+    fn parse_json(path: &Path, txt: &str) -> Result<Self> {
+        serde_json::from_str(txt)
+            .map_err(|e| SeshError::ConfigParse(format!("{}: {}", display_config_path(path), e)))
+    }
+
+    /// Merge `other` on top of this config.
+    ///
+    /// - `name`, `description`, `default_command`, `window_naming`, `cwd`, `shell`, `setup`, `tags` and `on_attach` are taken from `other` if it sets them.
+    /// - `options` and `global_options` are merged key by key, preferring `other`.
+    /// - A window in `other` replaces the window here with the same name.
+    /// - Any other windows in `other` (including unnamed ones) are appended.
+    pub fn merge(mut self, other: Config) -> Self {
+        if !other.name.is_empty() {
+            self.name = other.name;
+        }
+        if other.description.is_some() {
+            self.description = other.description;
+        }
+        if other.default_command.is_some() {
+            self.default_command = other.default_command;
+        }
+        if other.window_naming.is_some() {
+            self.window_naming = other.window_naming;
+        }
+        if other.cwd.is_some() {
+            self.cwd = other.cwd;
+        }
+        if other.shell.is_some() {
+            self.shell = other.shell;
+        }
+        if other.setup.is_some() {
+            self.setup = other.setup;
+        }
+        if other.tags.is_some() {
+            self.tags = other.tags;
+        }
+        if other.on_attach.is_some() {
+            self.on_attach = other.on_attach;
+        }
+        if let Some(options) = other.options {
+            self.options.get_or_insert_default().extend(options);
+        }
+        if let Some(options) = other.global_options {
+            self.global_options.get_or_insert_default().extend(options);
+        }
+
+        for window in other.window {
+            let existing = window.name.as_ref().and_then(|name| {
+                self.window
+                    .iter()
+                    .position(|w| w.name.as_ref() == Some(name))
+            });
+            match existing {
+                Some(idx) => self.window[idx] = window,
+                None => self.window.push(window),
+            }
+        }
+
+        self
+    }
+
     /// Write a config file to disk
-    pub fn write(&self, path: &PathBuf) -> Result<()> {
-        let txt = toml::to_string(&self)?;
-        fs::write(&path, &txt)?;
+    pub fn write(&self, path: &PathBuf, format: Option<ConfigFormat>) -> Result<()> {
+        let txt = match config_format(path, format) {
+            ConfigFormat::Toml => toml::to_string(&self)?,
+            ConfigFormat::Json => serde_json::to_string_pretty(&self)? + "\n",
+        };
+        fs::write(path, &txt)?;
         Ok(())
     }
 }
 
+/// The format of the config file at `path`: `format` if it's given
+/// (`--config-format`), otherwise JSON for a `.json` file and TOML for
+/// anything else, including no extension.
+pub fn config_format(path: &Path, format: Option<ConfigFormat>) -> ConfigFormat {
+    format.unwrap_or_else(|| match path.extension().and_then(|e| e.to_str()) {
+        Some("json") => ConfigFormat::Json,
+        _ => ConfigFormat::Toml,
+    })
+}
+
+/// Marker file that stops [`find_config`] from looking any higher.
+pub const ROOT_MARKER: &str = ".sesh-root";
+
+/// Find a config file by checking `start` and then each of its parents,
+/// so sesh can be run from anywhere inside a project.
+///
+/// The search stops at the first directory containing a [`ROOT_MARKER`]
+/// file: that directory's config is still used, but its parents' aren't.
+/// Returns `None` if no directory has the file.
+pub fn find_config(start: &Path, path: &Path) -> Option<PathBuf> {
+    for dir in start.ancestors() {
+        let candidate = dir.join(path);
+        if candidate.exists() {
+            return Some(candidate);
+        }
+        if dir.join(ROOT_MARKER).exists() {
+            break;
+        }
+    }
+    None
+}
+
+/// The global config's file name, in [`global_config_dir`].
+pub const GLOBAL_CONFIG: &str = "default.toml";
+
+/// The directory holding the global config: `dir` if given,
+/// otherwise `sesh` in the user's config directory
+/// (`$XDG_CONFIG_HOME`, or `~/.config`).
+pub fn global_config_dir(dir: Option<&Path>) -> Option<PathBuf> {
+    match dir {
+        Some(dir) => Some(dir.to_path_buf()),
+        None => dirs::config_dir().map(|dir| dir.join("sesh")),
+    }
+}
+
+/// Find the global config, the fallback when there's no
+/// project config. Returns `None` if it doesn't exist.
+pub fn find_global_config(dir: Option<&Path>) -> Option<PathBuf> {
+    global_config_dir(dir)
+        .map(|dir| dir.join(GLOBAL_CONFIG))
+        .filter(|path| path.exists())
+}
+
+/// Work out which config file to use: `config` in `start` or one of its
+/// parents (see [`find_config`]), then the global config unless
+/// `config` was passed `explicit`ly, and otherwise `config` as given.
+///
+/// Also returns whether the global config was picked.
+pub fn resolve_config(
+    start: Option<&Path>,
+    config: &Path,
+    explicit: bool,
+    config_dir: Option<&Path>,
+) -> (PathBuf, bool) {
+    if let Some(path) = start.and_then(|start| find_config(start, config)) {
+        return (path, false);
+    }
+    match find_global_config(config_dir).filter(|_| !explicit) {
+        Some(path) => (path, true),
+        None => (config.to_path_buf(), false),
+    }
+}
+
+/// Where `--backup` copies a config before it's rewritten,
+/// e.g. `.seshconf.toml.bak`.
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".bak");
+    PathBuf::from(name)
+}
+
+/// Show a config path in messages as a canonical absolute path, so it's
+/// clear which file was used when it was found in a parent directory.
+/// Falls back to the absolute path as given if it doesn't exist yet.
+pub fn display_config_path(path: &Path) -> String {
+    fs::canonicalize(path)
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+        .display()
+        .to_string()
+}
+
+/// Expand a leading `~` or `~user` to that user's home directory,
+/// for paths the shell didn't expand (e.g. quoted ones). Anything
+/// else, or a user whose home can't be found, is left as-is.
+pub fn expand_tilde(path: &Path) -> PathBuf {
+    let mut components = path.components();
+    let Some(Component::Normal(first)) = components.next() else {
+        return path.to_path_buf();
+    };
+    let Some(user) = first.to_str().and_then(|first| first.strip_prefix('~')) else {
+        return path.to_path_buf();
+    };
+    let home = if user.is_empty() {
+        dirs::home_dir()
+    } else {
+        user_home(user)
+    };
+
+    match home {
+        Some(home) if components.as_path().as_os_str().is_empty() => home,
+        Some(home) => home.join(components.as_path()),
+        None => path.to_path_buf(),
+    }
+}
+
+/// Look up another user's home directory with `getpwnam`, so users from
+/// any source the system is set up for (e.g. LDAP) are found too.
+fn user_home(user: &str) -> Option<PathBuf> {
+    use std::ffi::{CStr, CString, OsStr};
+    use std::os::unix::ffi::OsStrExt;
+
+    let name = CString::new(user).ok()?;
+    // SAFETY: `name` is a valid C string, and the entry `getpwnam` returns
+    // is only read here, before anything else could overwrite it
+    unsafe {
+        let entry = libc::getpwnam(name.as_ptr());
+        if entry.is_null() || (*entry).pw_dir.is_null() {
+            return None;
+        }
+        let dir = CStr::from_ptr((*entry).pw_dir);
+        Some(PathBuf::from(OsStr::from_bytes(dir.to_bytes())))
+    }
+}
+
+/// Build a parse error naming the file and, if there's a
+/// span, the line and column it starts at (both from 1).
+fn parse_error(path: &Path, txt: &str, span: Option<Range<usize>>, message: &str) -> SeshError {
+    let Some(span) = span else {
+        return SeshError::ConfigParse(format!("{}: {}", display_config_path(path), message));
+    };
+
+    let before = &txt[..span.start.min(txt.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+    SeshError::ConfigParse(format!(
+        "{} at line {}, column {}: {}",
+        display_config_path(path),
+        line,
+        column,
+        message
+    ))
+}
+
+/// A config file opened for editing in place.
+///
+/// Unlike [`Config::write`], which re-serializes the whole file,
+/// edits made through this keep a TOML file's comments and formatting.
+/// A JSON file is edited as TOML and re-serialized when it's written.
+pub struct ConfigDocument {
+    doc: DocumentMut,
+    /// Where the document was loaded from, for errors
+    path: PathBuf,
+    /// The format to write the document back in
+    format: ConfigFormat,
+}
+
+impl ConfigDocument {
+    /// Load a config file from path for editing, in `format` if
+    /// given or else the one its extension implies.
+    pub fn load(path: &PathBuf, format: Option<ConfigFormat>) -> Result<Self> {
+        if !path.exists() {
+            return Err(SeshError::ConfigNotFound(path.clone()));
+        }
+        let format = config_format(path, format);
+        let txt = match format {
+            ConfigFormat::Toml => read_to_string(path)?,
+            ConfigFormat::Json => toml::to_string(&Config::parse(path, Some(format))?)?,
+        };
+        let doc = txt
+            .parse::<DocumentMut>()
+            .map_err(|e| parse_error(path, &txt, e.span(), e.message()))?;
+        Ok(Self {
+            doc,
+            path: path.clone(),
+            format,
+        })
+    }
+
+    /// The config as it stands in the document, validated like
+    /// [`Config::load`], so it doesn't have to be read twice.
+    pub fn config(&self) -> Result<Config> {
+        let conf = Config::parse_str(&self.path, &self.doc.to_string())?;
+        conf.validate()?;
+        Ok(conf)
+    }
+
+    /// Append a window to the end of the `[[window]]` list.
+    pub fn push_window(&mut self, window: &WindowConf) -> Result<()> {
+        let table = toml_edit::ser::to_document(window)?.as_table().clone();
+        self.windows_mut()?.push(table);
+        Ok(())
+    }
+
+    /// Keep only the windows whose name passes `keep`,
+    /// returning how many were removed.
+    pub fn retain_windows<F>(&mut self, mut keep: F) -> Result<usize>
+    where
+        F: FnMut(Option<&str>) -> bool,
+    {
+        let windows = self.windows_mut()?;
+        let initial_len = windows.len();
+        windows.retain(|w| keep(w.get("name").and_then(|n| n.as_str())));
+        Ok(initial_len - windows.len())
+    }
+
+    /// Move the window called `name` to index `to` of the `[[window]]`
+    /// list, returning the index it was at.
+    pub fn move_window(&mut self, name: &str, to: usize) -> Result<usize> {
+        let windows = self.windows_mut()?;
+        let from = windows
+            .iter()
+            .position(|w| w.get("name").and_then(|n| n.as_str()) == Some(name))
+            .ok_or_else(|| SeshError::WindowNotInConfig(name.to_string()))?;
+        if to >= windows.len() {
+            return Err(SeshError::Usage(format!(
+                "Index {} is out of range (the config has {} window(s))",
+                to,
+                windows.len()
+            )));
+        }
+
+        // Tables are written in document order, so swap the positions
+        // around along with the tables themselves
+        let mut positions: Vec<_> = windows.iter().map(|w| w.position()).collect();
+        let table = windows.remove(from);
+        windows.insert(to, table);
+        positions.sort();
+        for (window, position) in windows.iter_mut().zip(positions) {
+            if let Some(position) = position {
+                window.set_position(Some(position));
+            }
+        }
+        Ok(from)
+    }
+
+    /// Set the `disabled` flag of the window called `name`. Enabling a
+    /// window removes the flag, since that's the default.
+    pub fn set_window_disabled(&mut self, name: &str, disabled: bool) -> Result<()> {
+        let window = self
+            .windows_mut()?
+            .iter_mut()
+            .find(|w| w.get("name").and_then(|n| n.as_str()) == Some(name))
+            .ok_or_else(|| SeshError::WindowNotInConfig(name.to_string()))?;
+        if disabled {
+            window.insert("disabled", toml_edit::value(true));
+        } else {
+            window.remove("disabled");
+        }
+        Ok(())
+    }
+
+    /// Write the edited config file to disk
+    pub fn write(&self, path: &PathBuf) -> Result<()> {
+        match self.format {
+            ConfigFormat::Json => {
+                let conf = Config::parse_str(&self.path, &self.doc.to_string())?;
+                conf.write(path, Some(ConfigFormat::Json))
+            }
+            ConfigFormat::Toml => {
+                fs::write(path, self.doc.to_string())?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Get the `[[window]]` list, converting an inline
+    /// `window = [...]` array if needed.
+    fn windows_mut(&mut self) -> Result<&mut ArrayOfTables> {
+        let item = self
+            .doc
+            .entry("window")
+            .or_insert(Item::ArrayOfTables(ArrayOfTables::new()));
+
+        if let Some(array) = item.as_array() {
+            let mut tables = ArrayOfTables::new();
+            for value in array.iter() {
+                let table = value.as_inline_table().ok_or_else(|| {
+                    SeshError::ConfigParse("expected `window` to be a list of tables".to_string())
+                })?;
+                tables.push(table.clone().into_table());
+            }
+            *item = Item::ArrayOfTables(tables);
+        }
+
+        item.as_array_of_tables_mut().ok_or_else(|| {
+            SeshError::ConfigParse("expected `window` to be a list of tables".to_string())
+        })
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
-    use anyhow::Result;
 
     #[test]
     fn test_deserialize_1() -> Result<()> {
@@ -67,12 +870,12 @@ command = ["npm", "run", "dev", "--port", "3000"]
                 WindowConf {
                     name: Some("editor".to_string()),
                     command: Some(vec!["vim".to_string(), ".".to_string()]),
-                    default: None,
+                    ..Default::default()
                 },
                 WindowConf {
                     name: Some("claude".to_string()),
                     command: Some(vec!["claude".to_string()]),
-                    default: None,
+                    ..Default::default()
                 },
                 WindowConf {
                     name: Some("server".to_string()),
@@ -83,13 +886,715 @@ command = ["npm", "run", "dev", "--port", "3000"]
                         "--port".to_string(),
                         "3000".to_string(),
                     ]),
-                    default: None,
+                    ..Default::default()
                 },
             ],
+            ..Default::default()
         };
 
         let parsed: Config = toml::from_str(txt)?;
         assert_eq!(parsed, expect);
         Ok(())
     }
+
+    #[test]
+    fn test_deserialize_panes() -> Result<()> {
+        let txt = r#"
+name = "panes"
+
+[[window]]
+name = "dev"
+command = ["vim"]
+layout = "main-vertical"
+
+[[window.pane]]
+command = ["cargo", "watch"]
+
+[[window.pane]]
+"#;
+        let parsed: Config = toml::from_str(txt)?;
+        parsed.validate()?;
+
+        let window = &parsed.window[0];
+        assert_eq!(window.layout.as_deref(), Some("main-vertical"));
+        assert_eq!(
+            window.pane,
+            Some(vec![
+                PaneConf {
+                    command: Some(vec!["cargo".to_string(), "watch".to_string()]),
+                    ..Default::default()
+                },
+                PaneConf::default(),
+            ])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_unknown_layout() -> Result<()> {
+        let txt = r#"
+name = "bad-layout"
+
+[[window]]
+layout = "diagonal"
+"#;
+        let parsed: Config = toml::from_str(txt)?;
+        let err = parsed.validate().unwrap_err();
+        assert!(err.to_string().contains("unknown layout 'diagonal'"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_unknown_window_naming() -> Result<()> {
+        let parsed: Config = toml::from_str("name = \"bad\"\nwindow_naming = \"random\"")?;
+        let err = parsed.validate().unwrap_err();
+        assert!(err.to_string().contains("unknown window_naming 'random'"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_default_window_name() {
+        let command: Vec<String> = ["/usr/bin/npm", "run", "dev:watch"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let name = |naming: Option<&str>, command: Option<&[String]>| {
+            let config = Config {
+                window_naming: naming.map(str::to_string),
+                ..Default::default()
+            };
+            config.default_window_name(command, 2)
+        };
+
+        assert_eq!(name(None, Some(&command)), Some("npm".to_string()));
+        assert_eq!(
+            name(Some("command"), Some(&command)),
+            Some("npm".to_string())
+        );
+        assert_eq!(
+            name(Some("full-command"), Some(&command)),
+            Some("usr-bin-npm-run-dev-watch".to_string())
+        );
+        assert_eq!(
+            name(Some("index"), Some(&command)),
+            Some("window-2".to_string())
+        );
+
+        // Only `index` can name a window without a command
+        assert_eq!(name(None, None), None);
+        assert_eq!(name(Some("full-command"), None), None);
+        assert_eq!(name(Some("index"), None), Some("window-2".to_string()));
+    }
+
+    #[test]
+    fn test_validate_empty_command() -> Result<()> {
+        let txt = r#"
+name = "empty"
+
+[[window]]
+command = []
+"#;
+        let parsed: Config = toml::from_str(txt)?;
+        let err = parsed.validate().unwrap_err();
+        assert!(err.to_string().contains("`command` can't be empty"));
+
+        // The same goes for panes and the default command
+        let pane: Config =
+            toml::from_str("name = \"x\"\n[[window]]\n[[window.pane]]\ncommand = []")?;
+        assert!(pane.validate().is_err());
+        let default: Config = toml::from_str("name = \"x\"\ndefault_command = []")?;
+        assert!(default.validate().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_command_string_or_array() -> Result<()> {
+        let line: Config = toml::from_str("name = \"x\"\n[[window]]\ncommand = \"npm run dev\"")?;
+        let args: Config =
+            toml::from_str("name = \"x\"\n[[window]]\ncommand = [\"npm\", \"run\", \"dev\"]")?;
+
+        // A command line is kept whole, and typed the same way
+        let (line, args) = (&line.window[0], &args.window[0]);
+        assert_eq!(line.command, Some(vec!["npm run dev".to_string()]));
+        assert_eq!(
+            line.command.as_ref().map(|c| c.join(" ")),
+            args.command.as_ref().map(|c| c.join(" "))
+        );
+        let config = Config::default();
+        assert_eq!(config.window_name(line, 0), Some("npm".to_string()));
+        assert_eq!(config.window_name(line, 0), config.window_name(args, 0));
+
+        // An empty line is as invalid as an empty array
+        let empty: Config = toml::from_str("name = \"x\"\n[[window]]\ncommand = \"\"")?;
+        assert!(empty.validate().is_err());
+
+        // Panes and the default command take either form too
+        let txt = r#"
+name = "x"
+default_command = "zsh -l"
+
+[[window]]
+
+[[window.pane]]
+command = "tail -f log"
+
+[[window.pane]]
+command = ["htop"]
+"#;
+        let config: Config = toml::from_str(txt)?;
+        config.validate()?;
+        assert_eq!(config.default_command, Some(vec!["zsh -l".to_string()]));
+        let panes = config.window[0].pane.as_ref().unwrap();
+        assert_eq!(panes[0].command, Some(vec!["tail -f log".to_string()]));
+        assert_eq!(panes[1].command, Some(vec!["htop".to_string()]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_command_and_commands() -> Result<()> {
+        let txt = r#"
+name = "both"
+
+[[window]]
+command = ["make"]
+commands = [["make", "test"]]
+"#;
+        let parsed: Config = toml::from_str(txt)?;
+        let err = parsed.validate().unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("`command` or `commands`, not both")
+        );
+
+        let empty: Config = toml::from_str("name = \"x\"\n[[window]]\ncommands = [[]]")?;
+        assert!(empty.validate().is_err());
+        let none: Config = toml::from_str("name = \"x\"\n[[window]]\ncommands = []")?;
+        assert!(none.validate().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_focus_out_of_range() -> Result<()> {
+        let txt = r#"
+name = "bad-focus"
+
+[[window]]
+focus = 1
+
+[[window]]
+focus = 1
+
+[[window.pane]]
+"#;
+        let parsed: Config = toml::from_str(txt)?;
+        let err = parsed.validate().unwrap_err();
+        assert!(err.to_string().contains("focus pane 1 is out of range"));
+
+        // Only the first window is out of range
+        assert!(parsed.window[1].validate().is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_error_location() -> Result<()> {
+        let dir = tempfile::TempDir::new()?;
+        let path = dir.path().join(".seshconf.toml");
+        fs::write(&path, "name = \"broken\"\n\n[[window]]\nname = \n")?;
+
+        // The file is named the same way as in every other message
+        let err = Config::load(&path).unwrap_err().to_string();
+        assert!(err.contains(&display_config_path(&path)));
+        assert!(err.contains("at line 4, column 8"));
+
+        let err = ConfigDocument::load(&path, None).err().unwrap().to_string();
+        assert!(err.contains(&display_config_path(&path)));
+        assert!(err.contains("at line 4"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_window_count() -> Result<()> {
+        let parsed: Config = toml::from_str("name = \"zero\"\n[[window]]\ncount = 0\n")?;
+        let err = parsed.validate().unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("window `count` must be at least 1")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_document_edits_preserve_comments() -> Result<()> {
+        let txt = r#"# My project's session
+name = "commented"
+
+# The main editor
+[[window]]
+name = "editor"
+command = ["vim", "."] # open the repo
+"#;
+        let dir = tempfile::TempDir::new()?;
+        let path = dir.path().join(".seshconf.toml");
+        fs::write(&path, txt)?;
+
+        let mut doc = ConfigDocument::load(&path, None)?;
+        doc.push_window(&WindowConf {
+            name: Some("server".to_string()),
+            command: Some(vec!["npm".to_string(), "start".to_string()]),
+            ..Default::default()
+        })?;
+        doc.write(&path)?;
+        assert_eq!(Config::load(&path)?.window.len(), 2);
+
+        let mut doc = ConfigDocument::load(&path, None)?;
+        let removed = doc.retain_windows(|name| name != Some("server"))?;
+        doc.write(&path)?;
+        assert_eq!(removed, 1);
+
+        assert_eq!(read_to_string(&path)?, txt);
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_skips_absent_fields() -> Result<()> {
+        let dir = tempfile::TempDir::new()?;
+        let path = dir.path().join(".seshconf.toml");
+        let config = Config {
+            name: "minimal".to_string(),
+            description: Some("Just a server".to_string()),
+            window: vec![WindowConf {
+                name: Some("server".to_string()),
+                command: Some(vec!["npm".to_string(), "start".to_string()]),
+                keys: Some(vec!["C-c".to_string()]),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        config.write(&path, None)?;
+
+        assert_eq!(
+            read_to_string(&path)?,
+            r#"name = "minimal"
+description = "Just a server"
+
+[[window]]
+name = "server"
+command = ["npm", "start"]
+keys = ["C-c"]
+"#
+        );
+        assert_eq!(Config::load(&path)?, config);
+        Ok(())
+    }
+
+    #[test]
+    fn test_document_move_window() -> Result<()> {
+        let txt = r#"name = "moving"
+
+[[window]]
+name = "editor"
+
+# Logs go last
+[[window]]
+name = "logs"
+
+[[window]]
+name = "server"
+"#;
+        let dir = tempfile::TempDir::new()?;
+        let path = dir.path().join(".seshconf.toml");
+        fs::write(&path, txt)?;
+
+        let mut doc = ConfigDocument::load(&path, None)?;
+        assert_eq!(doc.move_window("logs", 2)?, 1);
+        doc.write(&path)?;
+
+        let names: Vec<_> = Config::load(&path)?
+            .window
+            .into_iter()
+            .filter_map(|w| w.name)
+            .collect();
+        assert_eq!(names, vec!["editor", "server", "logs"]);
+        assert!(read_to_string(&path)?.contains("# Logs go last\n[[window]]\nname = \"logs\""));
+
+        assert!(matches!(
+            doc.move_window("nope", 0),
+            Err(SeshError::WindowNotInConfig(_))
+        ));
+        assert!(matches!(
+            doc.move_window("logs", 3),
+            Err(SeshError::Usage(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_document_push_to_inline_array() -> Result<()> {
+        let dir = tempfile::TempDir::new()?;
+        let path = dir.path().join(".seshconf.toml");
+        fs::write(&path, "name = \"inline\"\nwindow = []\n")?;
+
+        let mut doc = ConfigDocument::load(&path, None)?;
+        doc.push_window(&WindowConf {
+            name: Some("shell".to_string()),
+            ..Default::default()
+        })?;
+        doc.write(&path)?;
+
+        let config = Config::load(&path)?;
+        assert_eq!(config.window.len(), 1);
+        assert_eq!(config.window[0].name, Some("shell".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_config_in_parent() -> Result<()> {
+        let dir = tempfile::TempDir::new()?;
+        let path = dir.path().join(".seshconf.toml");
+        fs::write(&path, "name = \"parent\"\nwindow = []\n")?;
+
+        let nested = dir.path().join("src").join("deep");
+        fs::create_dir_all(&nested)?;
+
+        let name = Path::new(".seshconf.toml");
+        assert_eq!(find_config(&nested, name), Some(path.clone()));
+        assert_eq!(find_config(dir.path(), name), Some(path));
+        assert_eq!(find_config(&nested, Path::new("missing.toml")), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_config_stops_at_root_marker() -> Result<()> {
+        let dir = tempfile::TempDir::new()?;
+        let name = Path::new(".seshconf.toml");
+        fs::write(dir.path().join(name), "name = \"outer\"\n")?;
+
+        // project/.sesh-root
+        // project/sub/deep
+        let project = dir.path().join("project");
+        let deep = project.join("sub").join("deep");
+        fs::create_dir_all(&deep)?;
+        fs::write(project.join(ROOT_MARKER), "")?;
+
+        assert_eq!(find_config(&deep, name), None);
+        assert_eq!(find_config(&project, name), None);
+
+        // The marked directory's own config is still found
+        let inner = project.join(name);
+        fs::write(&inner, "name = \"inner\"\n")?;
+        assert_eq!(find_config(&deep, name), Some(inner));
+
+        // Above the marker, the outer config is used as before
+        assert_eq!(find_config(dir.path(), name), Some(dir.path().join(name)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_config() -> Result<()> {
+        let dir = tempfile::TempDir::new()?;
+        let name = Path::new(".seshconf.toml");
+        let project = dir.path().join("project");
+        let sub = project.join("sub");
+        let global_dir = dir.path().join("global");
+        fs::create_dir_all(&sub)?;
+        fs::create_dir_all(&global_dir)?;
+
+        // Nothing anywhere: the path as given
+        let resolve = |explicit| resolve_config(Some(&sub), name, explicit, Some(&global_dir));
+        assert_eq!(resolve(false), (name.to_path_buf(), false));
+
+        // The global config, unless --config was passed
+        let global = global_dir.join(GLOBAL_CONFIG);
+        fs::write(&global, "name = \"global\"\n")?;
+        assert_eq!(resolve(false), (global, true));
+        assert_eq!(resolve(true), (name.to_path_buf(), false));
+
+        // A project config in a parent directory wins
+        let local = project.join(name);
+        fs::write(&local, "name = \"local\"\n")?;
+        assert_eq!(resolve(false), (local.clone(), false));
+        assert_eq!(resolve(true), (local, false));
+
+        // Without a current directory, only the global config is looked for
+        assert!(resolve_config(None, name, false, Some(&global_dir)).1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_global_config() -> Result<()> {
+        let dir = tempfile::TempDir::new()?;
+        assert_eq!(find_global_config(Some(dir.path())), None);
+
+        let path = dir.path().join(GLOBAL_CONFIG);
+        fs::write(&path, "name = \"global\"\n")?;
+        assert_eq!(find_global_config(Some(dir.path())), Some(path));
+        Ok(())
+    }
+
+    #[test]
+    fn test_display_config_path() -> Result<()> {
+        let dir = tempfile::TempDir::new()?;
+        let path = dir.path().join(".seshconf.toml");
+        fs::write(&path, "name = \"shown\"\n")?;
+
+        let canonical = fs::canonicalize(dir.path())?.join(".seshconf.toml");
+        let nested = dir.path().join(".").join(".seshconf.toml");
+        assert_eq!(
+            display_config_path(&nested),
+            canonical.display().to_string()
+        );
+        assert_eq!(display_config_path(&path), canonical.display().to_string());
+
+        let missing = Path::new("missing.toml");
+        assert_eq!(
+            display_config_path(missing),
+            std::env::current_dir()?
+                .join("missing.toml")
+                .display()
+                .to_string()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_expand_tilde() {
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(
+            expand_tilde(Path::new("~/.config/sesh/web.toml")),
+            home.join(".config/sesh/web.toml")
+        );
+        assert_eq!(expand_tilde(Path::new("~")), home);
+
+        // root is always in the user database
+        let root = expand_tilde(Path::new("~root/web.toml"));
+        assert!(root.is_absolute());
+        assert!(root.ends_with("web.toml"));
+
+        // Only a leading tilde is expanded
+        for path in [
+            "web.toml",
+            "/etc/sesh.toml",
+            "dir/~/web.toml",
+            "~no-such-user-sesh/x",
+        ] {
+            assert_eq!(expand_tilde(Path::new(path)), PathBuf::from(path));
+        }
+    }
+
+    fn window(name: Option<&str>, command: &str) -> WindowConf {
+        WindowConf {
+            name: name.map(|n| n.to_string()),
+            command: Some(vec![command.to_string()]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_merge_windows() {
+        let base = Config {
+            name: "team".to_string(),
+            window: vec![
+                window(Some("editor"), "vim"),
+                window(Some("server"), "make"),
+            ],
+            ..Default::default()
+        };
+        let overlay = Config {
+            window: vec![
+                window(Some("editor"), "nvim"),
+                window(Some("notes"), "less"),
+                window(None, "htop"),
+            ],
+            ..Default::default()
+        };
+
+        let merged = base.merge(overlay);
+        assert_eq!(merged.name, "team");
+        assert_eq!(
+            merged.window,
+            vec![
+                window(Some("editor"), "nvim"),
+                window(Some("server"), "make"),
+                window(Some("notes"), "less"),
+                window(None, "htop"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_top_level_fields() {
+        let base = Config {
+            name: "team".to_string(),
+            default_command: Some(vec!["bash".to_string()]),
+            cwd: Some(PathBuf::from("app")),
+            ..Default::default()
+        };
+
+        // Unset fields in the overlay keep the base's values
+        let merged = base.merge(Config {
+            cwd: Some(PathBuf::from("mine")),
+            ..Default::default()
+        });
+        assert_eq!(merged.name, "team");
+        assert_eq!(merged.default_command, Some(vec!["bash".to_string()]));
+        assert_eq!(merged.cwd, Some(PathBuf::from("mine")));
+
+        let merged = merged.merge(Config {
+            name: "personal".to_string(),
+            ..Default::default()
+        });
+        assert_eq!(merged.name, "personal");
+    }
+
+    #[test]
+    fn test_apply_defaults() {
+        let mut config = Config {
+            name: "team".to_string(),
+            default_command: Some(vec!["bash".to_string()]),
+            cwd: Some(PathBuf::from("app")),
+            window: vec![
+                window(Some("editor"), "vim"),
+                WindowConf {
+                    name: Some("logs".to_string()),
+                    cwd: Some(PathBuf::from("logs")),
+                    pane: Some(vec![PaneConf::default()]),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        config.apply_defaults();
+        assert_eq!(config.window[0].cwd, Some(PathBuf::from("app")));
+        assert_eq!(config.window[0].focus, None);
+        assert_eq!(config.window[0].clear_line, Some(false));
+
+        let logs = &config.window[1];
+        assert_eq!(logs.command, Some(vec!["bash".to_string()]));
+        assert_eq!(logs.cwd, Some(PathBuf::from("logs")));
+        assert_eq!(logs.focus, Some(0));
+        assert_eq!(logs.default, Some(false));
+        assert_eq!(
+            logs.pane.as_ref().unwrap()[0].cwd,
+            Some(PathBuf::from("logs"))
+        );
+    }
+
+    #[test]
+    fn test_load_with_overlay() -> Result<()> {
+        let dir = tempfile::TempDir::new()?;
+        let base = dir.path().join("team.seshconf.toml");
+        let overlay = dir.path().join("local.seshconf.toml");
+        fs::write(&base, "name = \"team\"\n\n[[window]]\nname = \"editor\"\n")?;
+        fs::write(&overlay, "[[window]]\nname = \"scratch\"\n")?;
+
+        let config = Config::load_with_overlay(&base, Some(&overlay), None)?;
+        assert_eq!(config.name, "team");
+        assert_eq!(config.window.len(), 2);
+
+        // The base still needs a name of its own
+        assert!(Config::load(&overlay).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_format() -> Result<()> {
+        let dir = tempfile::TempDir::new()?;
+        let path = dir.path().join(".seshconf");
+        fs::write(
+            &path,
+            r#"{"name": "forced", "window": [{"name": "editor"}]}"#,
+        )?;
+
+        // Without an extension it's read as TOML, unless told otherwise
+        assert_eq!(config_format(&path, None), ConfigFormat::Toml);
+        assert!(Config::load(&path).is_err());
+        let config = Config::load_with_overlay(&path, None, Some(ConfigFormat::Json))?;
+        assert_eq!(config.name, "forced");
+        assert_eq!(config.window.len(), 1);
+
+        // Otherwise the extension decides
+        assert_eq!(config_format(Path::new("a.json"), None), ConfigFormat::Json);
+        assert_eq!(
+            config_format(Path::new("a.json"), Some(ConfigFormat::Toml)),
+            ConfigFormat::Toml
+        );
+
+        // Written back, and edited, in the same format
+        config.write(&path, Some(ConfigFormat::Json))?;
+        let mut doc = ConfigDocument::load(&path, Some(ConfigFormat::Json))?;
+        doc.push_window(&WindowConf {
+            name: Some("shell".to_string()),
+            ..Default::default()
+        })?;
+        doc.write(&path)?;
+        let written: Config = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        assert_eq!(written.window.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_schema() -> Result<()> {
+        let schema = serde_json::to_value(Config::json_schema())?;
+
+        // Every field that can be written to the config is described
+        let fields = |value: serde_json::Value| -> Vec<String> {
+            value.as_object().unwrap().keys().cloned().collect()
+        };
+        let properties =
+            |value: &serde_json::Value| -> Vec<String> { fields(value["properties"].clone()) };
+        // Absent fields aren't serialized, so fill every one of them in
+        let command = Some(vec!["true".to_string()]);
+        let pane = PaneConf {
+            command: command.clone(),
+            cwd: Some(PathBuf::from(".")),
+        };
+        let window = WindowConf {
+            name: Some("full".to_string()),
+            command: command.clone(),
+            commands: Some(vec![]),
+            keys: Some(vec![]),
+            default: Some(true),
+            layout: Some("tiled".to_string()),
+            pane: Some(vec![pane.clone()]),
+            focus: Some(0),
+            synchronize: Some(false),
+            allow_rename: Some(false),
+            clear_line: Some(false),
+            cwd: Some(PathBuf::from(".")),
+            log_file: Some(PathBuf::from("window.log")),
+            disabled: Some(false),
+            count: Some(1),
+        };
+        let config = Config {
+            name: "full".to_string(),
+            description: Some("Everything set".to_string()),
+            default_command: command.clone(),
+            window_naming: Some("command".to_string()),
+            shell: Some("sh".to_string()),
+            cwd: Some(PathBuf::from(".")),
+            setup: command.clone(),
+            on_attach: command,
+            tags: Some(vec![]),
+            options: Some(BTreeMap::new()),
+            global_options: Some(BTreeMap::new()),
+            window: vec![window.clone()],
+        };
+        assert_eq!(properties(&schema), fields(serde_json::to_value(config)?));
+        assert_eq!(
+            properties(&schema["$defs"]["WindowConf"]),
+            fields(serde_json::to_value(window)?)
+        );
+        assert_eq!(
+            properties(&schema["$defs"]["PaneConf"]),
+            fields(serde_json::to_value(pane)?)
+        );
+
+        assert_eq!(
+            schema["$defs"]["WindowConf"]["properties"]["layout"]["enum"],
+            serde_json::json!(LAYOUTS)
+        );
+        Ok(())
+    }
 }
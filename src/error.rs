@@ -0,0 +1,172 @@
+//! App's error types.
+use crate::conf::display_config_path;
+use std::path::PathBuf;
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, SeshError>;
+
+#[derive(Debug, Error)]
+pub enum SeshError {
+    #[error("tmux is not installed or {0:?} is not available in PATH")]
+    TmuxMissing(PathBuf),
+
+    /// A tmux command failed.
+    #[error("{0}")]
+    Tmux(String),
+
+    /// An attached tmux client lost its terminal, rather than detaching
+    /// or failing to attach.
+    #[error("Lost the connection to session '{0}'")]
+    AttachLost(String),
+
+    #[error("Config file {} does not exist", display_config_path(.0))]
+    ConfigNotFound(PathBuf),
+
+    #[error(
+        "Config file {} already exists. To overwrite, pass --overwrite.",
+        display_config_path(.0)
+    )]
+    ConfigExists(PathBuf),
+
+    #[error("Failed to parse config: {0}")]
+    ConfigParse(String),
+
+    #[error(transparent)]
+    ConfigInvalid(#[from] validator::ValidationErrors),
+
+    #[error("Failed to write config: {0}")]
+    ConfigWrite(String),
+
+    #[error("Session '{0}' already exists")]
+    SessionExists(String),
+
+    #[error("Session '{0}' is not running")]
+    SessionNotRunning(String),
+
+    #[error("Window '{0}' not found in config")]
+    WindowNotInConfig(String),
+
+    #[error(
+        "Window '{window}' is already in the config at index {index}. To add it anyway, pass --allow-duplicate."
+    )]
+    DuplicateWindow { window: String, index: usize },
+
+    #[error("Window '{window}' is not running in session '{session}'")]
+    WindowNotRunning { session: String, window: String },
+
+    /// The config isn't tagged with the `--tag` a command was limited to.
+    #[error("Session '{session}' is not tagged '{tag}'")]
+    NotTagged { session: String, tag: String },
+
+    /// Some of `up`'s windows failed to start, each listed as
+    /// `window: error`. The rest were started anyway.
+    #[error("Failed to start window(s) in session '{session}': {}", .failures.join("; "))]
+    WindowsFailed {
+        session: String,
+        failures: Vec<String>,
+    },
+
+    #[error("Timed out waiting for window '{window}' to print '{text}'")]
+    WaitTimeout { window: String, text: String },
+
+    #[error("{feature} needs tmux {required} or later, but {found} is installed")]
+    TmuxTooOld {
+        feature: &'static str,
+        found: String,
+        required: String,
+    },
+
+    #[error("Shell '{0}' does not exist")]
+    ShellNotFound(String),
+
+    /// The command was called with missing or conflicting arguments.
+    #[error("{0}")]
+    Usage(String),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    #[error("{0}")]
+    Other(String),
+}
+
+impl SeshError {
+    /// Whether this just reports that something isn't running (or, with
+    /// `--tag`, wasn't checked). With `--quiet`, these are shown by the
+    /// exit code alone.
+    pub fn is_not_running(&self) -> bool {
+        matches!(
+            self,
+            Self::SessionNotRunning(_) | Self::WindowNotRunning { .. } | Self::NotTagged { .. }
+        )
+    }
+
+    /// The exit code to use for this error, so scripts can
+    /// tell failures apart without matching on the message.
+    ///
+    /// - 2: the config file is missing, invalid or can't be written
+    /// - 3: tmux isn't installed
+    /// - 4: the session already exists
+    /// - 5: the session or window isn't running
+    /// - 1: anything else
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::ConfigNotFound(_)
+            | Self::ConfigExists(_)
+            | Self::ConfigParse(_)
+            | Self::ConfigInvalid(_)
+            | Self::ConfigWrite(_) => 2,
+            Self::TmuxMissing(_) => 3,
+            Self::SessionExists(_) => 4,
+            Self::SessionNotRunning(_) | Self::WindowNotRunning { .. } => 5,
+            _ => 1,
+        }
+    }
+}
+
+impl From<toml::de::Error> for SeshError {
+    fn from(err: toml::de::Error) -> Self {
+        Self::ConfigParse(err.to_string())
+    }
+}
+
+impl From<toml_edit::TomlError> for SeshError {
+    fn from(err: toml_edit::TomlError) -> Self {
+        Self::ConfigParse(err.to_string())
+    }
+}
+
+impl From<toml::ser::Error> for SeshError {
+    fn from(err: toml::ser::Error) -> Self {
+        Self::ConfigWrite(err.to_string())
+    }
+}
+
+impl From<toml_edit::ser::Error> for SeshError {
+    fn from(err: toml_edit::ser::Error) -> Self {
+        Self::ConfigWrite(err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes() {
+        assert_eq!(SeshError::ConfigNotFound(PathBuf::from("x")).exit_code(), 2);
+        assert_eq!(SeshError::TmuxMissing(PathBuf::from("tmux")).exit_code(), 3);
+        assert_eq!(SeshError::SessionExists("dev".to_string()).exit_code(), 4);
+        assert_eq!(
+            SeshError::SessionNotRunning("dev".to_string()).exit_code(),
+            5
+        );
+        assert_eq!(SeshError::Tmux("oops".to_string()).exit_code(), 1);
+
+        let err: SeshError = toml::from_str::<toml::Table>("name = ").unwrap_err().into();
+        assert_eq!(err.exit_code(), 2);
+    }
+}
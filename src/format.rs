@@ -0,0 +1,128 @@
+//! `--format` template strings, like tmux's own `-F` formats.
+
+/// Tokens available in `status --format`, rendered once per window.
+pub const STATUS_TOKENS: [&str; 6] = [
+    "session",
+    "name",
+    "index",
+    "state",
+    "running",
+    "window_count",
+];
+
+/// A parsed template such as `{name}: {state}`.
+///
+/// Tokens are written in braces, and literal braces are escaped by
+/// doubling them (`{{` and `}}`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Format {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Token(String),
+}
+
+impl Format {
+    /// Parse a template, only allowing the given tokens.
+    pub fn parse(template: &str, tokens: &[&str]) -> Result<Self, String> {
+        let mut parts = vec![];
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut token = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => token.push(c),
+                            None => return Err(format!("unclosed '{{{}'", token)),
+                        }
+                    }
+
+                    if !tokens.contains(&token.as_str()) {
+                        return Err(format!(
+                            "unknown token '{{{}}}' (expected one of: {})",
+                            token,
+                            tokens
+                                .iter()
+                                .map(|t| format!("{{{}}}", t))
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        ));
+                    }
+
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(Part::Token(token));
+                }
+                '}' => return Err("unmatched '}' (use '}}' for a literal brace)".to_string()),
+                c => literal.push(c),
+            }
+        }
+
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Self { parts })
+    }
+
+    /// Render the template, getting each token's value from `value`.
+    pub fn render<F>(&self, value: F) -> String
+    where
+        F: Fn(&str) -> String,
+    {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Literal(text) => text.clone(),
+                Part::Token(token) => value(token),
+            })
+            .collect()
+    }
+}
+
+/// Parse a `status --format` template (for clap).
+pub fn parse_status_format(template: &str) -> Result<Format, String> {
+    Format::parse(template, &STATUS_TOKENS)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_render() -> Result<(), String> {
+        let format = parse_status_format("{{{index}}} {name}: {state}")?;
+        let line = format.render(|token| match token {
+            "index" => "1".to_string(),
+            "name" => "editor".to_string(),
+            _ => "running".to_string(),
+        });
+        assert_eq!(line, "{1} editor: running");
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = parse_status_format("{name} {bogus}").unwrap_err();
+        assert!(err.contains("unknown token '{bogus}'"));
+        assert!(err.contains("{window_count}"));
+
+        assert!(parse_status_format("{name").is_err());
+        assert!(parse_status_format("name}").is_err());
+    }
+}
@@ -2,28 +2,92 @@ mod adjectives;
 mod app;
 mod cli;
 mod conf;
+mod error;
+mod format;
 mod nouns;
+mod snapshot;
+mod templates;
 mod tmux;
 mod words;
 
-use clap::Parser;
-use cli::{Cli, Command, WindowCommands};
+use anyhow::Context;
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches};
+use cli::{Cli, Command, ConfigCommands, WindowCommands};
+use error::SeshError;
 
 fn main() {
-    let c = Cli::parse();
-    if let Err(err) = match c.command {
-        Command::Init(ref args) => app::run_init(&c, args),
-        Command::Status => app::run_status(&c),
-        Command::Up => app::run_up(&c),
-        Command::Down => app::run_down(&c),
-        Command::Attach => app::run_attach(&c),
-        Command::Restart => app::run_restart(&c),
+    let matches = Cli::command().get_matches();
+    let mut c = Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    let explicit_config = matches.value_source("config") == Some(ValueSource::CommandLine);
+    // Silent is quiet, plus no error messages
+    c.quiet |= c.silent;
+    // A quoted `~` isn't expanded by the shell
+    c.config = conf::expand_tilde(&c.config);
+
+    if let Err(err) = run(&mut c, explicit_config) {
+        // Only sesh's own errors have their own exit codes
+        let sesh_err = err.downcast_ref::<SeshError>();
+        let shown = match sesh_err {
+            Some(sesh_err) => c.shows_error(sesh_err),
+            None => !c.silent,
+        };
+        if shown {
+            eprintln!("Error: {:#}", err);
+        }
+        std::process::exit(sesh_err.map_or(1, SeshError::exit_code));
+    }
+}
+
+/// Find the config and run the command.
+fn run(c: &mut Cli, explicit_config: bool) -> anyhow::Result<()> {
+    // Change directory before anything resolves relative paths
+    if let Some(dir) = &c.cwd {
+        std::env::set_current_dir(dir)
+            .with_context(|| format!("Failed to change directory to {}", dir.display()))?;
+    }
+
+    // `init` always creates a local config
+    if !matches!(c.command, Command::Init(_)) {
+        let start = std::env::current_dir().ok();
+        (c.config, c.global_config) = conf::resolve_config(
+            start.as_deref(),
+            &c.config,
+            explicit_config,
+            c.config_dir.as_deref(),
+        );
+    }
+
+    let c = &*c;
+    match c.command {
+        Command::Init(ref args) => app::run_init(c, args),
+        Command::Status(ref args) => app::run_status(c, args),
+        Command::Up(ref args) => app::run_up(c, args),
+        Command::Down(ref args) => app::run_down(c, args),
+        Command::Attach(ref args) => app::run_attach(c, args),
+        Command::Restart(ref args) => app::run_restart(c, args),
+        Command::Nuke(ref args) => app::run_nuke(c, args),
+        Command::Send(ref args) => app::run_send(c, args),
+        Command::Ps => app::run_ps(c),
+        Command::Logs(ref args) => app::run_logs(c, args),
+        Command::Popup(ref args) => app::run_popup(c, args),
+        Command::Save(ref args) => app::run_save(c, args),
+        Command::Restore(ref args) => app::run_restore(c, args),
         Command::Window(ref args) => match args.command {
-            WindowCommands::Add(ref add_args) => app::run_window_add(&c, add_args),
-            WindowCommands::Remove(ref rm_args) => app::run_window_remove(&c, rm_args),
+            WindowCommands::Add(ref add_args) => app::run_window_add(c, add_args),
+            WindowCommands::Remove(ref rm_args) => app::run_window_remove(c, rm_args),
+            WindowCommands::Move(ref mv_args) => app::run_window_move(c, mv_args),
+            WindowCommands::Show(ref show_args) => app::run_window_show(c, show_args),
+            WindowCommands::List(ref list_args) => app::run_window_list(c, list_args),
+            WindowCommands::Enable(ref args) => app::run_window_toggle(c, args, false),
+            WindowCommands::Disable(ref args) => app::run_window_toggle(c, args, true),
+            WindowCommands::Renumber => app::run_window_renumber(c),
         },
-    } {
-        eprintln!("Error: {}", err);
-        std::process::exit(1);
-    }
+        Command::Config(ref args) => match args.command {
+            ConfigCommands::Path => app::run_config_path(c),
+            ConfigCommands::Dump(ref dump_args) => app::run_config_dump(c, dump_args),
+        },
+        Command::Schema => app::run_schema(),
+    }?;
+    Ok(())
 }
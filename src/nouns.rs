@@ -1,4 +1,4 @@
-pub static NOUNS: [&'static str; 300] = [
+pub static NOUNS: [&str; 300] = [
     "people",
     "history",
     "way",
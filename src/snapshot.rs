@@ -0,0 +1,113 @@
+//! Snapshots of a running session, written by `save` and replayed by `restore`.
+//!
+//! A snapshot records what was actually running rather than what the
+//! config asked for, so a session can be recreated without its config.
+use crate::conf::display_config_path;
+use crate::error::{Result, SeshError};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Version of the snapshot format, bumped on incompatible changes.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Where `save` writes and `restore` reads by default, next to the config.
+pub const DEFAULT_SNAPSHOT_FILE: &str = ".sesh-state.json";
+
+/// A saved session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Format version (see [`SNAPSHOT_VERSION`])
+    pub version: u32,
+    /// Name of the tmux session
+    pub session: String,
+    /// The session's windows, in index order
+    pub windows: Vec<WindowSnapshot>,
+}
+
+/// A window in a saved session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowSnapshot {
+    /// The window's index when it was saved
+    pub index: usize,
+    pub name: String,
+    /// The window's panes, in order
+    pub panes: Vec<PaneSnapshot>,
+}
+
+/// A pane in a saved window.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaneSnapshot {
+    /// The program running in the pane, or `None` if it was at a shell
+    /// prompt. tmux only reports the program's name, not its arguments,
+    /// so `restore` only re-runs it when asked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// The pane's working directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<PathBuf>,
+}
+
+impl Snapshot {
+    /// Read a snapshot from `path`, rejecting other format versions.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Err(SeshError::Other(format!(
+                "Snapshot file {} does not exist. To create one, run `sesh save`.",
+                display_config_path(path)
+            )));
+        }
+        let snapshot: Self = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err(SeshError::Other(format!(
+                "Snapshot file {} has version {}, but only version {} is supported",
+                display_config_path(path),
+                snapshot.version,
+                SNAPSHOT_VERSION
+            )));
+        }
+        Ok(snapshot)
+    }
+
+    /// Write the snapshot to `path` as pretty-printed JSON.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)? + "\n")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_round_trip() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let path = temp_dir.path().join(DEFAULT_SNAPSHOT_FILE);
+        let snapshot = Snapshot {
+            version: SNAPSHOT_VERSION,
+            session: "dev".to_string(),
+            windows: vec![WindowSnapshot {
+                index: 0,
+                name: "editor".to_string(),
+                panes: vec![
+                    PaneSnapshot {
+                        command: Some("vim".to_string()),
+                        cwd: Some(PathBuf::from("/src")),
+                    },
+                    PaneSnapshot {
+                        command: None,
+                        cwd: None,
+                    },
+                ],
+            }],
+        };
+
+        snapshot.write(&path)?;
+        assert_eq!(Snapshot::load(&path)?, snapshot);
+
+        std::fs::write(&path, r#"{"version": 2, "session": "dev", "windows": []}"#)?;
+        assert!(Snapshot::load(&path).is_err());
+        assert!(Snapshot::load(&temp_dir.path().join("missing.json")).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,81 @@
+//! Built-in window templates for `sesh init --template`.
+
+use crate::cli::Template;
+use crate::conf::WindowConf;
+
+/// Get the windows for a built-in template.
+pub fn windows(template: Template) -> Vec<WindowConf> {
+    let windows = match template {
+        Template::Node => NODE,
+        Template::Rust => RUST,
+        Template::Python => PYTHON,
+    };
+    windows.iter().map(TemplateWindow::to_conf).collect()
+}
+
+/// The example window `sesh init` writes without a template, so new
+/// configs show the structure: one window named after the session,
+/// running the user's `$SHELL`.
+pub fn sample(session: &str) -> Vec<WindowConf> {
+    let shell = std::env::var("SHELL").ok();
+    vec![WindowConf {
+        name: Some(session.to_string()),
+        command: shell.map(|shell| vec![shell]),
+        ..Default::default()
+    }]
+}
+
+/// A template's window, kept as static data so templates are `const`.
+struct TemplateWindow {
+    name: &'static str,
+    command: Option<&'static [&'static str]>,
+    default: bool,
+}
+
+impl TemplateWindow {
+    const fn new(name: &'static str, command: Option<&'static [&'static str]>) -> Self {
+        Self {
+            name,
+            command,
+            default: false,
+        }
+    }
+
+    fn to_conf(&self) -> WindowConf {
+        WindowConf {
+            name: Some(self.name.to_string()),
+            command: self
+                .command
+                .map(|c| c.iter().map(|s| s.to_string()).collect()),
+            default: self.default.then_some(true),
+            ..Default::default()
+        }
+    }
+}
+
+/// The editor window every template starts with.
+const EDITOR: TemplateWindow = TemplateWindow {
+    default: true,
+    ..TemplateWindow::new("editor", Some(&["vim", "."]))
+};
+
+/// Editor, dev server, and a shell.
+const NODE: &[TemplateWindow] = &[
+    EDITOR,
+    TemplateWindow::new("server", Some(&["npm", "run", "dev"])),
+    TemplateWindow::new("shell", None),
+];
+
+/// Editor, `cargo watch`, and a shell.
+const RUST: &[TemplateWindow] = &[
+    EDITOR,
+    TemplateWindow::new("watch", Some(&["cargo", "watch", "-x", "check"])),
+    TemplateWindow::new("shell", None),
+];
+
+/// Editor, a REPL, and a shell.
+const PYTHON: &[TemplateWindow] = &[
+    EDITOR,
+    TemplateWindow::new("repl", Some(&["python3"])),
+    TemplateWindow::new("shell", None),
+];
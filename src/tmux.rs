@@ -1,64 +1,508 @@
 //! TMUX utility functions for managing sessions and windows.
 
-use anyhow::{Result, anyhow};
-use std::process::Command;
+use crate::error::{Result, SeshError};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::thread;
+use std::time::Duration;
+
+/// A parsed tmux version as `(major, minor, patch letter)`, e.g. `3.3a` is
+/// `(3, 3, Some('a'))`. Tuples compare lexicographically, so versions can be
+/// compared directly.
+pub type TmuxVersion = (u32, u32, Option<char>);
+
+/// The oldest tmux version sesh is tested against.
+pub const MIN_VERSION: TmuxVersion = (2, 6, None);
+
+/// The first tmux version with `display-popup`.
+pub const POPUP_VERSION: TmuxVersion = (3, 2, None);
+
+/// Parse the output of `tmux -V` (e.g. `tmux 3.3a` or `tmux next-3.4`).
+pub fn parse_version(output: &str) -> Result<TmuxVersion> {
+    let invalid = || {
+        SeshError::Tmux(format!(
+            "Unrecognized tmux version string '{}'",
+            output.trim()
+        ))
+    };
+
+    let version = output.trim().strip_prefix("tmux").unwrap_or(output).trim();
+
+    // Strip prefixes like `next-` or `openbsd-`
+    let version = version.rsplit('-').next().ok_or_else(invalid)?;
+
+    let (major, rest) = version.split_once('.').ok_or_else(invalid)?;
+    let major = major.parse::<u32>().map_err(|_| invalid())?;
+
+    let digits_end = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    let minor = rest[..digits_end].parse::<u32>().map_err(|_| invalid())?;
+
+    let mut suffix = rest[digits_end..].chars();
+    let patch = match (suffix.next(), suffix.next()) {
+        (None, _) => None,
+        (Some(c), None) if c.is_ascii_alphabetic() => Some(c),
+        _ => return Err(invalid()),
+    };
+
+    Ok((major, minor, patch))
+}
+
+/// Parse a line of `list-panes` output in the format used by `list_panes`.
+fn parse_pane_line(line: &str) -> Result<PaneInfo> {
+    let invalid = || SeshError::Tmux(format!("Unexpected list-panes output '{}'", line));
+
+    // The path comes last, so a tab in it stays part of it
+    let mut fields = line.splitn(6, '\t');
+    let mut next = || fields.next().ok_or_else(invalid);
+
+    let window_index = next()?.parse().map_err(|_| invalid())?;
+    let window_name = next()?.to_string();
+    let command = next()?.to_string();
+    let pid = next()?.parse().map_err(|_| invalid())?;
+    let dead = next()? == "1";
+    let path = PathBuf::from(next()?);
+
+    Ok(PaneInfo {
+        window_index,
+        window_name,
+        command,
+        pid,
+        dead,
+        path,
+    })
+}
+
+/// Parse `list-windows` output in the format used by `session_info`,
+/// where each line also carries the session's own fields.
+fn parse_session_info(output: &str) -> Result<SessionInfo> {
+    let mut info = SessionInfo::default();
+    for line in output.lines().filter(|l| !l.trim().is_empty()) {
+        let invalid = || SeshError::Tmux(format!("Unexpected list-windows output '{}'", line));
+
+        let mut fields = line.splitn(4, '\t');
+        let mut next = || fields.next().ok_or_else(invalid);
+
+        let index = next()?.parse().map_err(|_| invalid())?;
+        info.attached = next()?.parse().map_err(|_| invalid())?;
+        info.created = next()?.parse().map_err(|_| invalid())?;
+        let name = next()?.to_string();
+        info.windows.push(WindowInfo { index, name });
+    }
+    Ok(info)
+}
+
+/// Format a tmux version the way `tmux -V` prints it (e.g. `3.3a`).
+pub fn format_version(version: &TmuxVersion) -> String {
+    let (major, minor, patch) = version;
+    match patch {
+        Some(c) => format!("{}.{}{}", major, minor, c),
+        None => format!("{}.{}", major, minor),
+    }
+}
+
+/// A pane in a running session, as reported by `tmux list-panes`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaneInfo {
+    pub window_index: usize,
+    pub window_name: String,
+    /// The command currently running in the pane (e.g. `bash` or `vim`)
+    pub command: String,
+    pub pid: u32,
+    /// The pane's program has exited, but tmux kept the pane open
+    /// because of `remain-on-exit`
+    pub dead: bool,
+    /// The pane's current working directory
+    pub path: PathBuf,
+}
+
+/// A running session, as reported by `session_info`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionInfo {
+    /// The session's windows, in index order
+    pub windows: Vec<WindowInfo>,
+    /// How many clients are attached to the session
+    pub attached: usize,
+    /// When the session was created, in seconds since the epoch
+    pub created: u64,
+}
+
+/// A window in a running session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowInfo {
+    pub index: usize,
+    pub name: String,
+}
+
+/// How `attach_session` attaches the client.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AttachOptions {
+    /// Detach any other clients attached to the session
+    /// (`attach-session -d`)
+    pub detach_others: bool,
+    /// Don't pass keystrokes on to the session (`-r`)
+    pub read_only: bool,
+}
+
+/// Where `new_window` puts a window, by tmux window index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowTarget {
+    /// Right before the window at this index, moving it and any windows
+    /// right after it up one
+    Before(usize),
+    /// Right after the window at this index, moving any windows right
+    /// after that up one
+    After(usize),
+    /// At exactly this index, failing if a window is already there
+    At(usize),
+}
 
 /// Trait for tmux backend operations, allowing for testing with mock implementations.
-pub trait TmuxBackend {
+///
+/// Backends are shared between threads when `up` sets up windows in parallel.
+pub trait TmuxBackend: Sync {
     /// Check if tmux is installed and available.
     fn check_available(&self) -> Result<()>;
 
+    /// Get the installed tmux version.
+    fn version(&self) -> Result<TmuxVersion>;
+
     /// Check if a session with the given name exists.
     fn has_session(&self, name: &str) -> Result<bool>;
 
     /// List all windows in a session.
     fn list_windows(&self, session: &str) -> Result<Vec<String>>;
 
-    /// Create a new tmux session.
-    fn new_session(&self, name: &str, detached: bool) -> Result<()>;
+    /// Check whether a session has a window with the given name.
+    fn has_window(&self, session: &str, name: &str) -> Result<bool>;
+
+    /// List the names of all running sessions.
+    fn list_sessions(&self) -> Result<Vec<String>>;
+
+    /// Get a session's windows, attached clients and creation time from a
+    /// single tmux call, or `None` if it isn't running.
+    fn session_info(&self, name: &str) -> Result<Option<SessionInfo>>;
+
+    /// List all panes across a session's windows.
+    fn list_panes(&self, session: &str) -> Result<Vec<PaneInfo>>;
+
+    /// Create a new tmux session, optionally starting in `start_dir`.
+    ///
+    /// With a `window_name`, the session's first window is given that
+    /// name (`-n`) instead of tmux's default. With a `shell`, it runs
+    /// that instead of tmux's default shell.
+    fn new_session(
+        &self,
+        name: &str,
+        detached: bool,
+        window_name: Option<&str>,
+        start_dir: Option<&Path>,
+        shell: Option<&Path>,
+    ) -> Result<()>;
+
+    /// Set the shell new windows and panes in a session start with.
+    fn set_default_shell(&self, session: &str, shell: &Path) -> Result<()>;
+
+    /// Get the shell new windows and panes in a session start with
+    /// (its `default-shell`).
+    fn default_shell(&self, session: &str) -> Result<PathBuf>;
+
+    /// Set a tmux option on a session, or globally (`-g`) if `global`.
+    fn set_option(&self, session: &str, key: &str, value: &str, global: bool) -> Result<()>;
+
+    /// Set a variable in a session's environment (`set-environment`).
+    fn set_environment(&self, session: &str, key: &str, value: &str) -> Result<()>;
+
+    /// Get a variable from a session's environment, or `None` if it
+    /// isn't set there.
+    fn show_environment(&self, session: &str, key: &str) -> Result<Option<String>>;
 
     /// Create a new window in an existing session.
+    ///
+    /// Without a `target`, tmux picks the first free index from
+    /// `base-index`. Without a `start_dir`, tmux uses the session's directory.
     fn new_window(
         &self,
         session: &str,
         window_name: Option<&str>,
-        target_index: Option<usize>,
+        target: Option<WindowTarget>,
+        start_dir: Option<&Path>,
     ) -> Result<()>;
 
     /// Send keys/commands to a tmux window.
     fn send_keys(&self, session: &str, window_index: usize, command: &[String]) -> Result<()>;
 
+    /// Send tmux keys (e.g. `C-c`, `Escape`) to a window without pressing Enter.
+    fn send_raw_keys(&self, session: &str, window_index: usize, keys: &[String]) -> Result<()>;
+
+    /// Type `text` into a window exactly as given (`send-keys -l`), without
+    /// pressing Enter or reading any of it as key names.
+    fn send_literal(&self, session: &str, window_index: usize, text: &str) -> Result<()>;
+
+    /// Split a window, creating a new pane which becomes the active pane.
+    fn split_window(
+        &self,
+        session: &str,
+        window_index: usize,
+        start_dir: Option<&Path>,
+    ) -> Result<()>;
+
+    /// Apply one of tmux's built-in layouts to a window.
+    fn select_layout(&self, session: &str, window_index: usize, layout: &str) -> Result<()>;
+
+    /// Capture a window's visible output plus up to `lines` lines of
+    /// its scrollback.
+    fn capture_pane(&self, session: &str, window_index: usize, lines: usize) -> Result<String>;
+
+    /// Make a pane of a window the active one.
+    fn select_pane(&self, session: &str, window_index: usize, pane_index: usize) -> Result<()>;
+
+    /// Get the name of a session's active window.
+    fn current_window(&self, session: &str) -> Result<String>;
+
+    /// Make a window the session's active one, by name.
+    fn select_window(&self, session: &str, window: &str) -> Result<()>;
+
+    /// Turn tmux's `synchronize-panes` option on or off for a window, so
+    /// keystrokes go to every pane at once.
+    fn set_synchronize_panes(&self, session: &str, window_index: usize, on: bool) -> Result<()>;
+
+    /// Append everything a window's pane prints to the file at `path`
+    /// (`pipe-pane -o`), unless its output is already being piped.
+    fn pipe_pane(&self, session: &str, window_index: usize, path: &Path) -> Result<()>;
+
+    /// Turn tmux's `automatic-rename` option on or off for a window, which
+    /// renames it after the program running in it.
+    fn set_automatic_rename(&self, session: &str, window_index: usize, on: bool) -> Result<()>;
+
     /// Kill a tmux session.
     fn kill_session(&self, name: &str) -> Result<()>;
 
-    /// Kill a specific window in a session.
-    fn kill_window(&self, session: &str, window_name: &str) -> Result<()>;
+    /// Kill the tmux server, and with it every session (not just sesh's).
+    fn kill_server(&self) -> Result<()>;
+
+    /// Move a window to a free index (`move-window`), leaving the
+    /// session's active window as it is.
+    fn move_window(&self, session: &str, index: usize, new_index: usize) -> Result<()>;
+
+    /// Swap two windows, leaving the session's active window as it is.
+    fn swap_windows(&self, session: &str, index: usize, other_index: usize) -> Result<()>;
 
-    /// Rename a window in a session.
-    fn rename_window(&self, session: &str, window_index: usize, new_name: &str) -> Result<()>;
+    /// Renumber a session's windows from `base-index` to close any gaps
+    /// left by closed windows (`move-window -r`).
+    fn renumber_windows(&self, session: &str) -> Result<()>;
+
+    /// Run a command in a popup over the current client's session,
+    /// closing it when the command exits. `width` and `height` can be
+    /// cells or percentages (e.g. `80%`).
+    fn display_popup(&self, command: &str, width: &str, height: &str) -> Result<()>;
 
     /// Attach to a tmux session (foreground operation).
-    fn attach_session(&self, name: &str) -> Result<()>;
+    fn attach_session(&self, name: &str, options: AttachOptions) -> Result<()>;
+
+    /// Attach to a tmux session, creating a bare one (without any
+    /// configured windows) first if it isn't running, in a single
+    /// `new-session -A`. With `detach_others`, other clients attached to
+    /// the session are detached (`-D`).
+    fn attach_or_create(&self, name: &str, detach_others: bool) -> Result<()>;
 
     /// Attach to a tmux session and select a specific window.
-    fn attach_session_with_window(&self, session: &str, window: &str) -> Result<()>;
+    fn attach_session_with_window(
+        &self,
+        session: &str,
+        window: &str,
+        options: AttachOptions,
+    ) -> Result<()>;
+}
+
+/// tmux errors that tend to be transient (e.g. on a heavily loaded machine
+/// right after the server starts) and are worth retrying.
+const RECOVERABLE_ERRORS: [&str; 3] = [
+    "server exited unexpectedly",
+    "lost server",
+    "no server running",
+];
+
+/// tmux errors meaning there's no such session, either because the
+/// server isn't running or because it doesn't have one by that name.
+const NOT_RUNNING_ERRORS: [&str; 4] = [
+    "no server running",
+    "error connecting to",
+    "can't find session",
+    "session not found",
+];
+
+/// Base delay between retries, multiplied by the attempt number.
+const RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Run a command, retrying up to `retries` times if it fails with
+/// one of the [`RECOVERABLE_ERRORS`].
+fn run_with_retries<F>(retries: u32, delay: Duration, mut run: F) -> Result<Output>
+where
+    F: FnMut() -> Result<Output>,
+{
+    let mut attempt = 0;
+    loop {
+        let output = run()?;
+        if output.status.success() || attempt >= retries {
+            return Ok(output);
+        }
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !RECOVERABLE_ERRORS.iter().any(|e| stderr.contains(e)) {
+            return Ok(output);
+        }
+
+        attempt += 1;
+        thread::sleep(delay * attempt);
+    }
+}
+
+/// Build the `send-keys` invocations used to run a command in a window.
+///
+/// The command is sent with `-l` so tmux doesn't interpret parts of it
+/// (e.g. `;`, `{` or `Enter`) as key names, and Enter (`C-m`) is sent
+/// separately afterwards.
+fn send_keys_args(target: &str, command: &[String]) -> [Vec<String>; 2] {
+    let send = |args: &[&str]| {
+        ["send-keys", "-t", target]
+            .iter()
+            .chain(args)
+            .map(|arg| arg.to_string())
+            .collect()
+    };
+    [send(&["-l", &command.join(" ")]), send(&["C-m"])]
+}
+
+/// Quote `arg` for `sh`, for the commands tmux runs through the shell
+/// (like `pipe-pane`'s).
+fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', "'\\''"))
 }
 
 /// Real tmux backend that executes actual tmux commands.
-pub struct RealTmuxBackend;
+pub struct RealTmuxBackend {
+    /// The tmux executable to run.
+    bin: PathBuf,
+    /// How many times to retry commands that fail transiently.
+    retries: u32,
+}
+
+impl Default for RealTmuxBackend {
+    fn default() -> Self {
+        Self {
+            bin: PathBuf::from("tmux"),
+            retries: 0,
+        }
+    }
+}
+
+impl RealTmuxBackend {
+    /// Create a backend that retries transient failures `retries` times.
+    pub fn with_retries(retries: u32) -> Self {
+        Self {
+            retries,
+            ..Default::default()
+        }
+    }
+
+    /// Use a different tmux executable, e.g. `tmux3` or a full path.
+    pub fn with_bin(self, bin: impl Into<PathBuf>) -> Self {
+        Self {
+            bin: bin.into(),
+            ..self
+        }
+    }
+
+    /// Start building a tmux command.
+    fn command(&self) -> Command {
+        Command::new(&self.bin)
+    }
+
+    /// Build the command attaching to `target`. Inside tmux, where
+    /// `attach-session` would nest, the current client is switched
+    /// to it instead (and other clients are left alone). `switch-client`
+    /// can't make the client read-only, only toggle it, so `read_only`
+    /// is refused there.
+    fn attach_command(&self, target: &str, options: AttachOptions) -> Result<Command> {
+        let mut cmd = self.command();
+        if std::env::var_os("TMUX").is_some() {
+            if options.read_only {
+                return Err(SeshError::Usage(
+                    "--read-only can't be used from inside tmux; detach first".to_string(),
+                ));
+            }
+            cmd.arg("switch-client");
+        } else {
+            cmd.arg("attach-session");
+            if options.detach_others {
+                cmd.arg("-d");
+            }
+            if options.read_only {
+                cmd.arg("-r");
+            }
+        }
+        cmd.arg("-t").arg(target);
+        Ok(cmd)
+    }
+
+    /// Run an attach command, telling a dropped connection apart from
+    /// tmux refusing to attach. tmux reports its errors on stderr, but a
+    /// client that loses its terminal only prints "[lost tty]" to that
+    /// terminal before exiting non-zero, so a failure with nothing on
+    /// stderr is taken as a dropped connection.
+    fn run_attach(&self, mut cmd: Command, session: &str, target: &str) -> Result<()> {
+        let output = cmd
+            .stdin(Stdio::inherit())
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::piped())
+            .output()?;
+
+        if output.status.success() {
+            return Ok(());
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.trim().is_empty() {
+            return Err(SeshError::AttachLost(session.to_string()));
+        }
+        Err(SeshError::Tmux(format!(
+            "Failed to attach to '{}' ({}): {}",
+            target,
+            output.status,
+            stderr.trim()
+        )))
+    }
+
+    /// Run a command that may be retried on transient failures.
+    fn output_with_retries(&self, cmd: &mut Command) -> Result<Output> {
+        run_with_retries(self.retries, RETRY_DELAY, || Ok(cmd.output()?))
+    }
+}
 
 impl TmuxBackend for RealTmuxBackend {
     fn check_available(&self) -> Result<()> {
-        let output = Command::new("tmux").arg("-V").output();
+        let output = self.command().arg("-V").output();
 
         match output {
-            Ok(_) => Ok(()),
-            Err(_) => Err(anyhow!("tmux is not installed or not available in PATH")),
+            Ok(output) if output.status.success() => Ok(()),
+            _ => Err(SeshError::TmuxMissing(self.bin.clone())),
+        }
+    }
+
+    fn version(&self) -> Result<TmuxVersion> {
+        let output = self.command().arg("-V").output()?;
+
+        if !output.status.success() {
+            return Err(SeshError::Tmux("Failed to get tmux version".to_string()));
         }
+
+        parse_version(&String::from_utf8_lossy(&output.stdout))
     }
 
     fn has_session(&self, name: &str) -> Result<bool> {
-        let output = Command::new("tmux")
+        let output = self
+            .command()
             .arg("has-session")
             .arg("-t")
             .arg(name)
@@ -68,7 +512,8 @@ impl TmuxBackend for RealTmuxBackend {
     }
 
     fn list_windows(&self, session: &str) -> Result<Vec<String>> {
-        let output = Command::new("tmux")
+        let output = self
+            .command()
             .arg("list-windows")
             .arg("-t")
             .arg(session)
@@ -77,7 +522,10 @@ impl TmuxBackend for RealTmuxBackend {
             .output()?;
 
         if !output.status.success() {
-            return Err(anyhow!("Failed to list windows for session '{}'", session));
+            return Err(SeshError::Tmux(format!(
+                "Failed to list windows for session '{}'",
+                session
+            )));
         }
 
         let stdout = String::from_utf8_lossy(&output.stdout);
@@ -90,8 +538,94 @@ impl TmuxBackend for RealTmuxBackend {
         Ok(windows)
     }
 
-    fn new_session(&self, name: &str, detached: bool) -> Result<()> {
-        let mut cmd = Command::new("tmux");
+    fn has_window(&self, session: &str, name: &str) -> Result<bool> {
+        Ok(self.list_windows(session)?.iter().any(|w| w == name))
+    }
+
+    fn list_sessions(&self) -> Result<Vec<String>> {
+        let output = self
+            .command()
+            .arg("list-sessions")
+            .arg("-F")
+            .arg("#{session_name}")
+            .output()?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(SeshError::Tmux(format!(
+                "Failed to list sessions: {}",
+                stderr
+            )));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let sessions = stdout
+            .lines()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        Ok(sessions)
+    }
+
+    fn session_info(&self, name: &str) -> Result<Option<SessionInfo>> {
+        let output = self
+            .command()
+            .arg("list-windows")
+            .arg("-t")
+            .arg(name)
+            .arg("-F")
+            .arg("#{window_index}\t#{session_attached}\t#{session_created}\t#{window_name}")
+            .output()?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if NOT_RUNNING_ERRORS.iter().any(|e| stderr.contains(e)) {
+                return Ok(None);
+            }
+            return Err(SeshError::Tmux(format!(
+                "Failed to get info for session '{}': {}",
+                name, stderr
+            )));
+        }
+
+        parse_session_info(&String::from_utf8_lossy(&output.stdout)).map(Some)
+    }
+
+    fn list_panes(&self, session: &str) -> Result<Vec<PaneInfo>> {
+        let output = self
+            .command()
+            .arg("list-panes")
+            .arg("-s")
+            .arg("-t")
+            .arg(session)
+            .arg("-F")
+            .arg("#{window_index}\t#{window_name}\t#{pane_current_command}\t#{pane_pid}\t#{pane_dead}\t#{pane_current_path}")
+            .output()?;
+
+        if !output.status.success() {
+            return Err(SeshError::Tmux(format!(
+                "Failed to list panes for session '{}'",
+                session
+            )));
+        }
+
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(parse_pane_line)
+            .collect()
+    }
+
+    fn new_session(
+        &self,
+        name: &str,
+        detached: bool,
+        window_name: Option<&str>,
+        start_dir: Option<&Path>,
+        shell: Option<&Path>,
+    ) -> Result<()> {
+        let mut cmd = self.command();
         cmd.arg("new-session");
 
         if detached {
@@ -100,211 +634,625 @@ impl TmuxBackend for RealTmuxBackend {
 
         cmd.arg("-s").arg(name);
 
-        let output = cmd.output()?;
+        if let Some(window_name) = window_name {
+            cmd.arg("-n").arg(window_name);
+        }
+
+        if let Some(dir) = start_dir {
+            cmd.arg("-c").arg(dir);
+        }
+
+        // Must come last, it's the command the first window runs
+        if let Some(shell) = shell {
+            cmd.arg(shell);
+        }
+
+        let output = self.output_with_retries(&mut cmd)?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if stderr.contains("duplicate session") {
+                return Err(SeshError::SessionExists(name.to_string()));
+            }
+            return Err(SeshError::Tmux(format!(
+                "Failed to create session '{}': {}",
+                name, stderr
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn set_default_shell(&self, session: &str, shell: &Path) -> Result<()> {
+        let output = self
+            .command()
+            .arg("set-option")
+            .arg("-t")
+            .arg(session)
+            .arg("default-shell")
+            .arg(shell)
+            .output()?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(SeshError::Tmux(format!(
+                "Failed to set default-shell for session '{}': {}",
+                session, stderr
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn default_shell(&self, session: &str) -> Result<PathBuf> {
+        let output = self
+            .command()
+            .arg("display-message")
+            .arg("-p")
+            .arg("-t")
+            .arg(session)
+            .arg("#{default-shell}")
+            .output()?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(SeshError::Tmux(format!(
+                "Failed to get the default shell for session '{}': {}",
+                session, stderr
+            )));
+        }
+
+        Ok(PathBuf::from(
+            String::from_utf8_lossy(&output.stdout).trim(),
+        ))
+    }
+
+    fn set_option(&self, session: &str, key: &str, value: &str, global: bool) -> Result<()> {
+        let mut cmd = self.command();
+        cmd.arg("set-option");
+        if global {
+            cmd.arg("-g");
+        } else {
+            cmd.arg("-t").arg(session);
+        }
+        let output = cmd.arg(key).arg(value).output()?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(SeshError::Tmux(format!(
+                "Failed to set option '{}' for session '{}': {}",
+                key, session, stderr
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn set_environment(&self, session: &str, key: &str, value: &str) -> Result<()> {
+        let output = self
+            .command()
+            .arg("set-environment")
+            .arg("-t")
+            .arg(session)
+            .arg(key)
+            .arg(value)
+            .output()?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(anyhow!("Failed to create session '{}': {}", name, stderr));
+            return Err(SeshError::Tmux(format!(
+                "Failed to set '{}' in session '{}': {}",
+                key, session, stderr
+            )));
         }
 
         Ok(())
     }
 
+    fn show_environment(&self, session: &str, key: &str) -> Result<Option<String>> {
+        let output = self
+            .command()
+            .arg("show-environment")
+            .arg("-t")
+            .arg(session)
+            .arg(key)
+            .output()?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if stderr.contains("unknown variable") {
+                return Ok(None);
+            }
+            return Err(SeshError::Tmux(format!(
+                "Failed to get '{}' from session '{}': {}",
+                key, session, stderr
+            )));
+        }
+
+        // Either `KEY=value`, or `-KEY` if it's been removed
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(stdout
+            .trim_end_matches('\n')
+            .strip_prefix(&format!("{}=", key))
+            .map(str::to_string))
+    }
+
     fn new_window(
         &self,
         session: &str,
         window_name: Option<&str>,
-        target_index: Option<usize>,
+        target: Option<WindowTarget>,
+        start_dir: Option<&Path>,
     ) -> Result<()> {
-        let mut cmd = Command::new("tmux");
+        let mut cmd = self.command();
         cmd.arg("new-window");
 
-        // Build the target based on whether we have an index
-        let target = if let Some(idx) = target_index {
-            format!("{}:{}", session, idx)
-        } else {
-            session.to_string()
-        };
-        cmd.arg("-t").arg(target);
+        // `-a` inserts after the target window, moving later windows up
+        // instead of failing when the index is taken. `-b` (before) needs
+        // tmux 3.2, so the window is created after and swapped back instead.
+        match target {
+            Some(WindowTarget::Before(idx) | WindowTarget::After(idx)) => {
+                cmd.arg("-a").arg("-t").arg(format!("{}:{}", session, idx));
+            }
+            Some(WindowTarget::At(idx)) => {
+                cmd.arg("-t").arg(format!("{}:{}", session, idx));
+            }
+            None => {
+                cmd.arg("-t").arg(session);
+            }
+        }
 
         if let Some(name) = window_name {
             cmd.arg("-n").arg(name);
         }
 
-        let output = cmd.output()?;
+        if let Some(dir) = start_dir {
+            cmd.arg("-c").arg(dir);
+        }
+
+        let output = self.output_with_retries(&mut cmd)?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(anyhow!(
+            return Err(SeshError::Tmux(format!(
                 "Failed to create window in session '{}': {}",
-                session,
-                stderr
-            ));
+                session, stderr
+            )));
         }
 
-        Ok(())
+        match target {
+            Some(WindowTarget::Before(idx)) => self.swap_windows(session, idx + 1, idx),
+            _ => Ok(()),
+        }
     }
 
     fn send_keys(&self, session: &str, window_index: usize, command: &[String]) -> Result<()> {
         let target = format!("{}:{}", session, window_index);
-        let cmd_str = command.join(" ");
 
-        let output = Command::new("tmux")
+        for args in send_keys_args(&target, command) {
+            let output = self.output_with_retries(self.command().args(&args))?;
+
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                return Err(SeshError::Tmux(format!(
+                    "Failed to send keys to '{}': {}",
+                    target, stderr
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn send_raw_keys(&self, session: &str, window_index: usize, keys: &[String]) -> Result<()> {
+        let target = format!("{}:{}", session, window_index);
+
+        let output = self
+            .command()
             .arg("send-keys")
             .arg("-t")
             .arg(&target)
-            .arg(&cmd_str)
-            .arg("C-m") // Enter key
+            .args(keys)
             .output()?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(anyhow!("Failed to send keys to '{}': {}", target, stderr));
+            return Err(SeshError::Tmux(format!(
+                "Failed to send keys to '{}': {}",
+                target, stderr
+            )));
         }
 
         Ok(())
     }
 
-    fn kill_session(&self, name: &str) -> Result<()> {
-        let output = Command::new("tmux")
-            .arg("kill-session")
+    fn send_literal(&self, session: &str, window_index: usize, text: &str) -> Result<()> {
+        let target = format!("{}:{}", session, window_index);
+
+        let output = self
+            .command()
+            .arg("send-keys")
             .arg("-t")
-            .arg(name)
+            .arg(&target)
+            .arg("-l")
+            .arg(text)
             .output()?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(anyhow!("Failed to kill session '{}': {}", name, stderr));
+            return Err(SeshError::Tmux(format!(
+                "Failed to send keys to '{}': {}",
+                target, stderr
+            )));
         }
 
         Ok(())
     }
 
-    fn kill_window(&self, session: &str, window_name: &str) -> Result<()> {
-        let target = format!("{}:{}", session, window_name);
+    fn split_window(
+        &self,
+        session: &str,
+        window_index: usize,
+        start_dir: Option<&Path>,
+    ) -> Result<()> {
+        let target = format!("{}:{}", session, window_index);
 
-        let output = Command::new("tmux")
-            .arg("kill-window")
-            .arg("-t")
-            .arg(&target)
-            .output()?;
+        let mut cmd = self.command();
+        cmd.arg("split-window").arg("-t").arg(&target);
+
+        if let Some(dir) = start_dir {
+            cmd.arg("-c").arg(dir);
+        }
+
+        let output = cmd.output()?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(anyhow!("Failed to kill window '{}': {}", target, stderr));
+            return Err(SeshError::Tmux(format!(
+                "Failed to split window '{}': {}",
+                target, stderr
+            )));
         }
 
         Ok(())
     }
 
-    fn rename_window(&self, session: &str, window_index: usize, new_name: &str) -> Result<()> {
+    fn select_layout(&self, session: &str, window_index: usize, layout: &str) -> Result<()> {
         let target = format!("{}:{}", session, window_index);
 
-        let output = Command::new("tmux")
-            .arg("rename-window")
+        let output = self
+            .command()
+            .arg("select-layout")
             .arg("-t")
             .arg(&target)
-            .arg(new_name)
+            .arg(layout)
             .output()?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(anyhow!("Failed to rename window '{}': {}", target, stderr));
+            return Err(SeshError::Tmux(format!(
+                "Failed to select layout '{}' for '{}': {}",
+                layout, target, stderr
+            )));
         }
 
         Ok(())
     }
 
-    fn attach_session(&self, name: &str) -> Result<()> {
-        let status = Command::new("tmux")
-            .arg("attach-session")
+    fn capture_pane(&self, session: &str, window_index: usize, lines: usize) -> Result<String> {
+        let target = format!("{}:{}", session, window_index);
+
+        let output = self
+            .command()
+            .arg("capture-pane")
+            .arg("-p")
             .arg("-t")
-            .arg(name)
-            .status()?;
+            .arg(&target)
+            .arg("-S")
+            .arg(format!("-{}", lines))
+            .output()?;
 
-        if !status.success() {
-            return Err(anyhow!("Failed to attach to session '{}'", name));
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(SeshError::Tmux(format!(
+                "Failed to capture pane '{}': {}",
+                target, stderr
+            )));
         }
 
-        Ok(())
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
     }
 
-    fn attach_session_with_window(&self, session: &str, window: &str) -> Result<()> {
-        let target = format!("{}:{}", session, window);
-        let status = Command::new("tmux")
-            .arg("attach-session")
+    fn select_pane(&self, session: &str, window_index: usize, pane_index: usize) -> Result<()> {
+        let target = format!("{}:{}.{}", session, window_index, pane_index);
+
+        let output = self
+            .command()
+            .arg("select-pane")
             .arg("-t")
             .arg(&target)
-            .status()?;
+            .output()?;
 
-        if !status.success() {
-            return Err(anyhow!("Failed to attach to session '{}' window '{}'", session, window));
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(SeshError::Tmux(format!(
+                "Failed to select pane '{}': {}",
+                target, stderr
+            )));
         }
 
         Ok(())
     }
-}
 
-// Convenience functions using the real backend for backward compatibility
-static REAL_BACKEND: RealTmuxBackend = RealTmuxBackend;
+    fn current_window(&self, session: &str) -> Result<String> {
+        let output = self
+            .command()
+            .arg("display-message")
+            .arg("-p")
+            .arg("-t")
+            .arg(session)
+            .arg("#{window_name}")
+            .output()?;
 
-/// Check if tmux is installed and available.
-pub fn check_tmux_available() -> Result<()> {
-    REAL_BACKEND.check_available()
-}
+        if !output.status.success() {
+            return Err(SeshError::Tmux(format!(
+                "Failed to get the current window of session '{}'",
+                session
+            )));
+        }
 
-/// Check if a session with the given name exists.
-pub fn has_session(name: &str) -> Result<bool> {
-    REAL_BACKEND.has_session(name)
-}
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
 
-/// List all windows in a session.
-pub fn list_windows(session: &str) -> Result<Vec<String>> {
-    REAL_BACKEND.list_windows(session)
-}
+    fn select_window(&self, session: &str, window: &str) -> Result<()> {
+        let target = format!("{}:{}", session, window);
 
-/// Create a new tmux session.
-pub fn new_session(name: &str, detached: bool) -> Result<()> {
-    REAL_BACKEND.new_session(name, detached)
-}
+        let output = self
+            .command()
+            .arg("select-window")
+            .arg("-t")
+            .arg(&target)
+            .output()?;
 
-/// Create a new window in an existing session.
-pub fn new_window(
-    session: &str,
-    window_name: Option<&str>,
-    target_index: Option<usize>,
-) -> Result<()> {
-    REAL_BACKEND.new_window(session, window_name, target_index)
-}
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(SeshError::Tmux(format!(
+                "Failed to select window '{}': {}",
+                target, stderr
+            )));
+        }
 
-/// Send keys/commands to a tmux window.
-pub fn send_keys(session: &str, window_index: usize, command: &[String]) -> Result<()> {
-    REAL_BACKEND.send_keys(session, window_index, command)
-}
+        Ok(())
+    }
 
-/// Kill a tmux session.
-pub fn kill_session(name: &str) -> Result<()> {
-    REAL_BACKEND.kill_session(name)
-}
+    fn set_synchronize_panes(&self, session: &str, window_index: usize, on: bool) -> Result<()> {
+        let target = format!("{}:{}", session, window_index);
 
-/// Kill a specific window in a session.
-pub fn kill_window(session: &str, window_name: &str) -> Result<()> {
-    REAL_BACKEND.kill_window(session, window_name)
-}
+        let output = self
+            .command()
+            .arg("set-window-option")
+            .arg("-t")
+            .arg(&target)
+            .arg("synchronize-panes")
+            .arg(if on { "on" } else { "off" })
+            .output()?;
 
-/// Rename a window in a session.
-pub fn rename_window(session: &str, window_index: usize, new_name: &str) -> Result<()> {
-    REAL_BACKEND.rename_window(session, window_index, new_name)
-}
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(SeshError::Tmux(format!(
+                "Failed to set synchronize-panes for '{}': {}",
+                target, stderr
+            )));
+        }
 
-/// Attach to a tmux session (foreground operation).
-pub fn attach_session(name: &str) -> Result<()> {
-    REAL_BACKEND.attach_session(name)
-}
+        Ok(())
+    }
+
+    fn pipe_pane(&self, session: &str, window_index: usize, path: &Path) -> Result<()> {
+        let target = format!("{}:{}", session, window_index);
+
+        let output = self
+            .command()
+            .arg("pipe-pane")
+            .arg("-t")
+            .arg(&target)
+            .arg("-o")
+            .arg(format!("cat >> {}", shell_quote(&path.to_string_lossy())))
+            .output()?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(SeshError::Tmux(format!(
+                "Failed to pipe '{}' to {}: {}",
+                target,
+                path.display(),
+                stderr
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn set_automatic_rename(&self, session: &str, window_index: usize, on: bool) -> Result<()> {
+        let target = format!("{}:{}", session, window_index);
+
+        let output = self
+            .command()
+            .arg("set-window-option")
+            .arg("-t")
+            .arg(&target)
+            .arg("automatic-rename")
+            .arg(if on { "on" } else { "off" })
+            .output()?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(SeshError::Tmux(format!(
+                "Failed to set automatic-rename for '{}': {}",
+                target, stderr
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn kill_session(&self, name: &str) -> Result<()> {
+        let output = self
+            .command()
+            .arg("kill-session")
+            .arg("-t")
+            .arg(name)
+            .output()?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(SeshError::Tmux(format!(
+                "Failed to kill session '{}': {}",
+                name, stderr
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn kill_server(&self) -> Result<()> {
+        let output = self.command().arg("kill-server").output()?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(SeshError::Tmux(format!(
+                "Failed to kill the tmux server: {}",
+                stderr
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn move_window(&self, session: &str, index: usize, new_index: usize) -> Result<()> {
+        let source = format!("{}:{}", session, index);
+        let target = format!("{}:{}", session, new_index);
+        let output = self
+            .command()
+            .arg("move-window")
+            .arg("-d")
+            .arg("-s")
+            .arg(&source)
+            .arg("-t")
+            .arg(&target)
+            .output()?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(SeshError::Tmux(format!(
+                "Failed to move window '{}' to '{}': {}",
+                source, target, stderr
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn swap_windows(&self, session: &str, index: usize, other_index: usize) -> Result<()> {
+        let source = format!("{}:{}", session, index);
+        let target = format!("{}:{}", session, other_index);
+        let output = self
+            .command()
+            .arg("swap-window")
+            .arg("-d")
+            .arg("-s")
+            .arg(&source)
+            .arg("-t")
+            .arg(&target)
+            .output()?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(SeshError::Tmux(format!(
+                "Failed to swap windows '{}' and '{}': {}",
+                source, target, stderr
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn renumber_windows(&self, session: &str) -> Result<()> {
+        let output = self
+            .command()
+            .arg("move-window")
+            .arg("-r")
+            .arg("-t")
+            .arg(session)
+            .output()?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(SeshError::Tmux(format!(
+                "Failed to renumber windows in session '{}': {}",
+                session, stderr
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn attach_session(&self, name: &str, options: AttachOptions) -> Result<()> {
+        let cmd = self.attach_command(name, options)?;
+        self.run_attach(cmd, name, name)
+    }
+
+    fn attach_or_create(&self, name: &str, detach_others: bool) -> Result<()> {
+        let mut cmd = self.command();
+        cmd.arg("new-session").arg("-A");
+        if detach_others {
+            cmd.arg("-D");
+        }
+        let status = cmd.arg("-s").arg(name).status()?;
+
+        if !status.success() {
+            return Err(SeshError::Tmux(format!(
+                "Failed to attach to or create session '{}'",
+                name
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn display_popup(&self, command: &str, width: &str, height: &str) -> Result<()> {
+        let output = self
+            .command()
+            .arg("display-popup")
+            .arg("-w")
+            .arg(width)
+            .arg("-h")
+            .arg(height)
+            .arg("-E")
+            .arg(command)
+            .output()?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(SeshError::Tmux(format!(
+                "Failed to open popup: {}",
+                stderr.trim()
+            )));
+        }
 
-/// Attach to a tmux session and select a specific window.
-pub fn attach_session_with_window(session: &str, window: &str) -> Result<()> {
-    REAL_BACKEND.attach_session_with_window(session, window)
+        Ok(())
+    }
+
+    fn attach_session_with_window(
+        &self,
+        session: &str,
+        window: &str,
+        options: AttachOptions,
+    ) -> Result<()> {
+        let target = format!("{}:{}", session, window);
+        let cmd = self.attach_command(&target, options)?;
+        self.run_attach(cmd, session, &target)
+    }
 }
 
 #[cfg(test)]
@@ -323,8 +1271,78 @@ pub struct MockTmuxBackend {
 #[cfg(test)]
 #[derive(Default)]
 struct MockState {
-    sessions: HashMap<String, Vec<String>>, // session_name -> window_names
+    sessions: HashMap<String, Vec<(usize, String)>>, // session_name -> (index, name) in index order
+    base_index: usize,                               // index new sessions' windows start from
     commands_sent: Vec<(String, usize, Vec<String>)>, // (session, window_idx, command)
+    panes: HashMap<String, Vec<PaneInfo>>,           // session_name -> panes
+    keys_sent: Vec<(String, usize, Vec<String>)>,    // (session, window_idx, keys)
+    literal_sent: Vec<(String, usize, String)>,      // (session, window_idx, text)
+    sent: Vec<(usize, Vec<String>, bool)>,           // (window_idx, keys or command, enter)
+    splits: Vec<(String, usize)>,                    // (session, window_idx)
+    layouts: Vec<(String, usize, String)>,           // (session, window_idx, layout)
+    start_dirs: Vec<(String, PathBuf)>,              // (target, start_dir)
+    synchronized: Vec<(String, usize, bool)>,        // (session, window_idx, on)
+    automatic_rename: Vec<(String, usize, bool)>,    // (session, window_idx, on)
+    pipes: Vec<(String, usize, PathBuf)>,            // (session, window_idx, path)
+    attached: Vec<(String, Option<String>, bool, bool)>, // (session, window, detach_others, read_only)
+    shells: Vec<(String, PathBuf)>,                      // (target, shell)
+    options: Vec<(String, String, String, bool)>,        // (session, key, value, global)
+    environment: HashMap<(String, String), String>,      // (session, key) -> value
+    focused: Vec<(String, usize, usize)>,                // (session, window_idx, pane_idx)
+    captures: HashMap<(String, usize), String>,          // (session, window_idx) -> output
+    created: HashMap<String, u64>,                       // session_name -> epoch seconds
+    renumbered: Vec<String>,                             // session_name
+    current: HashMap<String, String>,                    // session_name -> active window
+    selected: Vec<(String, String)>,                     // (session, window)
+    version: Option<TmuxVersion>,
+    popups: Vec<(String, String, String)>, // (command, width, height)
+    rejected_commands: Vec<String>,        // programs `send_keys` fails on
+    rejected_windows: Vec<String>,         // names `new_window` fails on
+    dropped_attaches: usize,               // attaches left to fail
+}
+
+#[cfg(test)]
+impl MockState {
+    /// Get a session's windows, as `(index, name)` in index order.
+    fn windows(&self, session: &str) -> Result<&Vec<(usize, String)>> {
+        self.sessions
+            .get(session)
+            .ok_or_else(|| SeshError::SessionNotRunning(session.to_string()))
+    }
+
+    /// Fail like tmux does for a target window that doesn't exist.
+    fn check_window(&self, session: &str, window_index: usize) -> Result<()> {
+        if self
+            .windows(session)?
+            .iter()
+            .any(|(i, _)| *i == window_index)
+        {
+            return Ok(());
+        }
+        Err(SeshError::Tmux(format!(
+            "can't find window: {}:{}",
+            session, window_index
+        )))
+    }
+
+    /// Give a session's windows contiguous indices from `base-index`.
+    fn renumber(&mut self, session: &str) {
+        let base = self.base_index;
+        if let Some(windows) = self.sessions.get_mut(session) {
+            for (offset, window) in windows.iter_mut().enumerate() {
+                window.0 = base + offset;
+            }
+        }
+    }
+
+    /// Fail an attach if any are set to drop (see `with_dropped_attaches`).
+    fn drop_attach(&mut self, session: &str) -> Result<()> {
+        if self.dropped_attaches == 0 {
+            return Ok(());
+        }
+        self.dropped_attaches -= 1;
+        Err(SeshError::AttachLost(session.to_string()))
+    }
 }
 
 #[cfg(test)]
@@ -336,22 +1354,202 @@ impl MockTmuxBackend {
     }
 
     pub fn with_session(self, name: &str, windows: Vec<&str>) -> Self {
+        let base = self.state.lock().unwrap().base_index;
+        let indexed = windows
+            .into_iter()
+            .enumerate()
+            .map(|(offset, w)| (base + offset, w))
+            .collect();
+        self.with_indexed_session(name, indexed)
+    }
+
+    /// Add a session whose windows have the given indices, e.g. with
+    /// gaps left by closed windows.
+    pub fn with_indexed_session(self, name: &str, windows: Vec<(usize, &str)>) -> Self {
         let mut state = self.state.lock().unwrap();
-        state.sessions.insert(
-            name.to_string(),
-            windows.iter().map(|w| w.to_string()).collect(),
-        );
+        let mut windows: Vec<_> = windows
+            .into_iter()
+            .map(|(index, w)| (index, w.to_string()))
+            .collect();
+        windows.sort();
+        state.sessions.insert(name.to_string(), windows);
+        drop(state);
+        self
+    }
+
+    /// Set tmux's `base-index`, the index the windows of sessions created
+    /// from here on start at (otherwise 0).
+    pub fn with_base_index(self, base_index: usize) -> Self {
+        self.state.lock().unwrap().base_index = base_index;
+        self
+    }
+
+    /// Set the panes reported by `list_panes` for a session. Without
+    /// this, each window has a single pane running `bash`.
+    pub fn with_panes(self, session: &str, panes: Vec<PaneInfo>) -> Self {
+        let mut state = self.state.lock().unwrap();
+        state.panes.insert(session.to_string(), panes);
+        drop(state);
+        self
+    }
+
+    /// Set the output `capture_pane` returns for a window.
+    pub fn with_capture(self, session: &str, window_index: usize, output: &str) -> Self {
+        let mut state = self.state.lock().unwrap();
+        state
+            .captures
+            .insert((session.to_string(), window_index), output.to_string());
+        drop(state);
+        self
+    }
+
+    /// Set the creation time `session_info` reports for a session
+    /// (otherwise 0, the epoch).
+    pub fn with_created(self, session: &str, epoch_secs: u64) -> Self {
+        let mut state = self.state.lock().unwrap();
+        state.created.insert(session.to_string(), epoch_secs);
+        drop(state);
+        self
+    }
+
+    /// Set a variable in a session's environment.
+    pub fn with_environment(self, session: &str, key: &str, value: &str) -> Self {
+        let mut state = self.state.lock().unwrap();
+        state
+            .environment
+            .insert((session.to_string(), key.to_string()), value.to_string());
+        drop(state);
+        self
+    }
+
+    /// Set the version `version` returns (otherwise 3.4).
+    pub fn with_version(self, version: TmuxVersion) -> Self {
+        self.state.lock().unwrap().version = Some(version);
+        self
+    }
+
+    /// Make `send_keys` fail for commands running `program`.
+    pub fn with_rejected_command(self, program: &str) -> Self {
+        let mut state = self.state.lock().unwrap();
+        state.rejected_commands.push(program.to_string());
         drop(state);
         self
     }
 
+    /// Make `new_window` fail for windows called `name`.
+    pub fn with_rejected_window(self, name: &str) -> Self {
+        let mut state = self.state.lock().unwrap();
+        state.rejected_windows.push(name.to_string());
+        drop(state);
+        self
+    }
+
+    /// Make the next `count` attaches fail, as if the connection
+    /// dropped while attached.
+    pub fn with_dropped_attaches(self, count: usize) -> Self {
+        let mut state = self.state.lock().unwrap();
+        state.dropped_attaches = count;
+        drop(state);
+        self
+    }
+
+    /// Set the window `current_window` returns for a session
+    /// (otherwise its first window).
+    pub fn with_current(self, session: &str, window: &str) -> Self {
+        let mut state = self.state.lock().unwrap();
+        state
+            .current
+            .insert(session.to_string(), window.to_string());
+        drop(state);
+        self
+    }
+
+    /// Each session's window names, in index order.
     pub fn get_sessions(&self) -> HashMap<String, Vec<String>> {
-        self.state.lock().unwrap().sessions.clone()
+        let state = self.state.lock().unwrap();
+        state
+            .sessions
+            .iter()
+            .map(|(name, windows)| {
+                let names = windows.iter().map(|(_, w)| w.clone()).collect();
+                (name.clone(), names)
+            })
+            .collect()
     }
 
     pub fn get_commands_sent(&self) -> Vec<(String, usize, Vec<String>)> {
         self.state.lock().unwrap().commands_sent.clone()
     }
+
+    pub fn get_keys_sent(&self) -> Vec<(String, usize, Vec<String>)> {
+        self.state.lock().unwrap().keys_sent.clone()
+    }
+
+    pub fn get_literal_sent(&self) -> Vec<(String, usize, String)> {
+        self.state.lock().unwrap().literal_sent.clone()
+    }
+
+    /// Commands, raw keys and literal text in the order they were sent,
+    /// where `enter` is true for commands.
+    pub fn get_sent(&self) -> Vec<(usize, Vec<String>, bool)> {
+        self.state.lock().unwrap().sent.clone()
+    }
+
+    pub fn get_splits(&self) -> Vec<(String, usize)> {
+        self.state.lock().unwrap().splits.clone()
+    }
+
+    pub fn get_layouts(&self) -> Vec<(String, usize, String)> {
+        self.state.lock().unwrap().layouts.clone()
+    }
+
+    /// Shells that sessions' first windows were started with (keyed by
+    /// `session:0`) or that were set as a session's `default-shell`.
+    pub fn get_shells(&self) -> Vec<(String, PathBuf)> {
+        self.state.lock().unwrap().shells.clone()
+    }
+
+    pub fn get_renumbered(&self) -> Vec<String> {
+        self.state.lock().unwrap().renumbered.clone()
+    }
+
+    pub fn get_options(&self) -> Vec<(String, String, String, bool)> {
+        self.state.lock().unwrap().options.clone()
+    }
+
+    pub fn get_attached(&self) -> Vec<(String, Option<String>, bool, bool)> {
+        self.state.lock().unwrap().attached.clone()
+    }
+
+    pub fn get_focused(&self) -> Vec<(String, usize, usize)> {
+        self.state.lock().unwrap().focused.clone()
+    }
+
+    pub fn get_popups(&self) -> Vec<(String, String, String)> {
+        self.state.lock().unwrap().popups.clone()
+    }
+
+    pub fn get_selected(&self) -> Vec<(String, String)> {
+        self.state.lock().unwrap().selected.clone()
+    }
+
+    pub fn get_automatic_rename(&self) -> Vec<(String, usize, bool)> {
+        self.state.lock().unwrap().automatic_rename.clone()
+    }
+
+    pub fn get_synchronized(&self) -> Vec<(String, usize, bool)> {
+        self.state.lock().unwrap().synchronized.clone()
+    }
+
+    pub fn get_pipes(&self) -> Vec<(String, usize, PathBuf)> {
+        self.state.lock().unwrap().pipes.clone()
+    }
+
+    /// Directories that sessions, windows and panes were started in,
+    /// keyed by their `session:window_idx` target.
+    pub fn get_start_dirs(&self) -> Vec<(String, PathBuf)> {
+        self.state.lock().unwrap().start_dirs.clone()
+    }
 }
 
 #[cfg(test)]
@@ -360,6 +1558,10 @@ impl TmuxBackend for MockTmuxBackend {
         Ok(())
     }
 
+    fn version(&self) -> Result<TmuxVersion> {
+        Ok(self.state.lock().unwrap().version.unwrap_or((3, 4, None)))
+    }
+
     fn has_session(&self, name: &str) -> Result<bool> {
         let state = self.state.lock().unwrap();
         Ok(state.sessions.contains_key(name))
@@ -367,114 +1569,747 @@ impl TmuxBackend for MockTmuxBackend {
 
     fn list_windows(&self, session: &str) -> Result<Vec<String>> {
         let state = self.state.lock().unwrap();
-        state
-            .sessions
-            .get(session)
-            .cloned()
-            .ok_or_else(|| anyhow!("Session '{}' not found", session))
+        Ok(state
+            .windows(session)?
+            .iter()
+            .map(|(_, w)| w.clone())
+            .collect())
     }
 
-    fn new_session(&self, name: &str, _detached: bool) -> Result<()> {
+    fn has_window(&self, session: &str, name: &str) -> Result<bool> {
+        let state = self.state.lock().unwrap();
+        Ok(state.windows(session)?.iter().any(|(_, w)| w == name))
+    }
+
+    fn list_sessions(&self) -> Result<Vec<String>> {
+        let state = self.state.lock().unwrap();
+        let mut sessions: Vec<_> = state.sessions.keys().cloned().collect();
+        sessions.sort();
+        Ok(sessions)
+    }
+
+    fn session_info(&self, name: &str) -> Result<Option<SessionInfo>> {
+        let state = self.state.lock().unwrap();
+        let Some(windows) = state.sessions.get(name) else {
+            return Ok(None);
+        };
+
+        Ok(Some(SessionInfo {
+            windows: windows
+                .iter()
+                .map(|(index, name)| WindowInfo {
+                    index: *index,
+                    name: name.clone(),
+                })
+                .collect(),
+            attached: state.attached.iter().filter(|a| a.0 == name).count(),
+            created: state.created.get(name).copied().unwrap_or(0),
+        }))
+    }
+
+    fn list_panes(&self, session: &str) -> Result<Vec<PaneInfo>> {
+        let state = self.state.lock().unwrap();
+        let windows = state.windows(session)?;
+
+        if let Some(panes) = state.panes.get(session) {
+            return Ok(panes.clone());
+        }
+
+        // Panes are where their window was started, or at `/`
+        Ok(windows
+            .iter()
+            .map(|(idx, name)| PaneInfo {
+                window_index: *idx,
+                window_name: name.clone(),
+                command: "bash".to_string(),
+                pid: 1000 + *idx as u32,
+                dead: false,
+                path: state
+                    .start_dirs
+                    .iter()
+                    .find(|(target, _)| *target == format!("{}:{}", session, idx))
+                    .map_or_else(|| PathBuf::from("/"), |(_, dir)| dir.clone()),
+            })
+            .collect())
+    }
+
+    fn new_session(
+        &self,
+        name: &str,
+        _detached: bool,
+        window_name: Option<&str>,
+        start_dir: Option<&Path>,
+        shell: Option<&Path>,
+    ) -> Result<()> {
         let mut state = self.state.lock().unwrap();
         if state.sessions.contains_key(name) {
-            return Err(anyhow!("Session '{}' already exists", name));
+            return Err(SeshError::SessionExists(name.to_string()));
+        }
+        // Like tmux, the session starts with one window at `base-index`
+        let base = state.base_index;
+        state.sessions.insert(
+            name.to_string(),
+            vec![(base, window_name.unwrap_or("bash").to_string())],
+        );
+        if let Some(dir) = start_dir {
+            state
+                .start_dirs
+                .push((format!("{}:{}", name, base), dir.to_path_buf()));
+        }
+        if let Some(shell) = shell {
+            state
+                .shells
+                .push((format!("{}:{}", name, base), shell.to_path_buf()));
+        }
+        Ok(())
+    }
+
+    fn set_default_shell(&self, session: &str, shell: &Path) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if !state.sessions.contains_key(session) {
+            return Err(SeshError::SessionNotRunning(session.to_string()));
+        }
+        state
+            .shells
+            .push((session.to_string(), shell.to_path_buf()));
+        Ok(())
+    }
+
+    fn default_shell(&self, session: &str) -> Result<PathBuf> {
+        let state = self.state.lock().unwrap();
+        state.windows(session)?;
+        // The mock's panes run `bash` unless told otherwise
+        Ok(state
+            .shells
+            .iter()
+            .rev()
+            .find(|(target, _)| target == session)
+            .map_or_else(|| PathBuf::from("/bin/bash"), |(_, shell)| shell.clone()))
+    }
+
+    fn set_option(&self, session: &str, key: &str, value: &str, global: bool) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if !state.sessions.contains_key(session) {
+            return Err(SeshError::SessionNotRunning(session.to_string()));
         }
-        // Create session with default window at index 0 (matches real tmux behavior)
-        state.sessions.insert(name.to_string(), vec!["bash".to_string()]);
+        state.options.push((
+            session.to_string(),
+            key.to_string(),
+            value.to_string(),
+            global,
+        ));
         Ok(())
     }
 
+    fn set_environment(&self, session: &str, key: &str, value: &str) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if !state.sessions.contains_key(session) {
+            return Err(SeshError::SessionNotRunning(session.to_string()));
+        }
+        state
+            .environment
+            .insert((session.to_string(), key.to_string()), value.to_string());
+        Ok(())
+    }
+
+    fn show_environment(&self, session: &str, key: &str) -> Result<Option<String>> {
+        let state = self.state.lock().unwrap();
+        if !state.sessions.contains_key(session) {
+            return Err(SeshError::SessionNotRunning(session.to_string()));
+        }
+        Ok(state
+            .environment
+            .get(&(session.to_string(), key.to_string()))
+            .cloned())
+    }
+
     fn new_window(
         &self,
         session: &str,
         window_name: Option<&str>,
-        _target_index: Option<usize>,
+        target: Option<WindowTarget>,
+        start_dir: Option<&Path>,
     ) -> Result<()> {
         let mut state = self.state.lock().unwrap();
-        let windows = state
-            .sessions
-            .get_mut(session)
-            .ok_or_else(|| anyhow!("Session '{}' not found", session))?;
-
         let name = window_name.unwrap_or("unnamed").to_string();
-        windows.push(name);
+        if state.rejected_windows.contains(&name) {
+            return Err(SeshError::Tmux(format!(
+                "Failed to create window in session '{}': rejected '{}'",
+                session, name
+            )));
+        }
+
+        state.windows(session)?;
+
+        // The window placed before or after must exist. Without a target,
+        // tmux takes the first free index from `base-index`.
+        let taken = |idx: usize| {
+            state
+                .windows(session)
+                .map(|w| w.iter().any(|(i, _)| *i == idx))
+        };
+        let index = match target {
+            Some(WindowTarget::Before(idx) | WindowTarget::After(idx)) if !taken(idx)? => {
+                return Err(SeshError::Tmux(format!(
+                    "Failed to create window in session '{}': can't find window {}",
+                    session, idx
+                )));
+            }
+            Some(WindowTarget::At(idx)) if taken(idx)? => {
+                return Err(SeshError::Tmux(format!(
+                    "Failed to create window in session '{}': index {} in use",
+                    session, idx
+                )));
+            }
+            Some(WindowTarget::At(idx)) => idx,
+            Some(WindowTarget::Before(idx)) => idx,
+            Some(WindowTarget::After(idx)) => idx + 1,
+            None => (state.base_index..)
+                .find(|i| !taken(*i).unwrap_or(false))
+                .unwrap_or_default(),
+        };
+
+        // A taken index moves that window and any right after it up one
+        let windows = state.sessions.get_mut(session).unwrap();
+        let mut next = index;
+        for window in windows.iter_mut() {
+            if window.0 == next {
+                next += 1;
+                window.0 = next;
+            }
+        }
+        windows.push((index, name));
+        windows.sort();
+
+        if let Some(dir) = start_dir {
+            state
+                .start_dirs
+                .push((format!("{}:{}", session, index), dir.to_path_buf()));
+        }
         Ok(())
     }
 
     fn send_keys(&self, session: &str, window_index: usize, command: &[String]) -> Result<()> {
         let mut state = self.state.lock().unwrap();
-        if !state.sessions.contains_key(session) {
-            return Err(anyhow!("Session '{}' not found", session));
+        state.check_window(session, window_index)?;
+        if command
+            .first()
+            .is_some_and(|program| state.rejected_commands.contains(program))
+        {
+            return Err(SeshError::Tmux(format!(
+                "Failed to send keys to '{}:{}': rejected '{}'",
+                session,
+                window_index,
+                command.join(" ")
+            )));
         }
         state
             .commands_sent
             .push((session.to_string(), window_index, command.to_vec()));
+        state.sent.push((window_index, command.to_vec(), true));
+        Ok(())
+    }
+
+    fn send_raw_keys(&self, session: &str, window_index: usize, keys: &[String]) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.check_window(session, window_index)?;
+        state
+            .keys_sent
+            .push((session.to_string(), window_index, keys.to_vec()));
+        state.sent.push((window_index, keys.to_vec(), false));
+        Ok(())
+    }
+
+    fn send_literal(&self, session: &str, window_index: usize, text: &str) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.check_window(session, window_index)?;
+        state
+            .literal_sent
+            .push((session.to_string(), window_index, text.to_string()));
+        state
+            .sent
+            .push((window_index, vec![text.to_string()], false));
+        Ok(())
+    }
+
+    fn split_window(
+        &self,
+        session: &str,
+        window_index: usize,
+        start_dir: Option<&Path>,
+    ) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.check_window(session, window_index)?;
+        state.splits.push((session.to_string(), window_index));
+        if let Some(dir) = start_dir {
+            state
+                .start_dirs
+                .push((format!("{}:{}", session, window_index), dir.to_path_buf()));
+        }
+        Ok(())
+    }
+
+    fn select_layout(&self, session: &str, window_index: usize, layout: &str) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.check_window(session, window_index)?;
+        state
+            .layouts
+            .push((session.to_string(), window_index, layout.to_string()));
+        Ok(())
+    }
+
+    fn capture_pane(&self, session: &str, window_index: usize, _lines: usize) -> Result<String> {
+        let state = self.state.lock().unwrap();
+        state.check_window(session, window_index)?;
+        Ok(state
+            .captures
+            .get(&(session.to_string(), window_index))
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn select_pane(&self, session: &str, window_index: usize, pane_index: usize) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.check_window(session, window_index)?;
+        state
+            .focused
+            .push((session.to_string(), window_index, pane_index));
+        Ok(())
+    }
+
+    fn current_window(&self, session: &str) -> Result<String> {
+        let state = self.state.lock().unwrap();
+        let first = state.windows(session)?.first().map(|(_, w)| w);
+        Ok(state
+            .current
+            .get(session)
+            .or(first)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn select_window(&self, session: &str, window: &str) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if !state.windows(session)?.iter().any(|(_, w)| w == window) {
+            return Err(SeshError::WindowNotRunning {
+                session: session.to_string(),
+                window: window.to_string(),
+            });
+        }
+        state
+            .current
+            .insert(session.to_string(), window.to_string());
+        state
+            .selected
+            .push((session.to_string(), window.to_string()));
+        Ok(())
+    }
+
+    fn set_synchronize_panes(&self, session: &str, window_index: usize, on: bool) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.check_window(session, window_index)?;
+        state
+            .synchronized
+            .push((session.to_string(), window_index, on));
+        Ok(())
+    }
+
+    fn pipe_pane(&self, session: &str, window_index: usize, path: &Path) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.check_window(session, window_index)?;
+        state
+            .pipes
+            .push((session.to_string(), window_index, path.to_path_buf()));
+        Ok(())
+    }
+
+    fn set_automatic_rename(&self, session: &str, window_index: usize, on: bool) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.check_window(session, window_index)?;
+        state
+            .automatic_rename
+            .push((session.to_string(), window_index, on));
         Ok(())
     }
 
     fn kill_session(&self, name: &str) -> Result<()> {
         let mut state = self.state.lock().unwrap();
         if state.sessions.remove(name).is_none() {
-            return Err(anyhow!("Session '{}' not found", name));
+            return Err(SeshError::SessionNotRunning(name.to_string()));
         }
+        state.current.remove(name);
+        Ok(())
+    }
+
+    fn kill_server(&self) -> Result<()> {
+        self.state.lock().unwrap().sessions.clear();
         Ok(())
     }
 
-    fn kill_window(&self, session: &str, window_name: &str) -> Result<()> {
+    fn move_window(&self, session: &str, index: usize, new_index: usize) -> Result<()> {
         let mut state = self.state.lock().unwrap();
-        let windows = state
-            .sessions
-            .get_mut(session)
-            .ok_or_else(|| anyhow!("Session '{}' not found", session))?;
+        state.check_window(session, index)?;
+        if state.windows(session)?.iter().any(|(i, _)| *i == new_index) {
+            return Err(SeshError::Tmux(format!(
+                "Failed to move window '{}:{}': index {} in use",
+                session, index, new_index
+            )));
+        }
 
-        if let Some(pos) = windows.iter().position(|w| w == window_name) {
-            windows.remove(pos);
-            Ok(())
-        } else {
-            Err(anyhow!(
-                "Window '{}' not found in session '{}'",
-                window_name,
-                session
-            ))
+        let windows = state.sessions.get_mut(session).unwrap();
+        for window in windows.iter_mut().filter(|w| w.0 == index) {
+            window.0 = new_index;
         }
+        windows.sort();
+        Ok(())
     }
 
-    fn rename_window(&self, session: &str, window_index: usize, new_name: &str) -> Result<()> {
+    fn swap_windows(&self, session: &str, index: usize, other_index: usize) -> Result<()> {
         let mut state = self.state.lock().unwrap();
-        let windows = state
-            .sessions
-            .get_mut(session)
-            .ok_or_else(|| anyhow!("Session '{}' not found", session))?;
-
-        if window_index >= windows.len() {
-            return Err(anyhow!(
-                "Window index {} out of range in session '{}'",
-                window_index,
-                session
-            ));
+        state.check_window(session, index)?;
+        state.check_window(session, other_index)?;
+
+        for window in state.sessions.get_mut(session).unwrap() {
+            if window.0 == index {
+                window.0 = other_index;
+            } else if window.0 == other_index {
+                window.0 = index;
+            }
         }
+        state.sessions.get_mut(session).unwrap().sort();
+        Ok(())
+    }
 
-        windows[window_index] = new_name.to_string();
+    fn renumber_windows(&self, session: &str) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if !state.sessions.contains_key(session) {
+            return Err(SeshError::SessionNotRunning(session.to_string()));
+        }
+        state.renumber(session);
+        state.renumbered.push(session.to_string());
         Ok(())
     }
 
-    fn attach_session(&self, name: &str) -> Result<()> {
-        let state = self.state.lock().unwrap();
+    fn attach_session(&self, name: &str, options: AttachOptions) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
         if !state.sessions.contains_key(name) {
-            return Err(anyhow!("Session '{}' not found", name));
+            return Err(SeshError::SessionNotRunning(name.to_string()));
         }
+        state.attached.push((
+            name.to_string(),
+            None,
+            options.detach_others,
+            options.read_only,
+        ));
+        state.drop_attach(name)
+    }
+
+    fn attach_or_create(&self, name: &str, detach_others: bool) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let base = state.base_index;
+        state
+            .sessions
+            .entry(name.to_string())
+            .or_insert_with(|| vec![(base, "bash".to_string())]);
+        state
+            .attached
+            .push((name.to_string(), None, detach_others, false));
         Ok(())
     }
 
-    fn attach_session_with_window(&self, session: &str, window: &str) -> Result<()> {
-        let state = self.state.lock().unwrap();
-        let windows = state.sessions
-            .get(session)
-            .ok_or_else(|| anyhow!("Session '{}' not found", session))?;
+    fn display_popup(&self, command: &str, width: &str, height: &str) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state
+            .popups
+            .push((command.to_string(), width.to_string(), height.to_string()));
+        Ok(())
+    }
 
-        if !windows.iter().any(|w| w == window) {
-            return Err(anyhow!("Window '{}' not found in session '{}'", window, session));
+    fn attach_session_with_window(
+        &self,
+        session: &str,
+        window: &str,
+        options: AttachOptions,
+    ) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if !state.windows(session)?.iter().any(|(_, w)| w == window) {
+            return Err(SeshError::WindowNotRunning {
+                session: session.to_string(),
+                window: window.to_string(),
+            });
         }
+        state.attached.push((
+            session.to_string(),
+            Some(window.to_string()),
+            options.detach_others,
+            options.read_only,
+        ));
+        state.drop_attach(session)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::ExitStatus;
+
+    fn output(code: i32, stderr: &str) -> Output {
+        Output {
+            status: ExitStatus::from_raw(code << 8),
+            stdout: vec![],
+            stderr: stderr.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("logs/server.log"), "'logs/server.log'");
+        assert_eq!(shell_quote("it's here.log"), "'it'\\''s here.log'");
+    }
+
+    #[test]
+    fn test_retry_recovers_from_transient_failure() -> Result<()> {
+        let mut calls = 0;
+        let result = run_with_retries(2, Duration::ZERO, || {
+            calls += 1;
+            if calls == 1 {
+                Ok(output(1, "server exited unexpectedly"))
+            } else {
+                Ok(output(0, ""))
+            }
+        })?;
+
+        assert!(result.status.success());
+        assert_eq!(calls, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_retry_skips_genuine_errors() -> Result<()> {
+        let mut calls = 0;
+        let result = run_with_retries(2, Duration::ZERO, || {
+            calls += 1;
+            Ok(output(1, "duplicate session: web"))
+        })?;
+
+        assert!(!result.status.success());
+        assert_eq!(calls, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_retry_gives_up_after_limit() -> Result<()> {
+        let mut calls = 0;
+        let result = run_with_retries(2, Duration::ZERO, || {
+            calls += 1;
+            Ok(output(1, "lost server"))
+        })?;
+
+        assert!(!result.status.success());
+        assert_eq!(calls, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_version() -> Result<()> {
+        assert_eq!(parse_version("tmux 3.3")?, (3, 3, None));
+        assert_eq!(parse_version("tmux 3.3a\n")?, (3, 3, Some('a')));
+        assert_eq!(parse_version("tmux next-3.4")?, (3, 4, None));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_version_invalid() {
+        assert!(parse_version("tmux master").is_err());
+        assert!(parse_version("").is_err());
+    }
+
+    #[test]
+    fn test_parse_pane_line() -> Result<()> {
+        let pane = parse_pane_line("2\tserver\tnode\t4242\t0\t/home/me/my\tapp")?;
+        assert_eq!(
+            pane,
+            PaneInfo {
+                window_index: 2,
+                window_name: "server".to_string(),
+                command: "node".to_string(),
+                pid: 4242,
+                dead: false,
+                path: PathBuf::from("/home/me/my\tapp"),
+            }
+        );
+        assert!(parse_pane_line("2\tserver\tnode\t4242\t1\t/")?.dead);
+        assert!(parse_pane_line("server\tnode").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_session_info() -> Result<()> {
+        let info = parse_session_info("0\t1\t1700000000\teditor\n2\t1\t1700000000\tmy\tlogs\n")?;
+        assert_eq!(
+            info,
+            SessionInfo {
+                windows: vec![
+                    WindowInfo {
+                        index: 0,
+                        name: "editor".to_string(),
+                    },
+                    WindowInfo {
+                        index: 2,
+                        name: "my\tlogs".to_string(),
+                    },
+                ],
+                attached: 1,
+                created: 1700000000,
+            }
+        );
+        assert!(parse_session_info("0\teditor").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_mock_session_info() -> Result<()> {
+        let backend = MockTmuxBackend::new()
+            .with_session("dev", vec!["editor", "server"])
+            .with_created("dev", 42);
+
+        assert_eq!(backend.session_info("other")?, None);
+
+        let info = backend.session_info("dev")?.unwrap();
+        let names: Vec<_> = info.windows.iter().map(|w| w.name.as_str()).collect();
+        assert_eq!(names, ["editor", "server"]);
+        assert_eq!(info.windows[1].index, 1);
+        assert_eq!((info.attached, info.created), (0, 42));
+
+        backend.attach_session("dev", AttachOptions::default())?;
+        assert_eq!(backend.session_info("dev")?.unwrap().attached, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_version_ordering() {
+        assert!((2, 5, None) < MIN_VERSION);
+        assert!((2, 6, Some('a')) > MIN_VERSION);
+        assert!((3, 0, None) > MIN_VERSION);
+    }
+
+    #[test]
+    fn test_send_keys_args_literal() {
+        let command = vec!["echo".to_string(), "a; echo {b}".to_string()];
+        let [keys, enter] = send_keys_args("dev:1", &command);
+
+        assert_eq!(keys, ["send-keys", "-t", "dev:1", "-l", "echo a; echo {b}"]);
+        assert_eq!(enter, ["send-keys", "-t", "dev:1", "C-m"]);
+    }
+
+    #[test]
+    fn test_check_available_with_bin() {
+        let backend = RealTmuxBackend::default().with_bin("/no/such/tmux");
+        assert!(matches!(
+            backend.check_available(),
+            Err(SeshError::TmuxMissing(_))
+        ));
+
+        // Runs, but doesn't respond to `-V`
+        let backend = RealTmuxBackend::default().with_bin("false");
+        assert!(backend.check_available().is_err());
+    }
+
+    #[test]
+    fn test_session_info_passes_other_errors() {
+        // Fails without saying there's no such session or server
+        let backend = RealTmuxBackend::default().with_bin("false");
+        assert!(backend.session_info("dev").is_err());
+    }
+
+    #[test]
+    fn test_mock_has_window() -> Result<()> {
+        let backend = MockTmuxBackend::new().with_session("dev", vec!["editor", "server"]);
+
+        assert!(backend.has_window("dev", "server")?);
+        assert!(!backend.has_window("dev", "logs")?);
+        assert!(matches!(
+            backend.has_window("other", "editor"),
+            Err(SeshError::SessionNotRunning(_))
+        ));
+
+        // Windows created later are found too
+        backend.new_window("dev", Some("logs"), None, None)?;
+        assert!(backend.has_window("dev", "logs")?);
+        Ok(())
+    }
+
+    /// The `(index, name)` of each of a session's windows.
+    fn indices(backend: &MockTmuxBackend, session: &str) -> Result<Vec<(usize, String)>> {
+        let info = backend.session_info(session)?.unwrap();
+        Ok(info
+            .windows
+            .into_iter()
+            .map(|w| (w.index, w.name))
+            .collect())
+    }
+
+    #[test]
+    fn test_mock_window_indices() -> Result<()> {
+        let backend = MockTmuxBackend::new()
+            .with_base_index(1)
+            .with_session("dev", vec!["editor", "server"])
+            .with_indexed_session("gaps", vec![(0, "a"), (2, "b"), (3, "c")]);
+
+        assert_eq!(
+            indices(&backend, "dev")?,
+            [(1, "editor".to_string()), (2, "server".to_string())]
+        );
+
+        // Windows only exist at their real indices
+        assert!(backend.send_keys("dev", 0, &["ls".to_string()]).is_err());
+        backend.send_keys("dev", 2, &["ls".to_string()])?;
+
+        // New windows take the first free index, and inserting at a taken
+        // one moves it and the windows right after it up
+        backend.new_window("gaps", Some("d"), None, None)?;
+        backend.new_window("gaps", Some("e"), Some(WindowTarget::After(1)), None)?;
+        assert_eq!(
+            indices(&backend, "gaps")?,
+            [(0, "a"), (1, "d"), (2, "e"), (3, "b"), (4, "c")].map(|(i, w)| (i, w.to_string()))
+        );
+
+        // An exact index has to be free, and nothing else moves
+        assert!(
+            backend
+                .new_window("gaps", Some("f"), Some(WindowTarget::At(4)), None)
+                .is_err()
+        );
+        backend.new_window("gaps", Some("f"), Some(WindowTarget::At(7)), None)?;
+        backend.move_window("gaps", 0, 5)?;
+        assert!(backend.move_window("gaps", 1, 7).is_err());
+        assert_eq!(
+            indices(&backend, "gaps")?,
+            [(1, "d"), (2, "e"), (3, "b"), (4, "c"), (5, "a"), (7, "f")]
+                .map(|(i, w)| (i, w.to_string()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_mock_swap_and_renumber() -> Result<()> {
+        let backend = MockTmuxBackend::new()
+            .with_base_index(1)
+            .with_indexed_session("dev", vec![(1, "a"), (3, "b"), (4, "c")]);
+
+        backend.renumber_windows("dev")?;
+        assert_eq!(
+            indices(&backend, "dev")?,
+            [(1, "a"), (2, "b"), (3, "c")].map(|(i, w)| (i, w.to_string()))
+        );
+
+        backend.swap_windows("dev", 3, 1)?;
+        assert_eq!(
+            indices(&backend, "dev")?,
+            [(1, "c"), (2, "b"), (3, "a")].map(|(i, w)| (i, w.to_string()))
+        );
+        assert!(backend.swap_windows("dev", 0, 1).is_err());
         Ok(())
     }
 }
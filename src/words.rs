@@ -1,6 +1,6 @@
 use crate::adjectives::ADJECTIVES;
+use crate::error::{Result, SeshError};
 use crate::nouns::NOUNS;
-use anyhow::{Result, anyhow};
 use rand::seq::IndexedRandom;
 
 /// Generates a random (docker-like) name made up of 0+ adjectives
@@ -19,7 +19,7 @@ pub fn rand_phrase(nadj: Option<u8>, sep: Option<char>) -> Result<String> {
     for _ in 0..nadj.unwrap_or(1) {
         let w = match ADJECTIVES.choose(&mut rng) {
             Some(s) => s.to_string(),
-            None => return Err(anyhow!("Failed to get an adjective")),
+            None => return Err(SeshError::Other("Failed to get an adjective".to_string())),
         };
         words.push(w);
     }
@@ -27,7 +27,7 @@ pub fn rand_phrase(nadj: Option<u8>, sep: Option<char>) -> Result<String> {
     // Add a final noun
     let w = match NOUNS.choose(&mut rng) {
         Some(s) => s.to_string(),
-        None => return Err(anyhow!("Failed to get a noun")),
+        None => return Err(SeshError::Other("Failed to get a noun".to_string())),
     };
     words.push(w);
 